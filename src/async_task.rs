@@ -0,0 +1,205 @@
+use std::time::Duration;
+use napi::{Env, JsError, Task};
+use napi::bindgen_prelude::Either3;
+use crate::declares::{CaptureInfo, ConfirmedOutcome, EndedOutcome, FailedOutcome, CaptureSet, Gestures, OutputFormat, PickedColor, SelectedImage, SelectionOutcome, WindowCapture, WindowSelector};
+use crate::error::CaptureError;
+use crate::screen_capture::ScreenCapture;
+use crate::screenshots_impl::ScreenshotsImpl;
+
+/// 在工作线程中执行截图, 失败时暂存 `CaptureError` 以便在 `reject` 中转换为带错误码的 js 错误
+fn compute_with<T>(error: &mut Option<CaptureError>, job: impl FnOnce() -> Result<T, CaptureError>) -> napi::Result<T> {
+    job().map_err(|err| {
+        let reason = err.to_string();
+        *error = Some(err);
+        napi::Error::from_reason(reason)
+    })
+}
+
+/// 转换为带错误码的 js 错误 (无暂存错误时原样返回)
+fn reject_with(env: Env, error: Option<CaptureError>, fallback: napi::Error) -> napi::Error {
+    match error {
+        Some(err) => {
+            let js_error = JsError::from(napi::Error::<&'static str>::from(err));
+            napi::Error::from(js_error.into_unknown(env))
+        }
+        None => fallback,
+    }
+}
+
+/// 异步截取所有屏幕
+pub struct CaptureTask {
+    pub output: Option<OutputFormat>,
+    pub error: Option<CaptureError>,
+}
+
+#[napi]
+impl Task for CaptureTask {
+    type Output = CaptureSet;
+    type JsValue = CaptureSet;
+
+    fn compute(&mut self) -> napi::Result<Self::Output> {
+        let output = self.output;
+        compute_with(&mut self.error, || ScreenCapture::capture(&ScreenshotsImpl {})?.encoded(output))
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+        Ok(output)
+    }
+
+    fn reject(&mut self, env: Env, err: napi::Error) -> napi::Result<Self::JsValue> {
+        Err(reject_with(env, self.error.take(), err))
+    }
+}
+
+/// 异步截取桌面上任意区域 (物理坐标)
+pub struct CaptureRegionTask {
+    pub xywh: (i32, i32, u32, u32),
+    pub output: Option<OutputFormat>,
+    pub error: Option<CaptureError>,
+}
+
+#[napi]
+impl Task for CaptureRegionTask {
+    type Output = CaptureInfo;
+    type JsValue = CaptureInfo;
+
+    fn compute(&mut self) -> napi::Result<Self::Output> {
+        let ((x, y, w, h), output) = (self.xywh, self.output);
+        compute_with(&mut self.error, || ScreenCapture::capture_region(&ScreenshotsImpl {}, x, y, w, h)?.encoded(output))
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+        Ok(output)
+    }
+
+    fn reject(&mut self, env: Env, err: napi::Error) -> napi::Result<Self::JsValue> {
+        Err(reject_with(env, self.error.take(), err))
+    }
+}
+
+/// 异步截取符合条件的窗口
+pub struct CaptureWindowTask {
+    pub selector: WindowSelector,
+    pub output: Option<OutputFormat>,
+    pub error: Option<CaptureError>,
+}
+
+#[napi]
+impl Task for CaptureWindowTask {
+    type Output = WindowCapture;
+    type JsValue = WindowCapture;
+
+    fn compute(&mut self) -> napi::Result<Self::Output> {
+        let (selector, output) = (&self.selector, self.output);
+        compute_with(&mut self.error, || ScreenCapture::capture_window(&ScreenshotsImpl {}, selector)?.encoded(output))
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+        Ok(output)
+    }
+
+    fn reject(&mut self, env: Env, err: napi::Error) -> napi::Result<Self::JsValue> {
+        Err(reject_with(env, self.error.take(), err))
+    }
+}
+
+/// 异步截取所有屏幕并交互式裁剪, 用户取消时为 `null`
+///
+/// 选择窗口在常驻的 UI 线程中创建, 工作线程等待其结束, 不阻塞 js 线程
+pub struct CaptureWithCropTask {
+    pub output: OutputFormat,
+    pub gestures: Gestures,
+    pub error: Option<CaptureError>,
+}
+
+#[napi]
+impl Task for CaptureWithCropTask {
+    type Output = Option<SelectedImage>;
+    type JsValue = Option<SelectedImage>;
+
+    fn compute(&mut self) -> napi::Result<Self::Output> {
+        let (output, gestures) = (self.output, self.gestures);
+        compute_with(&mut self.error, || {
+            // macOS 的窗口只能在主线程中创建
+            if cfg!(target_os = "macos") {
+                return Err(CaptureError::Window(String::from("interactive crop must run on the main thread on macOS")));
+            }
+
+            match ScreenCapture::capture_with_crop(&ScreenshotsImpl {}, output, gestures) {
+                Ok(selected) => Ok(Some(selected)),
+                Err(CaptureError::Cancelled) => Ok(None),
+                Err(err) => Err(err),
+            }
+        })
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+        Ok(output)
+    }
+
+    fn reject(&mut self, env: Env, err: napi::Error) -> napi::Result<Self::JsValue> {
+        Err(reject_with(env, self.error.take(), err))
+    }
+}
+
+/// 异步截取所有屏幕并交互式裁剪, 按 `kind` 区分选择结束的原因 (不会失败)
+pub struct CaptureWithOutcomeTask {
+    pub output: OutputFormat,
+    pub gestures: Gestures,
+    pub timeout: Option<Duration>,
+}
+
+#[napi]
+impl Task for CaptureWithOutcomeTask {
+    type Output = SelectionOutcome;
+    type JsValue = Either3<ConfirmedOutcome, EndedOutcome, FailedOutcome>;
+
+    fn compute(&mut self) -> napi::Result<Self::Output> {
+        // macOS 的窗口只能在主线程中创建
+        if cfg!(target_os = "macos") {
+            return Ok(SelectionOutcome::Error(CaptureError::Window(String::from("interactive crop must run on the main thread on macOS"))));
+        }
+
+        Ok(ScreenCapture::capture_with_outcome(&ScreenshotsImpl {}, self.output, self.gestures, self.timeout))
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+        Ok(output.into())
+    }
+}
+
+/// 异步交互式取色, 用户取消时为 `null`
+pub struct PickColorTask {
+    pub sample_size: u32,
+    pub error: Option<CaptureError>,
+}
+
+#[napi]
+impl Task for PickColorTask {
+    type Output = Option<PickedColor>;
+    type JsValue = Option<PickedColor>;
+
+    fn compute(&mut self) -> napi::Result<Self::Output> {
+        let sample_size = self.sample_size;
+        compute_with(&mut self.error, || {
+            // macOS 的窗口只能在主线程中创建
+            if cfg!(target_os = "macos") {
+                return Err(CaptureError::Window(String::from("interactive crop must run on the main thread on macOS")));
+            }
+
+            match ScreenCapture::pick_color(&ScreenshotsImpl {}, sample_size) {
+                Ok(picked) => Ok(Some(picked)),
+                Err(CaptureError::Cancelled) => Ok(None),
+                Err(err) => Err(err),
+            }
+        })
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> napi::Result<Self::JsValue> {
+        Ok(output)
+    }
+
+    fn reject(&mut self, env: Env, err: napi::Error) -> napi::Result<Self::JsValue> {
+        Err(reject_with(env, self.error.take(), err))
+    }
+}
@@ -1,3 +1,188 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use napi::bindgen_prelude::Either3;
+use serde::Deserialize;
+use crate::error::CaptureError;
+use crate::geometry::DesktopRect;
+use crate::utils::{encode_image, rgb_to_hsl};
+use crate::utils::redact::{RedactKind, Redaction, DEFAULT_STRENGTH, MIN_STRENGTH};
+
+/// 图片编码格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    /// 无损 webp
+    WebP,
+    Bmp,
+    Qoi,
+    Tiff,
+    /// 未编码的 rgba 阵列 (行优先)
+    RawRgba,
+    /// 未编码的 rgb 阵列 (行优先)
+    RawRgb,
+}
+
+impl ImageFormat {
+    /// 保存为文件时的扩展名
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+            ImageFormat::WebP => "webp",
+            ImageFormat::Bmp => "bmp",
+            ImageFormat::Qoi => "qoi",
+            ImageFormat::Tiff => "tiff",
+            ImageFormat::RawRgba => "rgba",
+            ImageFormat::RawRgb => "rgb",
+        }
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = CaptureError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "png" => Ok(ImageFormat::Png),
+            "jpeg" | "jpg" => Ok(ImageFormat::Jpeg),
+            "webp" => Ok(ImageFormat::WebP),
+            "bmp" => Ok(ImageFormat::Bmp),
+            "qoi" => Ok(ImageFormat::Qoi),
+            "tiff" | "tif" => Ok(ImageFormat::Tiff),
+            "rgba" => Ok(ImageFormat::RawRgba),
+            "rgb" => Ok(ImageFormat::RawRgb),
+            _ => Err(CaptureError::InvalidArgument(format!("unsupported image format '{name}'"))),
+        }
+    }
+}
+
+/// 输出格式及编码选项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OutputFormat {
+    pub format: ImageFormat,
+    /// jpeg 质量 (1 ~ 100), 其他格式忽略
+    pub quality: u8,
+    /// 是否丢弃 alpha 通道 (jpeg / rgb 总是丢弃)
+    pub drop_alpha: bool,
+    /// 原始阵列每行的字节数 (不小于 宽度 * 通道数, 行尾补 0), `None` 时紧密排列, 其他格式忽略
+    pub stride: Option<u32>,
+}
+
+impl Default for OutputFormat {
+    fn default() -> Self {
+        OutputFormat::from(ImageFormat::Png)
+    }
+}
+
+impl From<ImageFormat> for OutputFormat {
+    fn from(format: ImageFormat) -> Self {
+        OutputFormat { format, quality: 90, drop_alpha: false, stride: None }
+    }
+}
+
+impl OutputFormat {
+    /// 输出是否保留 alpha 通道
+    pub fn has_alpha(&self) -> bool {
+        !self.drop_alpha && !matches!(self.format, ImageFormat::Jpeg | ImageFormat::RawRgb)
+    }
+
+    /// 每个像素的通道数
+    pub fn channels(&self) -> u32 {
+        if self.has_alpha() { 4 } else { 3 }
+    }
+
+    /// 原始阵列每行的字节数
+    pub fn stride(&self, width: u32) -> u32 {
+        self.stride.unwrap_or(width * self.channels())
+    }
+
+    /// 输出为原始阵列时每行的字节数, 其他格式为 `None`
+    pub fn raw_stride(&self, width: u32) -> Option<u32> {
+        matches!(self.format, ImageFormat::RawRgba | ImageFormat::RawRgb).then(|| self.stride(width))
+    }
+}
+
+/// js 侧的输出格式及编码选项, 对应 `OutputFormat`
+#[derive(Debug, Clone)]
+#[napi(object, js_name = "OutputFormat")]
+pub struct OutputOptions {
+    /// 编码格式
+    #[napi(ts_type = "'png' | 'jpeg' | 'webp' | 'bmp' | 'qoi' | 'tiff' | 'rgba' | 'rgb'")]
+    pub format: String,
+    /// jpeg 质量 (1 ~ 100), 默认 90
+    pub quality: Option<u32>,
+    /// 是否丢弃 alpha 通道, 默认 false
+    pub drop_alpha: Option<bool>,
+    /// 原始阵列 ('rgba' / 'rgb') 每行的字节数, 默认紧密排列
+    pub stride: Option<u32>,
+}
+
+impl TryFrom<OutputOptions> for OutputFormat {
+    type Error = CaptureError;
+
+    fn try_from(options: OutputOptions) -> Result<Self, Self::Error> {
+        let quality = options.quality.unwrap_or(90);
+        if !(1..=100).contains(&quality) {
+            return Err(CaptureError::InvalidArgument(format!("jpeg quality must be within 1 ~ 100, got {quality}")));
+        }
+
+        Ok(OutputFormat {
+            format: options.format.parse()?,
+            quality: quality as u8,
+            drop_alpha: options.drop_alpha.unwrap_or(false),
+            stride: options.stride,
+        })
+    }
+}
+
+/// 选择窗口中鼠标 / 按键的行为, 默认全部启用
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct Gestures {
+    /// 右键: 清除选区, 没有选区时退出
+    pub right_click_clears: bool,
+    /// 在选区内双击: 确认选区 (同 'Enter')
+    pub double_click_confirms: bool,
+    /// 'ESC': 有选区时先清除, 再次按下时退出 (关闭时直接退出)
+    pub escape_clears_first: bool,
+    /// 按住中键拖拽: 平移选区 (未选择标注工具时)
+    pub middle_drag_pans: bool,
+}
+
+impl Default for Gestures {
+    fn default() -> Self {
+        Gestures { right_click_clears: true, double_click_confirms: true, escape_clears_first: true, middle_drag_pans: true }
+    }
+}
+
+/// js 侧的鼠标 / 按键行为, 对应 `Gestures`
+#[derive(Debug, Clone, Copy)]
+#[napi(object, js_name = "Gestures")]
+pub struct GestureOptions {
+    /// 右键清除选区, 没有选区时退出, 默认 true
+    pub right_click_clears: Option<bool>,
+    /// 在选区内双击确认选区, 默认 true
+    pub double_click_confirms: Option<bool>,
+    /// 'ESC' 有选区时先清除, 再次按下时退出, 默认 true
+    pub escape_clears_first: Option<bool>,
+    /// 按住中键拖拽平移选区, 默认 true
+    pub middle_drag_pans: Option<bool>,
+}
+
+impl From<GestureOptions> for Gestures {
+    fn from(options: GestureOptions) -> Self {
+        let defaults = Gestures::default();
+
+        Gestures {
+            right_click_clears: options.right_click_clears.unwrap_or(defaults.right_click_clears),
+            double_click_confirms: options.double_click_confirms.unwrap_or(defaults.double_click_confirms),
+            escape_clears_first: options.escape_clears_first.unwrap_or(defaults.escape_clears_first),
+            middle_drag_pans: options.middle_drag_pans.unwrap_or(defaults.middle_drag_pans),
+        }
+    }
+}
+
 /// 屏幕捕获信息
 #[derive(Debug, Clone)]
 #[napi(object)]
@@ -14,19 +199,458 @@ pub struct CaptureInfo {
     pub physical_width: u32,
     // 图片高度
     pub physical_height: u32,
-    // 图片的 buffer (可直接使用)
-    pub buffer: Vec<u8>,
-    // 原始 rgba 阵列 (用于创建纹理)
+    // 编码后图片的 buffer (仅在请求输出格式时存在)
+    pub buffer: Option<Vec<u8>>,
+    // 以原始阵列 ('rgba' / 'rgb') 输出时 `buffer` 每行的字节数
+    pub stride: Option<u32>,
+    // 原始 rgba 阵列 (行优先)
     pub rgba: Vec<u8>,
 }
 
+impl CaptureInfo {
+    /// 屏幕区域 (桌面物理坐标)
+    pub fn physical_rect(&self) -> DesktopRect {
+        DesktopRect::new(self.physical_x, self.physical_y, self.physical_width, self.physical_height)
+    }
+
+    /// 按指定格式编码原始 rgba 阵列
+    pub fn encode(&self, output: OutputFormat) -> Result<Vec<u8>, CaptureError> {
+        encode_image(&self.rgba, self.physical_width, self.physical_height, output)
+    }
+
+    /// 按需编码并填充 `buffer` (`output` 为 `None` 时不编码)
+    pub fn encoded(mut self, output: Option<OutputFormat>) -> Result<CaptureInfo, CaptureError> {
+        if let Some(output) = output {
+            self.buffer = Some(self.encode(output)?);
+            self.stride = output.raw_stride(self.physical_width);
+        }
+        Ok(self)
+    }
+}
+
+/// 屏幕信息 (不含图像)
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[napi(object)]
+pub struct DisplayDescriptor {
+    // 屏幕 id
+    pub screen_id: u32,
+    // 缩放比例
+    pub scale_factor: f64,
+    // 屏幕左上角 x (物理坐标)
+    pub physical_x: i32,
+    // 屏幕左上角 y (物理坐标)
+    pub physical_y: i32,
+    // 屏幕宽度 (物理坐标, 已考虑旋转)
+    pub physical_width: u32,
+    // 屏幕高度 (物理坐标, 已考虑旋转)
+    pub physical_height: u32,
+    // 屏幕宽度 (逻辑坐标, 已考虑旋转)
+    pub logical_width: u32,
+    // 屏幕高度 (逻辑坐标, 已考虑旋转)
+    pub logical_height: u32,
+    // 顺时针旋转角度: 0 / 90 / 180 / 270
+    pub rotation: f64,
+    // 刷新率 (Hz), 未知时为 0
+    pub refresh_rate: f64,
+    // 是否为主屏幕
+    pub is_primary: bool,
+}
+
+impl DisplayDescriptor {
+    /// 物理坐标系下的 xywh
+    pub fn xywh(&self) -> (i32, i32, i32, i32) {
+        (self.physical_x, self.physical_y, self.physical_width as i32, self.physical_height as i32)
+    }
+
+    /// 是否旋转了 90 / 270 度
+    pub fn is_portrait(&self) -> bool {
+        matches!(self.rotation.round() as i64, 90 | 270)
+    }
+
+    /// 点 (物理坐标) 是否在屏幕内
+    pub fn contains(&self, x: i32, y: i32) -> bool {
+        let (sx, sy, sw, sh) = self.xywh();
+        x >= sx && x < sx + sw && y >= sy && y < sy + sh
+    }
+}
+
+/// 截取失败的屏幕
+#[derive(Debug, Clone, PartialEq)]
+#[napi(object)]
+pub struct CaptureFailure {
+    // 所处屏幕的 id
+    pub screen_id: u32,
+    // 失败原因
+    pub reason: String,
+}
+
+/// 所有屏幕的截取结果 (部分屏幕失败时仍返回成功的部分)
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct CaptureSet {
+    // 截取成功的屏幕
+    pub captures: Vec<CaptureInfo>,
+    // 截取失败的屏幕
+    pub failures: Vec<CaptureFailure>,
+}
+
+impl CaptureSet {
+    /// 按需编码所有截图 (`output` 为 `None` 时不编码)
+    pub fn encoded(self, output: Option<OutputFormat>) -> Result<CaptureSet, CaptureError> {
+        let CaptureSet { captures, failures } = self;
+        let captures = captures
+            .into_iter()
+            .map(|capture| capture.encoded(output))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(CaptureSet { captures, failures })
+    }
+}
+
+/// 选区与某个屏幕相交的部分
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct SelectedScreen {
+    // 屏幕 id
+    pub screen_id: u32,
+    // 屏幕的 scale_factor
+    pub scale_factor: f64,
+    /// 相交部分 (桌面物理坐标) \[x, y, width, height\]
+    #[napi(ts_type = "[x: number, y: number, width: number, height: number]")]
+    pub physical_rect: Vec<f64>,
+    /// 相交部分 (该屏幕的逻辑坐标, 以屏幕左上角为原点) \[x, y, width, height\]
+    #[napi(ts_type = "[x: number, y: number, width: number, height: number]")]
+    pub logical_rect: Vec<f64>,
+}
+
+/// 顶层窗口信息 (含窗口管理器的边框)
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[napi(object)]
+pub struct WindowInfo {
+    // 窗口 id (X11 为 XID)
+    pub window_id: u32,
+    // 窗口标题
+    #[serde(default)]
+    pub title: String,
+    // 窗口类名 (X11 为 `WM_CLASS` 的 class 部分)
+    #[serde(default)]
+    pub class: String,
+    // 窗口类型 (`_NET_WM_WINDOW_TYPE` 去掉前缀后的小写名称, 如 "normal" / "dock" / "desktop"), 未知时为空字符串
+    #[serde(default)]
+    pub window_type: String,
+    // 所属进程的 pid, 未知时为空
+    #[serde(default)]
+    pub pid: Option<u32>,
+    // 窗口是否可见 (未最小化等)
+    #[serde(default = "visible_by_default")]
+    pub visible: bool,
+    // 窗口左上角 x (桌面物理坐标)
+    pub physical_x: i32,
+    // 窗口左上角 y (桌面物理坐标)
+    pub physical_y: i32,
+    // 窗口宽度 (物理坐标)
+    pub physical_width: u32,
+    // 窗口高度 (物理坐标)
+    pub physical_height: u32,
+}
+
+/// 回放脚本中的窗口默认可见
+fn visible_by_default() -> bool {
+    true
+}
+
+impl WindowInfo {
+    /// 窗口区域 (桌面物理坐标)
+    pub fn physical_rect(&self) -> DesktopRect {
+        DesktopRect::new(self.physical_x, self.physical_y, self.physical_width, self.physical_height)
+    }
+
+    /// 是否为停靠栏 / 桌面等桌面组件 (不视为遮挡其它窗口)
+    pub fn is_desktop_component(&self) -> bool {
+        matches!(self.window_type.as_str(), "dock" | "desktop")
+    }
+}
+
+/// 查找窗口的条件, 所有给出的条件都满足时匹配
+#[derive(Debug, Clone, Default, PartialEq)]
+#[napi(object)]
+pub struct WindowSelector {
+    // 窗口 id
+    pub window_id: Option<u32>,
+    // 窗口标题包含的文字 (不区分大小写)
+    pub title: Option<String>,
+    // 窗口类名 (不区分大小写)
+    pub class: Option<String>,
+    // 所属进程的 pid
+    pub pid: Option<u32>,
+}
+
+impl WindowSelector {
+    /// 是否没有任何条件
+    pub fn is_empty(&self) -> bool {
+        self.window_id.is_none() && self.title.is_none() && self.class.is_none() && self.pid.is_none()
+    }
+
+    pub fn matches(&self, window: &WindowInfo) -> bool {
+        self.window_id.is_none_or(|id| window.window_id == id)
+            && self.title.as_ref().is_none_or(|title| window.title.to_lowercase().contains(&title.to_lowercase()))
+            && self.class.as_ref().is_none_or(|class| window.class.eq_ignore_ascii_case(class))
+            && self.pid.is_none_or(|pid| window.pid == Some(pid))
+    }
+}
+
+impl Display for WindowSelector {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let mut conditions = vec![];
+        if let Some(id) = self.window_id {
+            conditions.push(format!("id = {id}"));
+        }
+        if let Some(title) = &self.title {
+            conditions.push(format!("title contains '{title}'"));
+        }
+        if let Some(class) = &self.class {
+            conditions.push(format!("class = '{class}'"));
+        }
+        if let Some(pid) = self.pid {
+            conditions.push(format!("pid = {pid}"));
+        }
+        write!(f, "{}", conditions.join(", "))
+    }
+}
+
+/// 单个窗口的截图
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct WindowCapture {
+    // 截取的窗口
+    pub window: WindowInfo,
+    // 窗口图像, screen_id / scale_factor 为窗口所在 (相交面积最大) 的屏幕
+    pub capture: CaptureInfo,
+    // 窗口被遮挡或超出屏幕时直接读取窗口内容 (X11 为 XComposite), 此时不含窗口管理器的边框
+    pub composited: bool,
+}
+
+impl WindowCapture {
+    /// 按需编码窗口图像 (`output` 为 `None` 时不编码)
+    pub fn encoded(self, output: Option<OutputFormat>) -> Result<WindowCapture, CaptureError> {
+        let WindowCapture { window, capture, composited } = self;
+        Ok(WindowCapture { window, capture: capture.encoded(output)?, composited })
+    }
+}
+
 /// 选中的区域
 #[derive(Debug, Clone)]
 #[napi(object)]
 pub struct SelectedImage {
-    /// \[xmin, ymin, xmax, ymax\]
+    /// 桌面物理坐标 \[xmin, ymin, xmax, ymax\]
     #[napi(ts_type = "[xmin: number, ymin: number, xmax: number, ymax: number]")]
     pub p1p2: Vec<f64>,
+    // 选区跨越的各屏幕
+    pub screens: Vec<SelectedScreen>,
+    // 单击选中整个窗口时为该窗口 (选区为窗口在屏幕内的部分)
+    pub window: Option<WindowInfo>,
+    // 单击或按数字键选中整个屏幕时为该屏幕的 id (选区为屏幕在选择窗口内的部分)
+    pub screen_id: Option<u32>,
     // 图片的 buffer (可直接使用)
     pub buffer: Vec<u8>,
-}
\ No newline at end of file
+    // 以原始阵列 ('rgba' / 'rgb') 输出时 `buffer` 每行的字节数
+    pub stride: Option<u32>,
+    // 截取失败的屏幕 (不参与裁剪)
+    pub failures: Vec<CaptureFailure>,
+}
+
+/// 交互式选择的结果
+#[derive(Debug, Clone)]
+pub enum SelectionOutcome {
+    /// 确认了有效的选区
+    Confirmed(SelectedImage),
+    /// 用户按键 / 右键取消, 或确认时没有有效的选区
+    Cancelled,
+    /// 选择窗口被系统关闭
+    Closed,
+    /// 超过了选择的时限
+    TimedOut,
+    /// 截图 / 创建窗口 / 编码等失败
+    Error(CaptureError),
+}
+
+impl SelectionOutcome {
+    /// js 侧的 `kind`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SelectionOutcome::Confirmed(_) => "confirmed",
+            SelectionOutcome::Cancelled => "cancelled",
+            SelectionOutcome::Closed => "closed",
+            SelectionOutcome::TimedOut => "timed_out",
+            SelectionOutcome::Error(_) => "error",
+        }
+    }
+
+    /// 记录截取失败的屏幕 (仅 `Confirmed`)
+    pub fn with_failures(self, failures: Vec<CaptureFailure>) -> SelectionOutcome {
+        match self {
+            SelectionOutcome::Confirmed(selected) => SelectionOutcome::Confirmed(SelectedImage { failures, ..selected }),
+            outcome => outcome,
+        }
+    }
+
+    /// 转换为选区, 未确认的结果都视为 `Cancelled`
+    pub fn into_result(self) -> Result<SelectedImage, CaptureError> {
+        match self {
+            SelectionOutcome::Confirmed(selected) => Ok(selected),
+            SelectionOutcome::Error(err) => Err(err),
+            _ => Err(CaptureError::Cancelled),
+        }
+    }
+}
+
+impl From<Result<SelectionOutcome, CaptureError>> for SelectionOutcome {
+    fn from(result: Result<SelectionOutcome, CaptureError>) -> Self {
+        result.unwrap_or_else(SelectionOutcome::Error)
+    }
+}
+
+/// js 侧确认的选区 (`kind` 为 'confirmed')
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct ConfirmedOutcome {
+    #[napi(ts_type = "'confirmed'")]
+    pub kind: String,
+    pub selected: SelectedImage,
+}
+
+/// js 侧未确认选区而结束的原因
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct EndedOutcome {
+    #[napi(ts_type = "'cancelled' | 'closed' | 'timed_out'")]
+    pub kind: String,
+}
+
+/// js 侧选择失败的错误 (`kind` 为 'error')
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct FailedOutcome {
+    #[napi(ts_type = "'error'")]
+    pub kind: String,
+    /// 错误码, 同抛出的错误的 `code`
+    pub code: String,
+    /// 错误信息
+    pub message: String,
+}
+
+/// js 侧的交互式选择结果, 即 `ConfirmedOutcome | EndedOutcome | FailedOutcome`, 按 `kind` 区分
+impl From<SelectionOutcome> for Either3<ConfirmedOutcome, EndedOutcome, FailedOutcome> {
+    fn from(outcome: SelectionOutcome) -> Self {
+        let kind = String::from(outcome.kind());
+
+        match outcome {
+            SelectionOutcome::Confirmed(selected) => Either3::A(ConfirmedOutcome { kind, selected }),
+            SelectionOutcome::Error(err) => Either3::C(FailedOutcome { kind, code: String::from(err.code()), message: err.to_string() }),
+            _ => Either3::B(EndedOutcome { kind }),
+        }
+    }
+}
+
+/// 取色的结果
+#[derive(Debug, Clone, PartialEq)]
+#[napi(object)]
+pub struct PickedColor {
+    // 取色点 x (桌面物理坐标)
+    pub x: i32,
+    // 取色点 y (桌面物理坐标)
+    pub y: i32,
+    // 采样区域的边长 (像素), 大于 1 时为以取色点为中心的区域平均色
+    pub sample_size: u32,
+    // 十六进制颜色, 如 '#1E90FF'
+    pub hex: String,
+    /// \[r, g, b\] (0 ~ 255)
+    #[napi(ts_type = "[r: number, g: number, b: number]")]
+    pub rgb: Vec<u32>,
+    /// \[h (0 ~ 360), s (0 ~ 100), l (0 ~ 100)\]
+    #[napi(ts_type = "[h: number, s: number, l: number]")]
+    pub hsl: Vec<f64>,
+    // 截取失败的屏幕 (不参与取色)
+    pub failures: Vec<CaptureFailure>,
+}
+
+impl PickedColor {
+    pub fn new(x: i32, y: i32, sample_size: u32, rgb: [u8; 3]) -> PickedColor {
+        let [r, g, b] = rgb;
+
+        PickedColor {
+            x,
+            y,
+            sample_size,
+            hex: format!("#{r:02X}{g:02X}{b:02X}"),
+            rgb: vec![r as u32, g as u32, b as u32],
+            hsl: rgb_to_hsl(rgb).to_vec(),
+            failures: vec![],
+        }
+    }
+}
+
+/// js 侧的打码区域, 对应 `Redaction`
+#[derive(Debug, Clone)]
+#[napi(object)]
+pub struct RedactRegion {
+    /// 打码的方式
+    #[napi(ts_type = "'pixelate' | 'blur' | 'fill'")]
+    pub kind: String,
+    /// 区域左上角 x (桌面物理坐标)
+    pub x: i32,
+    /// 区域左上角 y (桌面物理坐标)
+    pub y: i32,
+    /// 区域宽度 (物理坐标)
+    pub width: u32,
+    /// 区域高度 (物理坐标)
+    pub height: u32,
+    /// 马赛克方块的边长 / 模糊的半径 (像素), 默认 12, 不小于 4
+    pub strength: Option<u32>,
+    /// 填充的颜色 \[r, g, b\] (0 ~ 255), 默认黑色
+    #[napi(ts_type = "[r: number, g: number, b: number]")]
+    pub color: Option<Vec<u32>>,
+}
+
+impl TryFrom<RedactRegion> for Redaction {
+    type Error = CaptureError;
+
+    fn try_from(region: RedactRegion) -> Result<Self, Self::Error> {
+        let kind: RedactKind = region.kind.parse()?;
+        let strength = region.strength.unwrap_or(DEFAULT_STRENGTH);
+        if kind != RedactKind::Fill && strength < MIN_STRENGTH {
+            return Err(CaptureError::InvalidArgument(format!("strength must be at least {MIN_STRENGTH}, got {strength}")));
+        }
+        let color = match region.color.as_deref() {
+            None => [0, 0, 0],
+            Some(&[r, g, b]) if r <= 255 && g <= 255 && b <= 255 => [r as u8, g as u8, b as u8],
+            Some(color) => return Err(CaptureError::InvalidArgument(format!("color must be [r, g, b] within 0 ~ 255, got {color:?}"))),
+        };
+
+        Ok(Redaction {
+            kind,
+            rect: DesktopRect::new(region.x, region.y, region.width, region.height),
+            strength,
+            color,
+        })
+    }
+}
+
+/// 截图后端 (产出各屏幕的 `CaptureInfo`)
+pub trait CaptureBackend {
+    /// 截取所有屏幕
+    ///
+    /// 部分屏幕失败时记录在 `CaptureSet.failures` 中, 全部失败时返回错误
+    fn capture_all(&self) -> Result<CaptureSet, CaptureError>;
+
+    /// 获取所有屏幕的信息 (不截图)
+    fn displays(&self) -> Result<Vec<DisplayDescriptor>, CaptureError>;
+
+    /// 截取单个屏幕
+    fn capture_screen(&self, screen: &DisplayDescriptor) -> Result<CaptureInfo, CaptureError>;
+
+    /// 截取单个屏幕内的区域
+    ///
+    /// `xywh` 为相对屏幕左上角的物理坐标, 且已约束在屏幕范围内
+    fn capture_area(&self, screen: &DisplayDescriptor, xywh: (i32, i32, u32, u32)) -> Result<CaptureInfo, CaptureError>;
+}
@@ -0,0 +1,63 @@
+use std::fmt::{Display, Formatter};
+
+/// 截图流程中可能出现的错误
+#[derive(Debug, Clone, PartialEq)]
+pub enum CaptureError {
+    /// 没有可用的屏幕 (获取屏幕信息失败或屏幕数量为 0)
+    NoDisplays(String),
+    /// 单个屏幕截取失败
+    Screen { screen_id: u32, reason: String },
+    /// 窗口 / GL 资源创建失败
+    Window(String),
+    /// 图像编码失败
+    Encode(String),
+    /// 用户取消了选择
+    Cancelled,
+    /// 已有交互式选择窗口处于打开状态
+    Busy,
+    /// 获取窗口列表失败 (或当前平台不支持)
+    WindowList(String),
+    /// 没有符合条件的窗口
+    WindowNotFound(String),
+    /// 读取窗口内容失败
+    WindowCapture { window_id: u32, reason: String },
+    /// 调用方传入的参数无效 (如区域过大、打码强度过小)
+    InvalidArgument(String),
+}
+
+impl CaptureError {
+    /// 暴露给 js 的错误码
+    pub fn code(&self) -> &'static str {
+        match self {
+            CaptureError::NoDisplays(_) => "NO_DISPLAYS",
+            CaptureError::Screen { .. } => "SCREEN_CAPTURE_FAILED",
+            CaptureError::Window(_) => "WINDOW_CREATION_FAILED",
+            CaptureError::Encode(_) => "ENCODING_FAILED",
+            CaptureError::Cancelled => "CANCELLED",
+            CaptureError::Busy => "BUSY",
+            CaptureError::WindowList(_) => "WINDOW_LIST_FAILED",
+            CaptureError::WindowNotFound(_) => "WINDOW_NOT_FOUND",
+            CaptureError::WindowCapture { .. } => "WINDOW_CAPTURE_FAILED",
+            CaptureError::InvalidArgument(_) => "INVALID_ARGUMENT",
+        }
+    }
+}
+
+impl Display for CaptureError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CaptureError::NoDisplays(reason) => write!(f, "No display available: {reason}"),
+            CaptureError::Screen { screen_id, reason } => write!(f, "Failed to capture screen {screen_id}: {reason}"),
+            CaptureError::Window(reason) => write!(f, "Failed to create window: {reason}"),
+            CaptureError::Encode(reason) => write!(f, "Failed to encode image: {reason}"),
+            CaptureError::Cancelled => write!(f, "Selection was cancelled"),
+            CaptureError::Busy => write!(f, "Another selection window is already open"),
+            CaptureError::WindowList(reason) => write!(f, "Failed to list windows: {reason}"),
+            CaptureError::WindowNotFound(selector) => write!(f, "No window matches: {selector}"),
+            CaptureError::WindowCapture { window_id, reason } => write!(f, "Failed to capture window {window_id}: {reason}"),
+            CaptureError::InvalidArgument(reason) => write!(f, "Invalid argument: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for CaptureError {}
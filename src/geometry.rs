@@ -0,0 +1,187 @@
+//! 坐标系
+//!
+//! - 桌面物理坐标 (`DesktopPoint` / `DesktopRect`): 所有屏幕共享的全局坐标, 即 `CaptureInfo.physical_x` 等使用的坐标
+//! - 窗口物理坐标 (`WindowPoint` / `WindowRect`): 以覆盖所有屏幕的选择窗口左上角为原点, 即鼠标事件和 imgui 绘制使用的坐标
+//! - 屏幕逻辑坐标 (`LogicalRect`): 以某个屏幕左上角为原点, 物理坐标 / 该屏幕的 scale_factor
+
+use crate::utils::intersect;
+
+/// 桌面物理坐标系下的点
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DesktopPoint {
+    pub x: i32,
+    pub y: i32,
+}
+
+impl DesktopPoint {
+    pub fn new(x: i32, y: i32) -> DesktopPoint {
+        DesktopPoint { x, y }
+    }
+
+    /// 转换为窗口物理坐标 (`origin` 为窗口左上角的桌面坐标)
+    pub fn to_window(self, origin: DesktopPoint) -> WindowPoint {
+        WindowPoint::new((self.x - origin.x) as f32, (self.y - origin.y) as f32)
+    }
+}
+
+/// 桌面物理坐标系下的矩形
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DesktopRect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl DesktopRect {
+    pub fn new(x: i32, y: i32, width: u32, height: u32) -> DesktopRect {
+        DesktopRect { x, y, width, height }
+    }
+
+    /// 左上角
+    pub fn origin(&self) -> DesktopPoint {
+        DesktopPoint::new(self.x, self.y)
+    }
+
+    pub fn xywh(&self) -> (i32, i32, u32, u32) {
+        (self.x, self.y, self.width, self.height)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.width == 0 || self.height == 0
+    }
+
+    /// 点是否在矩形内 (不含右 / 下边界)
+    pub fn contains(&self, point: DesktopPoint) -> bool {
+        point.x >= self.x && point.x < self.x + self.width as i32 && point.y >= self.y && point.y < self.y + self.height as i32
+    }
+
+    /// 与另一个矩形的交集
+    pub fn intersect(&self, other: &DesktopRect) -> Option<DesktopRect> {
+        intersect(
+            (self.x, self.y, self.width as i32, self.height as i32),
+            (other.x, other.y, other.width as i32, other.height as i32),
+        ).map(|(x, y, w, h)| DesktopRect::new(x, y, w as u32, h as u32))
+    }
+
+    /// 转换为窗口物理坐标 (`origin` 为窗口左上角的桌面坐标)
+    pub fn to_window(self, origin: DesktopPoint) -> WindowRect {
+        let min = self.origin().to_window(origin);
+        let max = DesktopPoint::new(self.x + self.width as i32, self.y + self.height as i32).to_window(origin);
+
+        WindowRect { min, max }
+    }
+
+    /// 转换为某个屏幕的逻辑坐标 (`screen` 为该屏幕的桌面物理区域)
+    pub fn to_logical(self, screen: &DesktopRect, scale_factor: f64) -> LogicalRect {
+        LogicalRect {
+            x: (self.x - screen.x) as f64 / scale_factor,
+            y: (self.y - screen.y) as f64 / scale_factor,
+            width: self.width as f64 / scale_factor,
+            height: self.height as f64 / scale_factor,
+        }
+    }
+}
+
+/// 窗口物理坐标系下的点
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowPoint {
+    pub x: f32,
+    pub y: f32,
+}
+
+impl WindowPoint {
+    pub fn new(x: f32, y: f32) -> WindowPoint {
+        WindowPoint { x, y }
+    }
+
+    /// 转换为桌面物理坐标 (`origin` 为窗口左上角的桌面坐标)
+    pub fn to_desktop(self, origin: DesktopPoint) -> DesktopPoint {
+        DesktopPoint::new(origin.x + self.x.round() as i32, origin.y + self.y.round() as i32)
+    }
+
+    /// imgui 绘制使用的坐标
+    pub fn to_array(self) -> [f32; 2] {
+        [self.x, self.y]
+    }
+}
+
+/// 窗口物理坐标系下的矩形, 即: 左上点 + 右下点
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowRect {
+    pub min: WindowPoint,
+    pub max: WindowPoint,
+}
+
+impl WindowRect {
+    /// 给定任意两点计算其包围的矩形
+    pub fn from_points(p1: WindowPoint, p2: WindowPoint) -> WindowRect {
+        WindowRect {
+            min: WindowPoint::new(p1.x.min(p2.x), p1.y.min(p2.y)),
+            max: WindowPoint::new(p1.x.max(p2.x), p1.y.max(p2.y)),
+        }
+    }
+
+    /// 点是否在矩形内 (含边界)
+    pub fn contains(&self, point: WindowPoint) -> bool {
+        point.x >= self.min.x && point.x <= self.max.x && point.y >= self.min.y && point.y <= self.max.y
+    }
+
+    /// 平移
+    pub fn translate(self, dx: f32, dy: f32) -> WindowRect {
+        WindowRect {
+            min: WindowPoint::new(self.min.x + dx, self.min.y + dy),
+            max: WindowPoint::new(self.max.x + dx, self.max.y + dy),
+        }
+    }
+
+    /// 转换为桌面物理坐标 (`origin` 为窗口左上角的桌面坐标)
+    pub fn to_desktop(self, origin: DesktopPoint) -> DesktopRect {
+        let min = self.min.to_desktop(origin);
+        let max = self.max.to_desktop(origin);
+
+        DesktopRect::new(min.x, min.y, (max.x - min.x) as u32, (max.y - min.y) as u32)
+    }
+}
+
+/// 屏幕逻辑坐标系下的矩形
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LogicalRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+#[cfg(test)]
+mod unit_test {
+    use crate::geometry::{DesktopPoint, DesktopRect, LogicalRect, WindowPoint, WindowRect};
+
+    #[test]
+    fn window_desktop_round_trip() {
+        // 副屏位于主屏左侧, 窗口左上角为 (-1280, -200)
+        let origin = DesktopPoint::new(-1280, -200);
+
+        assert_eq!(WindowPoint::new(1280.0, 200.0).to_desktop(origin), DesktopPoint::new(0, 0));
+        assert_eq!(DesktopPoint::new(0, 0).to_window(origin), WindowPoint::new(1280.0, 200.0));
+
+        let rect = WindowRect::from_points(WindowPoint::new(1300.0, 500.0), WindowPoint::new(1200.0, 300.0));
+        assert_eq!(rect.to_desktop(origin), DesktopRect::new(-80, 100, 100, 200));
+        assert_eq!(rect.to_desktop(origin).to_window(origin), rect);
+    }
+
+    #[test]
+    fn logical_of_mixed_dpi_screens() {
+        let left = DesktopRect::new(0, 0, 1920, 1080);
+        let right = DesktopRect::new(1920, 0, 2880, 1620);
+        let selection = DesktopRect::new(1820, 100, 400, 300);
+
+        let on_left = selection.intersect(&left).unwrap();
+        let on_right = selection.intersect(&right).unwrap();
+
+        assert_eq!(on_left, DesktopRect::new(1820, 100, 100, 300));
+        assert_eq!(on_right, DesktopRect::new(1920, 100, 300, 300));
+        assert_eq!(on_left.to_logical(&left, 1.0), LogicalRect { x: 1820.0, y: 100.0, width: 100.0, height: 300.0 });
+        assert_eq!(on_right.to_logical(&right, 1.5), LogicalRect { x: 0.0, y: 100.0 / 1.5, width: 200.0, height: 200.0 });
+    }
+}
@@ -0,0 +1,1776 @@
+use std::time::{Duration, Instant};
+use ab_glyph::FontArc;
+use glium::glutin::dpi::PhysicalPosition;
+use glium::glutin::event::{ElementState, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent};
+use serde::Deserialize;
+use crate::declares::{Gestures, WindowInfo};
+use crate::geometry::{DesktopPoint, DesktopRect, WindowPoint, WindowRect};
+use crate::imgui_impl::prefab::SelectedArea;
+use crate::imgui_impl::toolbar::{default_style, font_size, highlight_width, marker_radius, redact_strength, toolbar_layout, Tool, ToolbarItem, HIGHLIGHT_ALPHA, PALETTE, STROKE_WIDTHS};
+use crate::utils::annotate::{Annotation, AnnotationStyle, Highlight, Mark, StepMarker};
+use crate::utils::redact::Redaction;
+use crate::utils::text::{contrast_background, TextLabel};
+use crate::utils::detect::EdgeMap;
+
+/// 鼠标按键
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Button {
+    Left,
+    Right,
+    Middle,
+}
+
+/// 选择过程中响应的按键
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Key {
+    Escape,
+    Enter,
+    Left,
+    Right,
+    Up,
+    Down,
+    Tab,
+    Space,
+    Backspace,
+    /// 数字键 1 ~ 9, 如: `{ "digit": 2 }`
+    Digit(u8),
+}
+
+impl Key {
+    /// 方向键对应的单位位移
+    fn direction(&self) -> Option<(f32, f32)> {
+        match self {
+            Key::Left => Some((-1.0, 0.0)),
+            Key::Right => Some((1.0, 0.0)),
+            Key::Up => Some((0.0, -1.0)),
+            Key::Down => Some((0.0, 1.0)),
+            _ => None,
+        }
+    }
+}
+
+/// 与平台无关的输入事件 (坐标为窗口物理坐标)
+///
+/// 可由 winit 事件转换而来, 也可从 json 脚本中读取, 例如: `{ "type": "cursor_moved", "x": 10, "y": 20 }`
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum InputEvent {
+    /// 鼠标移动
+    CursorMoved { x: f64, y: f64 },
+    /// 鼠标按下 / 松开
+    Button { button: Button, pressed: bool },
+    /// 按键按下 / 松开
+    Key { key: Key, pressed: bool },
+    /// 鼠标左键双击 (代替第二次按下), 由 `ClickTracker` 识别
+    DoubleClick,
+    /// 输入的字符 (编辑文字时), 如: `{ "type": "character", "ch": "截" }`
+    Character { ch: char },
+    /// 修饰键状态变化
+    Modifiers {
+        #[serde(default)]
+        shift: bool,
+        #[serde(default)]
+        alt: bool,
+        #[serde(default)]
+        ctrl: bool,
+    },
+    /// 窗口被关闭
+    CloseRequested,
+    /// 超过了选择的时限 (由选择窗口发送)
+    TimedOut,
+}
+
+impl InputEvent {
+    /// 转换 winit 窗口事件, 不关心的事件为 `None`
+    pub fn from_window_event(event: &WindowEvent) -> Option<InputEvent> {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => Some(InputEvent::CursorMoved { x: position.x, y: position.y }),
+            WindowEvent::MouseInput { button, state, .. } => {
+                let button = match button {
+                    MouseButton::Left => Button::Left,
+                    MouseButton::Right => Button::Right,
+                    MouseButton::Middle => Button::Middle,
+                    MouseButton::Other(_) => return None,
+                };
+                Some(InputEvent::Button { button, pressed: *state == ElementState::Pressed })
+            }
+            WindowEvent::KeyboardInput { input: KeyboardInput { state, virtual_keycode: Some(keycode), .. }, .. } => {
+                let key = match keycode {
+                    VirtualKeyCode::Escape => Key::Escape,
+                    VirtualKeyCode::Return | VirtualKeyCode::NumpadEnter => Key::Enter,
+                    VirtualKeyCode::Left => Key::Left,
+                    VirtualKeyCode::Right => Key::Right,
+                    VirtualKeyCode::Up => Key::Up,
+                    VirtualKeyCode::Down => Key::Down,
+                    VirtualKeyCode::Tab => Key::Tab,
+                    VirtualKeyCode::Space => Key::Space,
+                    VirtualKeyCode::Back => Key::Backspace,
+                    VirtualKeyCode::Key1 | VirtualKeyCode::Numpad1 => Key::Digit(1),
+                    VirtualKeyCode::Key2 | VirtualKeyCode::Numpad2 => Key::Digit(2),
+                    VirtualKeyCode::Key3 | VirtualKeyCode::Numpad3 => Key::Digit(3),
+                    VirtualKeyCode::Key4 | VirtualKeyCode::Numpad4 => Key::Digit(4),
+                    VirtualKeyCode::Key5 | VirtualKeyCode::Numpad5 => Key::Digit(5),
+                    VirtualKeyCode::Key6 | VirtualKeyCode::Numpad6 => Key::Digit(6),
+                    VirtualKeyCode::Key7 | VirtualKeyCode::Numpad7 => Key::Digit(7),
+                    VirtualKeyCode::Key8 | VirtualKeyCode::Numpad8 => Key::Digit(8),
+                    VirtualKeyCode::Key9 | VirtualKeyCode::Numpad9 => Key::Digit(9),
+                    _ => return None,
+                };
+                Some(InputEvent::Key { key, pressed: *state == ElementState::Pressed })
+            }
+            WindowEvent::ReceivedCharacter(ch) => Some(InputEvent::Character { ch: *ch }),
+            WindowEvent::ModifiersChanged(modifiers) => Some(InputEvent::Modifiers { shift: modifiers.shift(), alt: modifiers.alt(), ctrl: modifiers.ctrl() }),
+            WindowEvent::CloseRequested => Some(InputEvent::CloseRequested),
+            _ => None,
+        }
+    }
+}
+
+/// 两次按下的间隔不超过该时长时视为双击
+const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(500);
+
+/// 从窗口事件中识别双击: 间隔及移动距离足够小的第二次左键按下转换为 `InputEvent::DoubleClick`
+#[derive(Debug, Default)]
+pub struct ClickTracker {
+    /// 当前的鼠标位置
+    cursor: Option<(f64, f64)>,
+    /// 上一次左键按下的时间及位置
+    last_press: Option<(Instant, (f64, f64))>,
+}
+
+impl ClickTracker {
+    pub fn track(&mut self, event: InputEvent, now: Instant) -> InputEvent {
+        match event {
+            InputEvent::CursorMoved { x, y } => self.cursor = Some((x, y)),
+            InputEvent::Button { button: Button::Left, pressed: true } => {
+                let Some(cursor) = self.cursor else { return event };
+                let double = self.last_press.is_some_and(|(time, (x, y))| {
+                    now.duration_since(time) <= DOUBLE_CLICK_TIME
+                        && (cursor.0 - x).abs() <= CLICK_TOLERANCE as f64
+                        && (cursor.1 - y).abs() <= CLICK_TOLERANCE as f64
+                });
+
+                // 双击后重新计时, 第三次按下不再视为双击
+                self.last_press = if double { None } else { Some((now, cursor)) };
+                if double {
+                    return InputEvent::DoubleClick;
+                }
+            }
+            _ => {}
+        }
+
+        event
+    }
+}
+
+/// 选择结束的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    /// 按下 'Enter' 确认
+    Confirmed,
+    /// 按下 'ESC' 取消
+    Cancelled,
+    /// 窗口被关闭
+    Closed,
+    /// 超过了选择的时限
+    TimedOut,
+}
+
+/// 选择的模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionMode {
+    /// 拖拽选择区域
+    Region,
+    /// 单击拾取像素颜色
+    Color,
+}
+
+/// 方向键的步长 (窗口物理像素), 按住 Shift 时为 `NUDGE_STEP_LARGE`
+const NUDGE_STEP: f32 = 1.0;
+const NUDGE_STEP_LARGE: f32 = 10.0;
+
+/// 拖拽时吸附到边缘的距离 (物理像素)
+const SNAP_DISTANCE: i32 = 6;
+
+/// 按下到释放的移动不超过该距离时视为单击 (窗口物理像素)
+const CLICK_TOLERANCE: f32 = 2.0;
+
+/// 控制点的点击容差 (窗口物理像素)
+const HANDLE_TOLERANCE: f32 = 6.0;
+
+/// 选区的 8 个控制点
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Handle {
+    TopLeft,
+    Top,
+    TopRight,
+    Right,
+    BottomRight,
+    Bottom,
+    BottomLeft,
+    Left,
+}
+
+impl Handle {
+    pub const ALL: [Handle; 8] = [
+        Handle::TopLeft, Handle::Top, Handle::TopRight, Handle::Right,
+        Handle::BottomRight, Handle::Bottom, Handle::BottomLeft, Handle::Left,
+    ];
+
+    /// 控制点在选区上的位置
+    pub fn position(&self, rect: WindowRect) -> WindowPoint {
+        let ([x1, y1], [x2, y2]) = (rect.min.to_array(), rect.max.to_array());
+        let (xc, yc) = ((x1 + x2) / 2.0, (y1 + y2) / 2.0);
+
+        match self {
+            Handle::TopLeft => WindowPoint::new(x1, y1),
+            Handle::Top => WindowPoint::new(xc, y1),
+            Handle::TopRight => WindowPoint::new(x2, y1),
+            Handle::Right => WindowPoint::new(x2, yc),
+            Handle::BottomRight => WindowPoint::new(x2, y2),
+            Handle::Bottom => WindowPoint::new(xc, y2),
+            Handle::BottomLeft => WindowPoint::new(x1, y2),
+            Handle::Left => WindowPoint::new(x1, yc),
+        }
+    }
+
+    /// 点位处的控制点 (选区较小时控制点重叠, 取最近的)
+    pub fn hit(rect: WindowRect, point: WindowPoint) -> Option<Handle> {
+        Handle::ALL.into_iter()
+            .map(|handle| {
+                let WindowPoint { x, y } = handle.position(rect);
+                (handle, (point.x - x).abs(), (point.y - y).abs())
+            })
+            .filter(|&(_, dx, dy)| dx <= HANDLE_TOLERANCE && dy <= HANDLE_TOLERANCE)
+            .min_by(|(_, dx1, dy1), (_, dx2, dy2)| (dx1 + dy1).total_cmp(&(dx2 + dy2)))
+            .map(|(handle, _, _)| handle)
+    }
+
+    /// 拖拽控制点到 `point` 后的选区 (越过对边时翻转)
+    pub fn resize(&self, rect: WindowRect, point: WindowPoint) -> WindowRect {
+        let ([mut x1, mut y1], [mut x2, mut y2]) = (rect.min.to_array(), rect.max.to_array());
+
+        match self {
+            Handle::TopLeft | Handle::Left | Handle::BottomLeft => x1 = point.x,
+            Handle::TopRight | Handle::Right | Handle::BottomRight => x2 = point.x,
+            _ => {}
+        }
+        match self {
+            Handle::TopLeft | Handle::Top | Handle::TopRight => y1 = point.y,
+            Handle::BottomLeft | Handle::Bottom | Handle::BottomRight => y2 = point.y,
+            _ => {}
+        }
+
+        WindowRect::from_points(WindowPoint::new(x1, y1), WindowPoint::new(x2, y2))
+    }
+}
+
+/// 鼠标拖拽的方式
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Drag {
+    /// 从起点开始绘制新选区
+    Create { anchor: WindowPoint },
+    /// 移动选区 (`grab` 为按下时的点位, `origin` 为按下时的选区, `button` 为按下的按键)
+    Move { grab: WindowPoint, origin: WindowRect, button: Button },
+    /// 拖拽控制点改变选区大小
+    Resize { handle: Handle, origin: WindowRect },
+    /// 在选区内绘制标注
+    Annotate { anchor: WindowPoint },
+}
+
+impl Drag {
+    /// 开始拖拽的按键, 只有释放该按键时结束拖拽 (中键平移选区, 其余均为左键)
+    fn button(&self) -> Button {
+        match self {
+            Drag::Move { button, .. } => *button,
+            _ => Button::Left,
+        }
+    }
+}
+
+/// 计算范围约束后的点位 (窗口物理坐标)
+pub fn calc_constrained_point(physical_point: PhysicalPosition<f64>, bounding: DesktopRect) -> WindowPoint {
+    WindowPoint::new(
+        physical_point.x.clamp(0.0, bounding.width as f64) as f32,
+        physical_point.y.clamp(0.0, bounding.height as f64) as f32,
+    )
+}
+
+/// 交互式选择的状态机
+///
+/// 只消费 `InputEvent`, 不依赖窗口, 绘制时从中读取状态
+pub struct SelectionController {
+    /// 窗口覆盖的区域 (桌面物理坐标)
+    bounding: DesktopRect,
+    /// 选择的模式
+    mode: SelectionMode,
+    /// 当前显示的选框 (窗口物理坐标)
+    selection: Option<WindowRect>,
+    /// 正在进行的拖拽
+    drag: Option<Drag>,
+    /// 当前的鼠标位置
+    curr_point: Option<WindowPoint>,
+    /// 各屏幕区域 (桌面物理坐标), 用于 Tab 切换屏幕及选中整个屏幕
+    screens: Vec<DesktopRect>,
+    /// 截图中检测到的边缘, 用于吸附和自动识别区域
+    edges: EdgeMap,
+    /// 各顶层窗口 (由上到下), 鼠标悬停时识别整个窗口
+    windows: Vec<WindowInfo>,
+    /// 鼠标下自动识别的区域 (窗口物理坐标), 单击即选中
+    candidate: Option<WindowRect>,
+    /// 修饰键状态: (shift, alt, ctrl)
+    modifiers: (bool, bool, bool),
+    /// 鼠标 / 按键的行为
+    gestures: Gestures,
+    /// 当前的标注工具, 为 `None` 时拖拽选区
+    tool: Option<Tool>,
+    /// 新标注的样式
+    style: AnnotationStyle,
+    /// 已添加的标注 (桌面物理坐标)
+    annotations: Vec<Mark>,
+    /// 文字标注使用的字体, 为 `None` 时不能添加文字
+    font: Option<FontArc>,
+    /// 正在编辑的文字标注 (`annotations` 中的序号)
+    editing: Option<usize>,
+    /// 正在绘制的荧光笔笔迹 (桌面物理坐标)
+    brush: Vec<[f32; 2]>,
+    /// 已确定的选区
+    selected: SelectedArea,
+    /// 取色模式下拾取的像素 (桌面物理坐标)
+    picked: Option<DesktopPoint>,
+    /// 结束的原因, 未结束时为 `None`
+    exit: Option<ExitReason>,
+}
+
+impl SelectionController {
+    pub fn new(bounding: DesktopRect) -> SelectionController {
+        SelectionController {
+            bounding,
+            mode: SelectionMode::Region,
+            selection: None,
+            drag: None,
+            curr_point: None,
+            screens: vec![],
+            edges: EdgeMap::default(),
+            windows: vec![],
+            candidate: None,
+            modifiers: (false, false, false),
+            gestures: Gestures::default(),
+            tool: None,
+            style: default_style(),
+            annotations: vec![],
+            font: None,
+            editing: None,
+            brush: vec![],
+            selected: SelectedArea::empty(),
+            picked: None,
+            exit: None,
+        }
+    }
+
+    /// 设置选择的模式
+    pub fn with_mode(mut self, mode: SelectionMode) -> SelectionController {
+        self.mode = mode;
+        self
+    }
+
+    /// 设置截图中检测到的边缘, 启用吸附和自动识别区域
+    pub fn with_edges(mut self, edges: EdgeMap) -> SelectionController {
+        self.edges = edges;
+        self
+    }
+
+    /// 设置各顶层窗口 (由上到下), 启用悬停识别窗口
+    pub fn with_windows(mut self, windows: Vec<WindowInfo>) -> SelectionController {
+        self.windows = windows;
+        self
+    }
+
+    /// 设置鼠标 / 按键的行为
+    pub fn with_gestures(mut self, gestures: Gestures) -> SelectionController {
+        self.gestures = gestures;
+        self
+    }
+
+    /// 设置文字标注使用的字体, 启用文字工具
+    pub fn with_font(mut self, font: Option<FontArc>) -> SelectionController {
+        self.font = font;
+        self
+    }
+
+    /// 设置各屏幕区域 (桌面物理坐标)
+    pub fn with_screens(mut self, screens: Vec<DesktopRect>) -> SelectionController {
+        self.screens = screens;
+        self
+    }
+
+    /// 处理一个输入事件 (结束后忽略后续事件)
+    pub fn handle(&mut self, event: InputEvent) {
+        if self.exit.is_some() {
+            return;
+        }
+        // 编辑文字时按键只用于编辑
+        if self.edit_text(event) {
+            return;
+        }
+
+        match event {
+            InputEvent::CursorMoved { x, y } => {
+                // 更新当前点位, 处理边界问题
+                let point = calc_constrained_point(PhysicalPosition::new(x, y), self.bounding);
+                self.curr_point = Some(point);
+
+                // 鼠标拖拽时吸附到附近的边缘 (键盘移动不吸附, 以便逐像素调整)
+                match self.drag {
+                    Some(Drag::Create { .. } | Drag::Resize { .. }) => self.drag_to(self.snap_point(point)),
+                    Some(Drag::Move { .. }) => {
+                        self.drag_to(point);
+                        self.selection = self.selection.map(|rect| self.snap_rect(rect));
+                    }
+                    Some(Drag::Annotate { .. }) if self.tool == Some(Tool::Highlight) => self.extend_brush(point),
+                    Some(Drag::Annotate { .. }) | None => {}
+                }
+                self.update_candidate();
+            }
+            // 取色模式: 单击 / 空格 / 'Enter' 拾取鼠标所在的像素
+            InputEvent::Button { button: Button::Left, pressed: true } | InputEvent::DoubleClick |
+            InputEvent::Key { key: Key::Space | Key::Enter, pressed: true } if self.mode == SelectionMode::Color => self.pick(),
+            // 拖拽中 (如中键平移) 忽略再次按下的左键
+            InputEvent::Button { button: Button::Left, pressed: true } if self.drag.is_none() => self.begin_drag(),
+            // 释放: 结束拖拽 -> 计算区域
+            InputEvent::Button { button, pressed: false } if self.drag.is_some_and(|drag| drag.button() == button) => self.end_drag(),
+            // 在选区内双击: 确认 (标注时除外), 否则与按下相同
+            InputEvent::DoubleClick => match (self.gestures.double_click_confirms, self.selection, self.curr_point) {
+                (true, Some(rect), Some(point)) if rect.contains(point) && self.selected.valid && self.tool.is_none() => {
+                    println!("Exit (cause selection was double clicked)");
+                    self.exit = Some(ExitReason::Confirmed);
+                }
+                _ => self.begin_drag(),
+            },
+            // 右键: 清除选区, 没有选区时退出
+            InputEvent::Button { button: Button::Right, pressed: true } if self.gestures.right_click_clears => {
+                if self.selection.is_some() {
+                    self.clear_selection();
+                } else {
+                    println!("Exit (cause right button was clicked)");
+                    self.exit = Some(ExitReason::Cancelled);
+                }
+            }
+            // 中键: 按住拖拽平移选区 (鼠标不必在选区内)
+            InputEvent::Button { button: Button::Middle, pressed: true } if self.gestures.middle_drag_pans => self.begin_pan(),
+            InputEvent::Modifiers { shift, alt, ctrl } => self.modifiers = (shift, alt, ctrl),
+            // 空格: 开始 / 结束拖拽, 与鼠标按下 / 释放相同
+            InputEvent::Key { key: Key::Space, pressed: true } => match self.drag {
+                Some(_) => self.end_drag(),
+                None => {
+                    self.init_cursor();
+                    self.begin_drag();
+                }
+            },
+            InputEvent::Key { key: Key::Tab, pressed: true } => self.cycle_screen(),
+            // 数字键: 选中第 n 个屏幕
+            InputEvent::Key { key: Key::Digit(n @ 1..=9), pressed: true } if self.mode == SelectionMode::Region => self.select_screen(n as usize - 1),
+            InputEvent::Key { key, pressed: true } if key.direction().is_some() => self.nudge(key),
+            // 'ESC': 先取消标注工具
+            InputEvent::Key { key: Key::Escape, pressed: true } if self.tool.is_some() => self.set_tool(None),
+            InputEvent::Key { key: Key::Escape, pressed: true } if self.gestures.escape_clears_first && self.selection.is_some() => self.clear_selection(),
+            InputEvent::Key { key: Key::Escape, pressed: true } => {
+                println!("Exit (cause 'ESC' was pressed)");
+                self.selected.clear();
+                self.exit = Some(ExitReason::Cancelled);
+            }
+            InputEvent::Key { key: Key::Enter, pressed: true } => {
+                println!("Exit (cause 'Enter' was pressed)");
+                self.exit = Some(ExitReason::Confirmed);
+            }
+            InputEvent::CloseRequested => {
+                println!("Exit (cause 'WindowEvent::CloseRequested' was sent)");
+                self.exit = Some(ExitReason::Closed);
+            }
+            InputEvent::TimedOut => {
+                println!("Exit (cause selection timed out)");
+                self.exit = Some(ExitReason::TimedOut);
+            }
+            _ => {}
+        }
+    }
+
+    /// 拾取鼠标所在的像素并结束
+    fn pick(&mut self) {
+        if let Some(pixel) = self.cursor_pixel() {
+            println!("Exit (cause pixel {:?} was picked)", pixel);
+            self.picked = Some(pixel);
+            self.exit = Some(ExitReason::Confirmed);
+        }
+    }
+
+    /// 按下: 工具栏上为点击按钮, 控制点上为改变大小, 选区内为移动 (有标注工具时为绘制标注 / 放置文字及编号标记), 否则绘制新选区
+    ///
+    /// 有标注工具时不在选区外绘制新选区, 以免丢失标注
+    fn begin_drag(&mut self) {
+        let Some(point) = self.curr_point else { return };
+        if let Some(item) = self.toolbar_item_at(point) {
+            self.click_toolbar(item);
+            return;
+        }
+        self.finish_editing();
+
+        self.drag = match self.selection {
+            Some(rect) => match Handle::hit(rect, point) {
+                Some(handle) => Some(Drag::Resize { handle, origin: rect }),
+                None if rect.contains(point) => match self.tool {
+                    Some(Tool::Shape(_) | Tool::Redact(_) | Tool::Spotlight) => Some(Drag::Annotate { anchor: point }),
+                    Some(Tool::Highlight) => {
+                        self.brush = vec![self.to_desktop(point)];
+                        Some(Drag::Annotate { anchor: point })
+                    }
+                    Some(Tool::Text) => {
+                        self.place_text(point);
+                        None
+                    }
+                    Some(Tool::Marker) => {
+                        self.place_marker(point);
+                        None
+                    }
+                    None => Some(Drag::Move { grab: point, origin: rect, button: Button::Left }),
+                },
+                None if self.tool.is_some() => None,
+                None => Some(Drag::Create { anchor: point }),
+            },
+            None => Some(Drag::Create { anchor: point }),
+        };
+        if matches!(self.drag, Some(Drag::Create { .. })) {
+            self.clear_annotations();
+        }
+        self.drag_to(point);
+    }
+
+    /// 中键按下: 开始平移选区, 拖拽中或有标注工具时忽略
+    fn begin_pan(&mut self) {
+        if let (None, None, Some(rect), Some(point)) = (self.drag, self.tool, self.selection, self.curr_point) {
+            self.finish_editing();
+            self.drag = Some(Drag::Move { grab: point, origin: rect, button: Button::Middle });
+        }
+    }
+
+    /// 释放: 结束拖拽 -> 计算区域; 单击时选中鼠标下自动识别的区域; 绘制标注时保存 (单击及过窄的区域不保存)
+    fn end_drag(&mut self) {
+        let mark = self.drawing_mark();
+        match (self.drag.take(), self.curr_point) {
+            (Some(Drag::Create { anchor }), Some(point)) => {
+                let clicked = (point.x - anchor.x).abs() <= CLICK_TOLERANCE && (point.y - anchor.y).abs() <= CLICK_TOLERANCE;
+                if clicked && self.candidate.is_some() {
+                    self.selection = self.candidate;
+                }
+            }
+            (Some(Drag::Annotate { .. }), _) => {
+                if let Some(mark) = mark {
+                    self.annotations.push(mark);
+                    println!("Add Annotation! count: {}", self.annotations.len());
+                }
+                self.brush.clear();
+            }
+            _ => {}
+        }
+
+        self.commit();
+        self.update_candidate();
+    }
+
+    /// 鼠标下的工具栏按钮 (工具栏显示时)
+    fn toolbar_item_at(&self, point: WindowPoint) -> Option<ToolbarItem> {
+        self.toolbar()
+            .into_iter()
+            .find(|(_, rect)| rect.contains(point))
+            .map(|(item, _)| item)
+    }
+
+    /// 点击工具栏按钮, 修改样式时同时修改正在编辑的文字
+    fn click_toolbar(&mut self, item: ToolbarItem) {
+        match item {
+            ToolbarItem::Tool(Tool::Text) if self.font.is_none() => println!("No font for text annotations"),
+            ToolbarItem::Tool(tool) => self.set_tool(if self.tool == Some(tool) { None } else { Some(tool) }),
+            ToolbarItem::Color(index) => self.style.color = PALETTE[index],
+            ToolbarItem::Width(index) => self.style.stroke_width = STROKE_WIDTHS[index],
+            ToolbarItem::Fill => self.style.fill = !self.style.fill,
+            ToolbarItem::Undo => {
+                self.finish_editing();
+                self.annotations.pop();
+            }
+        }
+
+        let style = self.text_label(DesktopPoint::new(0, 0));
+        if let Some(Mark::Text(label)) = self.editing.and_then(|index| self.annotations.get_mut(index)) {
+            *label = TextLabel { text: std::mem::take(&mut label.text), position: label.position, ..style };
+        }
+    }
+
+    /// 切换标注工具 (结束文字的编辑)
+    fn set_tool(&mut self, tool: Option<Tool>) {
+        self.finish_editing();
+        self.tool = tool;
+    }
+
+    /// 按当前样式在 `position` (桌面物理坐标) 处创建的空白文字
+    fn text_label(&self, position: DesktopPoint) -> TextLabel {
+        TextLabel {
+            text: String::new(),
+            position,
+            font_size: font_size(self.style.stroke_width),
+            color: self.style.color,
+            background: self.style.fill.then(|| contrast_background(self.style.color)),
+        }
+    }
+
+    /// 窗口物理坐标 -> 桌面物理坐标 (保留小数)
+    fn to_desktop(&self, point: WindowPoint) -> [f32; 2] {
+        let origin = self.bounding.origin();
+        [point.x + origin.x as f32, point.y + origin.y as f32]
+    }
+
+    /// 荧光笔经过 `point` (约束在选区内), 与上一个点过近时忽略
+    fn extend_brush(&mut self, point: WindowPoint) {
+        let Some(rect) = self.selection else { return };
+        let point = self.to_desktop(WindowPoint::new(point.x.clamp(rect.min.x, rect.max.x), point.y.clamp(rect.min.y, rect.max.y)));
+
+        if self.brush.last().is_none_or(|last| (point[0] - last[0]).abs() + (point[1] - last[1]).abs() >= 1.0) {
+            self.brush.push(point);
+        }
+    }
+
+    /// 编号标记工具单击: 删除点击的标记 (之后的标记依次减 1), 否则在点击处添加标记
+    fn place_marker(&mut self, point: WindowPoint) {
+        let [x, y] = self.to_desktop(point);
+        let hit = self.annotations.iter().rposition(|mark| {
+            matches!(mark, Mark::Marker(marker) if (x - marker.center[0]).hypot(y - marker.center[1]) <= marker.radius)
+        });
+
+        match hit {
+            Some(index) => {
+                self.annotations.remove(index);
+            }
+            None => self.annotations.push(Mark::Marker(StepMarker {
+                center: [x, y],
+                radius: marker_radius(self.style.stroke_width),
+                color: self.style.color,
+            })),
+        }
+    }
+
+    /// 文字工具单击: 编辑点击的文字, 否则在点击处添加文字
+    fn place_text(&mut self, point: WindowPoint) {
+        let position = point.to_desktop(self.bounding.origin());
+        let hit = self.font.as_ref().and_then(|font| {
+            self.annotations
+                .iter()
+                .rposition(|mark| matches!(mark, Mark::Text(label) if label.bounds(font).contains(position)))
+        });
+
+        self.editing = Some(match hit {
+            Some(index) => index,
+            None => {
+                self.annotations.push(Mark::Text(self.text_label(position)));
+                self.annotations.len() - 1
+            }
+        });
+    }
+
+    /// 编辑文字: 输入字符, 退格删除, 'Enter' / 'ESC' 结束编辑, 忽略其他按键; 返回事件是否被消费
+    fn edit_text(&mut self, event: InputEvent) -> bool {
+        let Some(Mark::Text(label)) = self.editing.and_then(|index| self.annotations.get_mut(index)) else {
+            return false;
+        };
+
+        match event {
+            InputEvent::Character { ch } => {
+                // 控制字符 (如: 退格、回车) 由按键处理
+                if !ch.is_control() {
+                    label.text.push(ch);
+                }
+            }
+            InputEvent::Key { key: Key::Backspace, pressed: true } => {
+                label.text.pop();
+            }
+            InputEvent::Key { key: Key::Enter | Key::Escape, pressed: true } => self.finish_editing(),
+            InputEvent::Key { .. } => {}
+            _ => return false,
+        }
+        true
+    }
+
+    /// 结束文字的编辑, 移除空白的文字
+    fn finish_editing(&mut self) {
+        if let Some(index) = self.editing.take() {
+            if matches!(&self.annotations[index], Mark::Text(label) if label.text.is_empty()) {
+                self.annotations.remove(index);
+            }
+        }
+    }
+
+    /// 清除选区 (及正在进行的拖拽、标注)
+    fn clear_selection(&mut self) {
+        self.drag = None;
+        self.selection = None;
+        self.tool = None;
+        self.clear_annotations();
+        self.selected.clear();
+        self.update_candidate();
+        println!("Clear Selection!");
+    }
+
+    /// 清除标注: 选区被替换时标注不再对应新的选区, 不应再导出
+    fn clear_annotations(&mut self) {
+        self.editing = None;
+        if !self.annotations.is_empty() {
+            self.annotations.clear();
+            println!("Clear Annotations!");
+        }
+    }
+
+    /// 吸附到距离点最近的边缘 (x / y 分别吸附)
+    fn snap_point(&self, point: WindowPoint) -> WindowPoint {
+        let origin = self.bounding.origin();
+        let DesktopPoint { x, y } = point.to_desktop(origin);
+        let snapped_x = self.edges.nearest_vertical(x, y, SNAP_DISTANCE).map_or(point.x, |x| (x - origin.x) as f32);
+        let snapped_y = self.edges.nearest_horizontal(y, x, SNAP_DISTANCE).map_or(point.y, |y| (y - origin.y) as f32);
+
+        WindowPoint::new(snapped_x, snapped_y)
+    }
+
+    /// 平移选区, 使其距离边缘最近的一条边吸附到该边缘 (x / y 分别吸附)
+    fn snap_rect(&self, rect: WindowRect) -> WindowRect {
+        let origin = self.bounding.origin();
+        let DesktopRect { x, y, width, height } = rect.to_desktop(origin);
+        let (right, bottom, cx, cy) = (x + width as i32, y + height as i32, x + width as i32 / 2, y + height as i32 / 2);
+
+        let dx = [x, right]
+            .into_iter()
+            .filter_map(|edge| self.edges.nearest_vertical(edge, cy, SNAP_DISTANCE).map(|line| line - edge))
+            .min_by_key(|delta| delta.abs())
+            .unwrap_or(0);
+        let dy = [y, bottom]
+            .into_iter()
+            .filter_map(|edge| self.edges.nearest_horizontal(edge, cx, SNAP_DISTANCE).map(|line| line - edge))
+            .min_by_key(|delta| delta.abs())
+            .unwrap_or(0);
+        let snapped = rect.translate(dx as f32, dy as f32);
+
+        // 吸附后超出窗口时放弃
+        let window = WindowRect::from_points(WindowPoint::new(0.0, 0.0), WindowPoint::new(self.bounding.width as f32, self.bounding.height as f32));
+        if window.contains(snapped.min) && window.contains(snapped.max) { snapped } else { rect }
+    }
+
+    /// 鼠标下最上层的窗口 (桌面物理坐标)
+    fn window_at(&self, point: DesktopPoint) -> Option<&WindowInfo> {
+        self.windows.iter().find(|window| window.physical_rect().contains(point))
+    }
+
+    /// 窗口在选择窗口内的部分 (窗口物理坐标)
+    fn window_rect(&self, window: &WindowInfo) -> Option<WindowRect> {
+        window.physical_rect()
+            .intersect(&self.bounding)
+            .map(|rect| rect.to_window(self.bounding.origin()))
+    }
+
+    /// 屏幕在选择窗口内的部分 (窗口物理坐标)
+    fn screen_rect(&self, screen: &DesktopRect) -> Option<WindowRect> {
+        screen.intersect(&self.bounding).map(|rect| rect.to_window(self.bounding.origin()))
+    }
+
+    /// 选中第 `index` 个屏幕 (拖拽中忽略)
+    fn select_screen(&mut self, index: usize) {
+        if self.drag.is_some() {
+            return;
+        }
+
+        if let Some(rect) = self.screens.get(index).and_then(|screen| self.screen_rect(screen)) {
+            self.selection = Some(rect);
+            self.clear_annotations();
+            self.commit();
+            self.update_candidate();
+        }
+    }
+
+    /// 更新鼠标下自动识别的区域: 仅在鼠标不在已有选区 (及其控制点) 上时识别, 拖拽中保留按下时的区域
+    ///
+    /// 鼠标在某个窗口上时为整个窗口, 否则为截图中检测到的区域, 都没有时为鼠标所在的屏幕
+    fn update_candidate(&mut self) {
+        if self.drag.is_some() {
+            return;
+        }
+
+        self.candidate = match (self.mode, self.curr_point) {
+            (SelectionMode::Region, Some(point)) => {
+                let on_selection = self.selection.is_some_and(|rect| rect.contains(point) || Handle::hit(rect, point).is_some())
+                    || self.toolbar_item_at(point).is_some();
+                let desktop = point.to_desktop(self.bounding.origin());
+
+                match (on_selection, self.window_at(desktop)) {
+                    (true, _) => None,
+                    (false, Some(window)) => self.window_rect(window),
+                    (false, None) => self.edges
+                        .rect_at(desktop.x, desktop.y)
+                        .map(|rect| rect.to_window(self.bounding.origin()))
+                        .or_else(|| self.screens.iter().find(|screen| screen.contains(desktop)).and_then(|screen| self.screen_rect(screen))),
+                }
+            }
+            _ => None,
+        };
+    }
+
+    /// 仅使用键盘时鼠标位置未知, 从第一个屏幕的中心开始
+    fn init_cursor(&mut self) {
+        if self.curr_point.is_none() {
+            self.move_cursor(self.screen_center(0));
+        }
+    }
+
+    /// 移动鼠标位置 (约束在窗口内), 拖拽中同时更新选框
+    fn move_cursor(&mut self, point: WindowPoint) {
+        let point = calc_constrained_point(PhysicalPosition::new(point.x as f64, point.y as f64), self.bounding);
+        self.curr_point = Some(point);
+        self.drag_to(point);
+        self.update_candidate();
+    }
+
+    /// 第 `index` 个屏幕的中心 (窗口物理坐标), 没有屏幕信息时为窗口中心
+    fn screen_center(&self, index: usize) -> WindowPoint {
+        let screen = self.screens.get(index).copied().unwrap_or(self.bounding);
+        let rect = screen.to_window(self.bounding.origin());
+
+        WindowPoint::new((rect.min.x + rect.max.x) / 2.0, (rect.min.y + rect.max.y) / 2.0)
+    }
+
+    /// Tab: 鼠标移动到下一个屏幕的中心 (Shift + Tab 为上一个)
+    fn cycle_screen(&mut self) {
+        let count = self.screens.len().max(1);
+        let current = self.curr_point.and_then(|point| {
+            let point = point.to_desktop(self.bounding.origin());
+            self.screens.iter().position(|screen| screen.contains(point))
+        });
+        let next = match (current, self.modifiers.0) {
+            (Some(index), false) => (index + 1) % count,
+            (Some(index), true) => (index + count - 1) % count,
+            (None, _) => 0,
+        };
+
+        self.move_cursor(self.screen_center(next));
+    }
+
+    /// 方向键: 移动鼠标位置 (拖拽中同时更新选框); 按住 Alt 时移动选区的右 / 下边, 同时按住 Ctrl 时移动左 / 上边
+    fn nudge(&mut self, key: Key) {
+        let Some((dx, dy)) = key.direction() else { return };
+        let (shift, alt, ctrl) = self.modifiers;
+        let step = if shift { NUDGE_STEP_LARGE } else { NUDGE_STEP };
+        let (dx, dy) = (dx * step, dy * step);
+
+        if alt {
+            // 拖拽中不调整, 避免与拖拽冲突
+            let (Some(rect), None) = (self.selection, self.drag) else { return };
+            let handle = match (dx != 0.0, ctrl) {
+                (true, false) => Handle::Right,
+                (true, true) => Handle::Left,
+                (false, false) => Handle::Bottom,
+                (false, true) => Handle::Top,
+            };
+            let point = handle.position(rect);
+            let point = calc_constrained_point(PhysicalPosition::new((point.x + dx) as f64, (point.y + dy) as f64), self.bounding);
+
+            let resized = handle.resize(rect, point);
+
+            // 不缩小到 0
+            if resized.min.x < resized.max.x && resized.min.y < resized.max.y {
+                self.selection = Some(resized);
+                self.commit();
+            }
+        } else {
+            match self.curr_point {
+                Some(point) => self.move_cursor(WindowPoint::new(point.x + dx, point.y + dy)),
+                None => self.init_cursor(),
+            }
+        }
+    }
+
+    /// 拖拽到 `point`, 更新选框
+    fn drag_to(&mut self, point: WindowPoint) {
+        self.selection = match self.drag {
+            Some(Drag::Create { anchor }) => Some(WindowRect::from_points(anchor, point)),
+            Some(Drag::Move { grab, origin, .. }) => {
+                // 移动时保持在窗口内
+                let dx = (point.x - grab.x).clamp(-origin.min.x, self.bounding.width as f32 - origin.max.x);
+                let dy = (point.y - grab.y).clamp(-origin.min.y, self.bounding.height as f32 - origin.max.y);
+                Some(origin.translate(dx, dy))
+            }
+            Some(Drag::Resize { handle, origin }) => Some(handle.resize(origin, point)),
+            Some(Drag::Annotate { .. }) | None => self.selection,
+        };
+    }
+
+    /// 缓存选中的区域 (仅记录区域, 确认后再从原始截图中裁剪)
+    fn commit(&mut self) {
+        if let Some(rect) = self.selection {
+            let rect = rect.to_desktop(self.bounding.origin());
+
+            if !rect.is_empty() && self.selected.check(rect) {
+                self.selected.update(rect);
+                println!("Update Selection! rect: {:?}", rect);
+            }
+        }
+    }
+
+    /// 当前显示的选框 (窗口物理坐标)
+    pub fn rect(&self) -> Option<WindowRect> {
+        self.selection
+    }
+
+    /// 当前显示的选框 (桌面物理坐标)
+    pub fn desktop_rect(&self) -> Option<DesktopRect> {
+        self.selection.map(|rect| rect.to_desktop(self.bounding.origin()))
+    }
+
+    /// 鼠标下自动识别的区域 (窗口物理坐标), 拖拽中不显示
+    pub fn candidate(&self) -> Option<WindowRect> {
+        self.candidate.filter(|_| self.drag.is_none())
+    }
+
+    /// 鼠标下自动识别的窗口, 拖拽中不显示
+    pub fn hovered_window(&self) -> Option<&WindowInfo> {
+        let candidate = self.candidate()?;
+        self.windows.iter().find(|window| self.window_rect(window) == Some(candidate))
+    }
+
+    /// 鼠标下自动识别的区域为整个屏幕 (且不是窗口) 时为该屏幕的序号, 拖拽中不显示
+    pub fn hovered_screen(&self) -> Option<usize> {
+        let candidate = self.candidate()?;
+        if self.hovered_window().is_some() {
+            return None;
+        }
+        self.screens.iter().position(|screen| self.screen_rect(screen) == Some(candidate))
+    }
+
+    /// 选区恰好为某个屏幕 (在选择窗口内的部分) 时为该屏幕的序号
+    pub fn selected_screen(&self) -> Option<usize> {
+        let rect = self.selected.rect.filter(|_| self.selected.valid)?;
+        self.screens.iter().position(|screen| screen.intersect(&self.bounding) == Some(rect))
+    }
+
+    /// 选区恰好为某个窗口 (在选择窗口内的部分) 时为该窗口
+    pub fn selected_window(&self) -> Option<&WindowInfo> {
+        let rect = self.selected.rect.filter(|_| self.selected.valid)?;
+        self.windows.iter().find(|window| window.physical_rect().intersect(&self.bounding) == Some(rect))
+    }
+
+    /// 工具栏各按钮的位置 (窗口物理坐标): 仅在选择区域且有确定的选区时显示, 拖拽选区时隐藏
+    pub fn toolbar(&self) -> Vec<(ToolbarItem, WindowRect)> {
+        match (self.mode, self.selection, self.drag) {
+            (SelectionMode::Region, Some(rect), None | Some(Drag::Annotate { .. })) if self.selected.valid => {
+                toolbar_layout(rect, [self.bounding.width as f32, self.bounding.height as f32])
+            }
+            _ => vec![],
+        }
+    }
+
+    /// 当前的标注工具
+    pub fn tool(&self) -> Option<Tool> {
+        self.tool
+    }
+
+    /// 新标注的样式
+    pub fn style(&self) -> AnnotationStyle {
+        self.style
+    }
+
+    /// 已添加的标注 (桌面物理坐标)
+    pub fn annotations(&self) -> &[Mark] {
+        &self.annotations
+    }
+
+    /// 正在编辑的文字
+    pub fn editing(&self) -> Option<&TextLabel> {
+        match self.annotations.get(self.editing?)? {
+            Mark::Text(label) => Some(label),
+            _ => None,
+        }
+    }
+
+    /// 文字标注使用的字体
+    pub fn font(&self) -> Option<&FontArc> {
+        self.font.as_ref()
+    }
+
+    /// 正在拖拽的标注的起点和终点 (桌面物理坐标), 终点约束在选区内
+    fn drawing_points(&self) -> Option<([f32; 2], [f32; 2])> {
+        let (Some(Drag::Annotate { anchor }), Some(rect), Some(point)) = (self.drag, self.selection, self.curr_point) else {
+            return None;
+        };
+        let point = WindowPoint::new(point.x.clamp(rect.min.x, rect.max.x), point.y.clamp(rect.min.y, rect.max.y));
+
+        Some((self.to_desktop(anchor), self.to_desktop(point)))
+    }
+
+    /// 正在拖拽的矩形区域 (桌面物理坐标), 约束在选区内
+    fn drawing_rect(&self) -> Option<DesktopRect> {
+        let (from, to) = self.drawing_points()?;
+        let (x1, y1) = (from[0].min(to[0]).round() as i32, from[1].min(to[1]).round() as i32);
+        let (x2, y2) = (from[0].max(to[0]).round() as i32, from[1].max(to[1]).round() as i32);
+
+        Some(DesktopRect::new(x1, y1, (x2 - x1) as u32, (y2 - y1) as u32))
+    }
+
+    /// 松开时要保存的标注, 单击及过窄的区域为 `None`
+    fn drawing_mark(&self) -> Option<Mark> {
+        let moved = |from: [f32; 2], to: [f32; 2]| (to[0] - from[0]).abs() > CLICK_TOLERANCE || (to[1] - from[1]).abs() > CLICK_TOLERANCE;
+        let wide = |rect: &DesktopRect| rect.width as f32 > CLICK_TOLERANCE && rect.height as f32 > CLICK_TOLERANCE;
+
+        match self.tool? {
+            Tool::Shape(_) => self.drawing().filter(|annotation| moved(annotation.from, annotation.to)).map(Mark::Shape),
+            Tool::Redact(_) => self.redacting().filter(|redaction| wide(&redaction.rect)).map(Mark::Redact),
+            Tool::Spotlight => self.spotlighting().filter(wide).map(Mark::Spotlight),
+            Tool::Highlight => self.highlighting()
+                .filter(|highlight| highlight.points.iter().any(|point| moved(highlight.points[0], *point)))
+                .map(Mark::Highlight),
+            Tool::Text | Tool::Marker => None,
+        }
+    }
+
+    /// 正在绘制的标注 (桌面物理坐标), 终点约束在选区内
+    pub fn drawing(&self) -> Option<Annotation> {
+        let Some(Tool::Shape(shape)) = self.tool else { return None };
+        let (from, to) = self.drawing_points()?;
+
+        Some(Annotation { shape, from, to, style: self.style })
+    }
+
+    /// 正在拖拽的打码区域 (桌面物理坐标), 约束在选区内; 强度取决于线宽, 填充使用当前颜色
+    pub fn redacting(&self) -> Option<Redaction> {
+        let Some(Tool::Redact(kind)) = self.tool else { return None };
+        let [r, g, b, _] = self.style.color;
+
+        Some(Redaction {
+            kind,
+            rect: self.drawing_rect()?,
+            strength: redact_strength(self.style.stroke_width),
+            color: [r, g, b],
+        })
+    }
+
+    /// 正在拖拽的聚光灯区域 (桌面物理坐标), 约束在选区内
+    pub fn spotlighting(&self) -> Option<DesktopRect> {
+        match self.tool {
+            Some(Tool::Spotlight) => self.drawing_rect(),
+            _ => None,
+        }
+    }
+
+    /// 正在绘制的荧光笔笔迹 (桌面物理坐标), 颜色为半透明的当前颜色
+    pub fn highlighting(&self) -> Option<Highlight> {
+        let (Some(Drag::Annotate { .. }), Some(Tool::Highlight)) = (self.drag, self.tool) else { return None };
+        let [r, g, b, _] = self.style.color;
+
+        Some(Highlight {
+            points: self.brush.clone(),
+            width: highlight_width(self.style.stroke_width),
+            color: [r, g, b, HIGHLIGHT_ALPHA],
+        })
+    }
+
+    /// 是否正在绘制新选区 (绘制中不显示控制点)
+    pub fn is_creating(&self) -> bool {
+        matches!(self.drag, Some(Drag::Create { .. }))
+    }
+
+    /// 当前的鼠标位置 (窗口物理坐标)
+    pub fn cursor(&self) -> Option<WindowPoint> {
+        self.curr_point
+    }
+
+    /// 鼠标所在的像素 (桌面物理坐标)
+    pub fn cursor_pixel(&self) -> Option<DesktopPoint> {
+        let point = self.curr_point?;
+        let x = (point.x.floor() as i32).clamp(0, self.bounding.width as i32 - 1);
+        let y = (point.y.floor() as i32).clamp(0, self.bounding.height as i32 - 1);
+
+        Some(DesktopPoint::new(self.bounding.x + x, self.bounding.y + y))
+    }
+
+    /// 鼠标所在的控制点 (拖拽中为正在拖拽的控制点)
+    pub fn hovered_handle(&self) -> Option<Handle> {
+        match self.drag {
+            Some(Drag::Resize { handle, .. }) => Some(handle),
+            Some(_) => None,
+            None => Handle::hit(self.selection?, self.curr_point?),
+        }
+    }
+
+    /// 窗口覆盖的区域 (桌面物理坐标)
+    pub fn bounding(&self) -> DesktopRect {
+        self.bounding
+    }
+
+    /// 选择的模式
+    pub fn mode(&self) -> SelectionMode {
+        self.mode
+    }
+
+    /// 取色模式下拾取的像素 (桌面物理坐标)
+    pub fn picked(&self) -> Option<DesktopPoint> {
+        self.picked
+    }
+
+    /// 已确定的选区
+    pub fn selected(&self) -> &SelectedArea {
+        &self.selected
+    }
+
+    /// 结束的原因, 未结束时为 `None`
+    pub fn exit(&self) -> Option<ExitReason> {
+        self.exit
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use std::time::{Duration, Instant};
+    use glium::glutin::dpi::PhysicalPosition;
+    use crate::declares::{CaptureInfo, Gestures};
+    use crate::geometry::{DesktopPoint, DesktopRect, WindowPoint};
+    use crate::imgui_impl::controller::{Button, calc_constrained_point, ClickTracker, ExitReason, Handle, InputEvent, Key, SelectionController, SelectionMode};
+    use crate::imgui_impl::toolbar::{highlight_width, Tool, ToolbarItem, HIGHLIGHT_ALPHA, PALETTE, STROKE_WIDTHS};
+    use crate::utils::annotate::{dimmed_rects, draw_annotations, draw_marks, Mark, Shape};
+    use crate::utils::detect::EdgeMap;
+    use crate::utils::redact::RedactKind;
+    use crate::utils::text::text_font;
+
+    fn drag(controller: &mut SelectionController, from: (f64, f64), to: (f64, f64)) {
+        controller.handle(InputEvent::CursorMoved { x: from.0, y: from.1 });
+        controller.handle(InputEvent::Button { button: Button::Left, pressed: true });
+        controller.handle(InputEvent::CursorMoved { x: to.0, y: to.1 });
+        controller.handle(InputEvent::Button { button: Button::Left, pressed: false });
+    }
+
+    /// 点击当前工具栏上的按钮
+    fn click(controller: &mut SelectionController, item: ToolbarItem) {
+        let rect = controller.toolbar().into_iter().find(|(other, _)| *other == item).unwrap().1;
+        controller.handle(InputEvent::CursorMoved { x: (rect.min.x + 1.0) as f64, y: (rect.min.y + 1.0) as f64 });
+        controller.handle(InputEvent::Button { button: Button::Left, pressed: true });
+        controller.handle(InputEvent::Button { button: Button::Left, pressed: false });
+    }
+
+    fn pan(controller: &mut SelectionController, from: (f64, f64), to: (f64, f64)) {
+        controller.handle(InputEvent::CursorMoved { x: from.0, y: from.1 });
+        controller.handle(InputEvent::Button { button: Button::Middle, pressed: true });
+        controller.handle(InputEvent::CursorMoved { x: to.0, y: to.1 });
+        controller.handle(InputEvent::Button { button: Button::Middle, pressed: false });
+    }
+
+    fn press(controller: &mut SelectionController, key: Key, times: usize) {
+        for _ in 0..times {
+            controller.handle(InputEvent::Key { key, pressed: true });
+        }
+    }
+
+    #[test]
+    fn constrain_to_window() {
+        let bounding = DesktopRect::new(-1280, -200, 3200, 1280);
+
+        assert_eq!(calc_constrained_point(PhysicalPosition::new(-5.0, 20.0), bounding), WindowPoint::new(0.0, 20.0));
+        assert_eq!(calc_constrained_point(PhysicalPosition::new(4000.0, 2000.0), bounding), WindowPoint::new(3200.0, 1280.0));
+    }
+
+    #[test]
+    fn drag_and_redrag() {
+        let mut controller = SelectionController::new(DesktopRect::new(-100, 0, 300, 200));
+
+        // 反向拖拽, 选区为桌面坐标
+        drag(&mut controller, (50.0, 60.0), (10.0, 20.0));
+        assert_eq!(controller.selected().xywh(), Some((-90, 20, 40, 40)));
+
+        // 重新拖拽: 松开前保留上一次的选区
+        controller.handle(InputEvent::CursorMoved { x: 100.0, y: 100.0 });
+        controller.handle(InputEvent::Button { button: Button::Left, pressed: true });
+        controller.handle(InputEvent::CursorMoved { x: 400.0, y: 150.0 });
+        assert_eq!(controller.selected().xywh(), Some((-90, 20, 40, 40)));
+        assert_eq!(controller.rect().map(|rect| rect.max), Some(WindowPoint::new(300.0, 150.0)));
+
+        // 超出窗口的部分被裁剪
+        controller.handle(InputEvent::Button { button: Button::Left, pressed: false });
+        assert_eq!(controller.selected().xywh(), Some((0, 100, 200, 50)));
+
+        // 单击不产生选区
+        drag(&mut controller, (5.0, 5.0), (5.0, 5.0));
+        assert_eq!(controller.selected().xywh(), Some((0, 100, 200, 50)));
+
+        controller.handle(InputEvent::Key { key: Key::Enter, pressed: true });
+        assert_eq!(controller.exit(), Some(ExitReason::Confirmed));
+    }
+
+    #[test]
+    fn move_inside_window() {
+        let mut controller = SelectionController::new(DesktopRect::new(-100, 0, 300, 200));
+        drag(&mut controller, (10.0, 20.0), (50.0, 60.0));
+
+        // 在选区内部拖拽: 平移, 尺寸不变
+        drag(&mut controller, (30.0, 30.0), (60.0, 50.0));
+        assert_eq!(controller.selected().xywh(), Some((-60, 40, 40, 40)));
+
+        // 平移不超出窗口
+        drag(&mut controller, (50.0, 50.0), (500.0, -100.0));
+        assert_eq!(controller.selected().xywh(), Some((160, 0, 40, 40)));
+    }
+
+    #[test]
+    fn resize_with_handles() {
+        let mut controller = SelectionController::new(DesktopRect::new(0, 0, 300, 200));
+        drag(&mut controller, (10.0, 20.0), (50.0, 60.0));
+
+        let rect = controller.rect().unwrap();
+        assert_eq!(Handle::hit(rect, WindowPoint::new(52.0, 58.0)), Some(Handle::BottomRight));
+        assert_eq!(Handle::hit(rect, WindowPoint::new(30.0, 21.0)), Some(Handle::Top));
+        assert_eq!(Handle::hit(rect, WindowPoint::new(30.0, 40.0)), None);
+
+        // 拖拽右边: 只改变宽度
+        drag(&mut controller, (50.0, 40.0), (80.0, 10.0));
+        assert_eq!(controller.selected().xywh(), Some((10, 20, 70, 40)));
+
+        // 左上角越过右下角: 选区翻转
+        drag(&mut controller, (10.0, 20.0), (100.0, 70.0));
+        assert_eq!(controller.selected().xywh(), Some((80, 60, 20, 10)));
+
+        // 鼠标悬停于控制点
+        controller.handle(InputEvent::CursorMoved { x: 100.0, y: 65.0 });
+        assert_eq!(controller.hovered_handle(), Some(Handle::Right));
+        assert!(!controller.is_creating());
+    }
+
+    #[test]
+    fn keyboard_only_selection() {
+        let mut controller = SelectionController::new(DesktopRect::new(-100, 0, 300, 200))
+            .with_screens(vec![DesktopRect::new(-100, 0, 100, 200), DesktopRect::new(0, 0, 200, 200)]);
+
+        // Tab: 从第一个屏幕的中心开始, 空格开始拖拽, Shift + 方向键步长为 10
+        press(&mut controller, Key::Tab, 1);
+        press(&mut controller, Key::Space, 1);
+        controller.handle(InputEvent::Modifiers { shift: true, alt: false, ctrl: false });
+        press(&mut controller, Key::Right, 2);
+        controller.handle(InputEvent::Modifiers { shift: false, alt: false, ctrl: false });
+        press(&mut controller, Key::Down, 3);
+        assert!(controller.is_creating());
+        press(&mut controller, Key::Space, 1);
+        assert_eq!(controller.selected().xywh(), Some((-50, 100, 20, 3)));
+
+        // Alt + 方向键: 移动右 / 下边, 不缩小到 0
+        controller.handle(InputEvent::Modifiers { shift: false, alt: true, ctrl: false });
+        press(&mut controller, Key::Right, 1);
+        press(&mut controller, Key::Down, 1);
+        assert_eq!(controller.selected().xywh(), Some((-50, 100, 21, 4)));
+        press(&mut controller, Key::Up, 4);
+        assert_eq!(controller.selected().xywh(), Some((-50, 100, 21, 1)));
+
+        // Ctrl + Alt + 方向键: 移动左 / 上边
+        controller.handle(InputEvent::Modifiers { shift: false, alt: true, ctrl: true });
+        press(&mut controller, Key::Left, 2);
+        press(&mut controller, Key::Up, 3);
+        assert_eq!(controller.selected().xywh(), Some((-52, 97, 23, 4)));
+        press(&mut controller, Key::Right, 1);
+        press(&mut controller, Key::Down, 4);
+        assert_eq!(controller.selected().xywh(), Some((-51, 100, 22, 1)));
+
+        // Tab / Shift + Tab 切换屏幕
+        controller.handle(InputEvent::Modifiers { shift: false, alt: false, ctrl: false });
+        press(&mut controller, Key::Tab, 1);
+        assert_eq!(controller.cursor(), Some(WindowPoint::new(200.0, 100.0)));
+        controller.handle(InputEvent::Modifiers { shift: true, alt: false, ctrl: false });
+        press(&mut controller, Key::Tab, 1);
+        assert_eq!(controller.cursor(), Some(WindowPoint::new(50.0, 100.0)));
+    }
+
+    /// 60x40 灰色屏幕 (x = -60) 上的白色窗口 (-50, 10, 30, 20)
+    fn snapping_controller() -> SelectionController {
+        let mut rgba = [200u8, 200, 200, 255].repeat(60 * 40);
+        for row in 10..30 {
+            for col in 10..40 {
+                let i = (row * 60 + col) * 4;
+                rgba[i..i + 4].copy_from_slice(&[255, 255, 255, 255]);
+            }
+        }
+        let capture = CaptureInfo {
+            screen_id: 1,
+            scale_factor: 1.0,
+            physical_x: -60,
+            physical_y: 0,
+            physical_width: 60,
+            physical_height: 40,
+            buffer: None,
+            stride: None,
+            rgba,
+        };
+
+        SelectionController::new(DesktopRect::new(-60, 0, 60, 40)).with_edges(EdgeMap::detect(&[capture]))
+    }
+
+    #[test]
+    fn click_detected_rect() {
+        let mut controller = snapping_controller();
+
+        controller.handle(InputEvent::CursorMoved { x: 15.0, y: 15.0 });
+        assert_eq!(controller.candidate().map(|rect| (rect.min, rect.max)), Some((WindowPoint::new(10.0, 10.0), WindowPoint::new(40.0, 30.0))));
+
+        // 单击 (允许轻微抖动) 选中识别的区域
+        drag(&mut controller, (15.0, 15.0), (16.0, 15.0));
+        assert_eq!(controller.selected().xywh(), Some((-50, 10, 30, 20)));
+        // 鼠标在选区内时不识别
+        assert_eq!(controller.candidate(), None);
+    }
+
+    #[test]
+    fn snap_to_edges() {
+        let mut controller = snapping_controller();
+
+        // 终点吸附到窗口的右下角, 起点不吸附
+        drag(&mut controller, (3.0, 3.0), (37.0, 28.0));
+        assert_eq!(controller.selected().xywh(), Some((-57, 3, 37, 27)));
+
+        // 移动时最近的边吸附到屏幕边界
+        drag(&mut controller, (20.0, 20.0), (39.0, 18.0));
+        assert_eq!(controller.selected().xywh(), Some((-37, 0, 37, 27)));
+
+        // 键盘移动不吸附
+        let mut controller = snapping_controller();
+        controller.handle(InputEvent::CursorMoved { x: 37.0, y: 5.0 });
+        press(&mut controller, Key::Space, 1);
+        press(&mut controller, Key::Down, 3);
+        press(&mut controller, Key::Left, 20);
+        press(&mut controller, Key::Space, 1);
+        assert_eq!(controller.selected().xywh(), Some((-43, 5, 20, 3)));
+    }
+
+    #[test]
+    fn select_whole_screen() {
+        let mut controller = SelectionController::new(DesktopRect::new(-100, 0, 300, 200))
+            .with_screens(vec![DesktopRect::new(-100, 0, 100, 200), DesktopRect::new(0, 0, 200, 150)]);
+
+        // 悬停时识别鼠标所在的屏幕, 单击选中
+        controller.handle(InputEvent::CursorMoved { x: 150.0, y: 100.0 });
+        assert_eq!(controller.hovered_screen(), Some(1));
+        drag(&mut controller, (150.0, 100.0), (150.0, 100.0));
+        assert_eq!(controller.selected().xywh(), Some((0, 0, 200, 150)));
+        assert_eq!(controller.selected_screen(), Some(1));
+
+        // 数字键选中第 n 个屏幕, 超出屏幕数量或不在 1 ~ 9 时忽略
+        press(&mut controller, Key::Digit(1), 1);
+        assert_eq!(controller.selected().xywh(), Some((-100, 0, 100, 200)));
+        assert_eq!(controller.selected_screen(), Some(0));
+        press(&mut controller, Key::Digit(3), 1);
+        assert_eq!(controller.selected_screen(), Some(0));
+        press(&mut controller, Key::Digit(0), 1);
+        assert_eq!(controller.selected_screen(), Some(0));
+
+        // 拖拽的选区不是整个屏幕
+        drag(&mut controller, (10.0, 10.0), (50.0, 50.0));
+        assert_eq!(controller.selected_screen(), None);
+        // 屏幕之外的区域不识别
+        controller.handle(InputEvent::CursorMoved { x: 150.0, y: 180.0 });
+        assert_eq!(controller.candidate(), None);
+    }
+
+    #[test]
+    fn right_click_and_double_click() {
+        let mut controller = SelectionController::new(DesktopRect::new(0, 0, 100, 100));
+        drag(&mut controller, (10.0, 10.0), (50.0, 50.0));
+
+        // 在选区外双击: 与按下相同, 开始绘制新选区
+        controller.handle(InputEvent::CursorMoved { x: 60.0, y: 60.0 });
+        controller.handle(InputEvent::DoubleClick);
+        assert!(controller.is_creating());
+        controller.handle(InputEvent::CursorMoved { x: 90.0, y: 80.0 });
+        controller.handle(InputEvent::Button { button: Button::Left, pressed: false });
+        assert_eq!(controller.selected().xywh(), Some((60, 60, 30, 20)));
+
+        // 右键: 清除选区, 再次右键时退出
+        controller.handle(InputEvent::Button { button: Button::Right, pressed: true });
+        assert_eq!((controller.selected().xywh(), controller.exit()), (None, None));
+        controller.handle(InputEvent::Button { button: Button::Right, pressed: true });
+        assert_eq!(controller.exit(), Some(ExitReason::Cancelled));
+
+        // 在选区内双击: 确认
+        let mut controller = SelectionController::new(DesktopRect::new(0, 0, 100, 100));
+        drag(&mut controller, (10.0, 10.0), (50.0, 50.0));
+        controller.handle(InputEvent::CursorMoved { x: 30.0, y: 30.0 });
+        controller.handle(InputEvent::DoubleClick);
+        assert_eq!(controller.exit(), Some(ExitReason::Confirmed));
+        assert_eq!(controller.selected().xywh(), Some((10, 10, 40, 40)));
+    }
+
+    #[test]
+    fn middle_drag_pans_selection() {
+        let mut controller = SelectionController::new(DesktopRect::new(0, 0, 100, 100));
+        // 没有选区时忽略
+        pan(&mut controller, (10.0, 10.0), (20.0, 20.0));
+        assert_eq!(controller.selected().xywh(), None);
+
+        // 在选区外按下也平移, 并保持在窗口内
+        drag(&mut controller, (10.0, 10.0), (50.0, 50.0));
+        pan(&mut controller, (70.0, 80.0), (90.0, 70.0));
+        assert_eq!(controller.selected().xywh(), Some((30, 0, 40, 40)));
+        pan(&mut controller, (0.0, 0.0), (100.0, 100.0));
+        assert_eq!(controller.selected().xywh(), Some((60, 60, 40, 40)));
+        assert!(!controller.is_creating());
+
+        // 拖拽中按下 / 释放另一个按键不结束拖拽
+        controller.handle(InputEvent::CursorMoved { x: 80.0, y: 80.0 });
+        controller.handle(InputEvent::Button { button: Button::Left, pressed: true });
+        controller.handle(InputEvent::CursorMoved { x: 70.0, y: 70.0 });
+        controller.handle(InputEvent::Button { button: Button::Middle, pressed: true });
+        controller.handle(InputEvent::Button { button: Button::Middle, pressed: false });
+        controller.handle(InputEvent::CursorMoved { x: 60.0, y: 60.0 });
+        controller.handle(InputEvent::Button { button: Button::Left, pressed: false });
+        assert_eq!(controller.selected().xywh(), Some((40, 40, 40, 40)));
+
+        controller.handle(InputEvent::Button { button: Button::Middle, pressed: true });
+        controller.handle(InputEvent::CursorMoved { x: 70.0, y: 70.0 });
+        controller.handle(InputEvent::Button { button: Button::Left, pressed: true });
+        controller.handle(InputEvent::Button { button: Button::Left, pressed: false });
+        controller.handle(InputEvent::CursorMoved { x: 80.0, y: 80.0 });
+        controller.handle(InputEvent::Button { button: Button::Middle, pressed: false });
+        assert_eq!(controller.selected().xywh(), Some((60, 60, 40, 40)));
+
+        // 有标注工具时不平移 (以免标注脱离选区)
+        controller.set_tool(Some(Tool::Spotlight));
+        pan(&mut controller, (70.0, 70.0), (10.0, 10.0));
+        assert_eq!(controller.selected().xywh(), Some((60, 60, 40, 40)));
+    }
+
+    #[test]
+    fn disable_gestures() {
+        let gestures = Gestures { right_click_clears: false, double_click_confirms: false, escape_clears_first: false, middle_drag_pans: false };
+        let mut controller = SelectionController::new(DesktopRect::new(0, 0, 100, 100)).with_gestures(gestures);
+        drag(&mut controller, (10.0, 10.0), (50.0, 50.0));
+
+        // 右键无效, 双击只是按下
+        controller.handle(InputEvent::Button { button: Button::Right, pressed: true });
+        controller.handle(InputEvent::CursorMoved { x: 30.0, y: 30.0 });
+        controller.handle(InputEvent::DoubleClick);
+        controller.handle(InputEvent::Button { button: Button::Left, pressed: false });
+        assert_eq!((controller.selected().xywh(), controller.exit()), (Some((10, 10, 40, 40)), None));
+
+        // 中键拖拽无效
+        pan(&mut controller, (30.0, 30.0), (60.0, 60.0));
+        assert_eq!(controller.selected().xywh(), Some((10, 10, 40, 40)));
+
+        // 'ESC' 直接退出
+        controller.handle(InputEvent::Key { key: Key::Escape, pressed: true });
+        assert_eq!((controller.selected().xywh(), controller.exit()), (None, Some(ExitReason::Cancelled)));
+    }
+
+    #[test]
+    fn track_double_click() {
+        let mut tracker = ClickTracker::default();
+        let start = Instant::now();
+        let press = InputEvent::Button { button: Button::Left, pressed: true };
+        let mut track = |event: InputEvent, ms: u64| tracker.track(event, start + Duration::from_millis(ms));
+
+        track(InputEvent::CursorMoved { x: 10.0, y: 10.0 }, 0);
+        assert_eq!(track(press, 0), press);
+        // 间隔及移动都足够小
+        track(InputEvent::CursorMoved { x: 11.0, y: 10.0 }, 100);
+        assert_eq!(track(press, 200), InputEvent::DoubleClick);
+        // 第三次按下重新计时
+        assert_eq!(track(press, 300), press);
+        // 间隔过长
+        assert_eq!(track(press, 900), press);
+        // 移动过远
+        track(InputEvent::CursorMoved { x: 20.0, y: 10.0 }, 950);
+        assert_eq!(track(press, 1000), press);
+    }
+
+    #[test]
+    fn pick_pixel() {
+        let mut controller = SelectionController::new(DesktopRect::new(-100, 0, 300, 200)).with_mode(SelectionMode::Color);
+
+        // 取色模式下不产生选区
+        controller.handle(InputEvent::CursorMoved { x: 10.6, y: 20.2 });
+        controller.handle(InputEvent::Button { button: Button::Left, pressed: true });
+
+        assert_eq!(controller.picked(), Some(DesktopPoint::new(-90, 20)));
+        assert_eq!(controller.rect(), None);
+        assert_eq!(controller.exit(), Some(ExitReason::Confirmed));
+
+        let mut controller = SelectionController::new(DesktopRect::new(0, 0, 100, 100)).with_mode(SelectionMode::Color);
+        controller.handle(InputEvent::CursorMoved { x: 10.0, y: 10.0 });
+        controller.handle(InputEvent::Key { key: Key::Escape, pressed: true });
+
+        assert_eq!(controller.picked(), None);
+        assert_eq!(controller.exit(), Some(ExitReason::Cancelled));
+    }
+
+    #[test]
+    fn annotate_selection() {
+        let mut controller = SelectionController::new(DesktopRect::new(100, 0, 800, 600));
+        drag(&mut controller, (10.0, 10.0), (300.0, 200.0));
+
+        click(&mut controller, ToolbarItem::Tool(Tool::Shape(Shape::Arrow)));
+        click(&mut controller, ToolbarItem::Color(2));
+        click(&mut controller, ToolbarItem::Width(2));
+        assert_eq!((controller.tool(), controller.style().color, controller.style().stroke_width), (Some(Tool::Shape(Shape::Arrow)), PALETTE[2], 8.0));
+        // 点击工具栏不改变选区
+        assert_eq!(controller.selected().xywh(), Some((110, 10, 290, 190)));
+
+        // 在选区内绘制 (终点约束在选区内, 桌面物理坐标), 单击不添加标注
+        drag(&mut controller, (50.0, 50.0), (400.0, 100.0));
+        drag(&mut controller, (60.0, 60.0), (61.0, 60.0));
+        let [Mark::Shape(arrow)] = controller.annotations() else { panic!("one arrow expected") };
+        assert_eq!((arrow.from, arrow.to), ([150.0, 50.0], [400.0, 100.0]));
+        assert_eq!(controller.selected().xywh(), Some((110, 10, 290, 190)));
+
+        // 选区外不绘制新选区
+        drag(&mut controller, (500.0, 300.0), (600.0, 400.0));
+        assert_eq!(controller.selected().xywh(), Some((110, 10, 290, 190)));
+
+        click(&mut controller, ToolbarItem::Undo);
+        assert!(controller.annotations().is_empty());
+
+        // 'ESC': 先取消工具, 再清除选区
+        drag(&mut controller, (50.0, 50.0), (80.0, 80.0));
+        press(&mut controller, Key::Escape, 1);
+        assert_eq!((controller.tool(), controller.annotations().len()), (None, 1));
+        press(&mut controller, Key::Escape, 1);
+        assert!(controller.annotations().is_empty() && controller.toolbar().is_empty());
+        assert_eq!(controller.exit(), None);
+
+        // 清除后可重新选中相同的区域; 没有字体时不能选择文字工具
+        drag(&mut controller, (10.0, 10.0), (300.0, 200.0));
+        assert_eq!(controller.selected().xywh(), Some((110, 10, 290, 190)));
+        click(&mut controller, ToolbarItem::Tool(Tool::Text));
+        assert_eq!(controller.tool(), None);
+    }
+
+    #[test]
+    fn redact_selection() {
+        let mut controller = SelectionController::new(DesktopRect::new(100, 0, 800, 600));
+        drag(&mut controller, (10.0, 10.0), (300.0, 200.0));
+
+        click(&mut controller, ToolbarItem::Tool(Tool::Redact(RedactKind::Fill)));
+        click(&mut controller, ToolbarItem::Color(3));
+        click(&mut controller, ToolbarItem::Width(2));
+
+        // 打码区域约束在选区内 (桌面物理坐标), 单击及过窄的区域不添加
+        drag(&mut controller, (50.0, 100.0), (400.0, 50.0));
+        drag(&mut controller, (60.0, 60.0), (61.0, 61.0));
+        drag(&mut controller, (300.0, 150.0), (200.0, 152.0));
+        let [Mark::Redact(fill)] = controller.annotations() else { panic!("one redaction expected") };
+        assert_eq!((fill.kind, fill.rect, fill.strength), (RedactKind::Fill, DesktopRect::new(150, 50, 250, 50), 24));
+        assert_eq!(fill.color, [0x1e, 0x90, 0xff]);
+
+        click(&mut controller, ToolbarItem::Undo);
+        assert!(controller.annotations().is_empty());
+        assert_eq!(controller.selected().xywh(), Some((110, 10, 290, 190)));
+    }
+
+    #[test]
+    fn reselect_clears_annotations() {
+        let screens = vec![DesktopRect::new(100, 0, 400, 600), DesktopRect::new(500, 0, 400, 600)];
+        let mut controller = SelectionController::new(DesktopRect::new(100, 0, 800, 600)).with_screens(screens);
+        let annotate = |controller: &mut SelectionController, from: (f64, f64), to: (f64, f64)| {
+            click(controller, ToolbarItem::Tool(Tool::Shape(Shape::Arrow)));
+            drag(controller, from, to);
+            press(controller, Key::Escape, 1);
+            assert_eq!((controller.tool(), controller.annotations().len()), (None, 1));
+        };
+
+        // 在选区外绘制新选区
+        drag(&mut controller, (10.0, 10.0), (300.0, 200.0));
+        annotate(&mut controller, (50.0, 50.0), (100.0, 100.0));
+        drag(&mut controller, (400.0, 300.0), (600.0, 500.0));
+        assert_eq!(controller.selected().xywh(), Some((500, 300, 200, 200)));
+        assert!(controller.annotations().is_empty());
+
+        // 数字键选中屏幕
+        annotate(&mut controller, (450.0, 350.0), (500.0, 400.0));
+        press(&mut controller, Key::Digit(1), 1);
+        assert_eq!(controller.selected().xywh(), Some((100, 0, 400, 600)));
+        assert!(controller.annotations().is_empty());
+
+        // 单击选中自动识别的区域
+        annotate(&mut controller, (100.0, 100.0), (200.0, 200.0));
+        drag(&mut controller, (600.0, 300.0), (600.0, 300.0));
+        assert_eq!(controller.selected().xywh(), Some((500, 0, 400, 600)));
+        assert!(controller.annotations().is_empty());
+    }
+
+    #[test]
+    fn renumber_step_markers() {
+        let mut controller = SelectionController::new(DesktopRect::new(0, 0, 800, 600));
+        drag(&mut controller, (10.0, 10.0), (300.0, 200.0));
+        click(&mut controller, ToolbarItem::Tool(Tool::Marker));
+
+        // 单击依次放置三个标记, 再单击中间的标记删除之
+        for x in [50.0, 120.0, 190.0] {
+            drag(&mut controller, (x, 100.0), (x, 100.0));
+        }
+        assert_eq!(controller.annotations().len(), 3);
+        drag(&mut controller, (122.0, 101.0), (122.0, 101.0));
+        let [Mark::Marker(first), Mark::Marker(last)] = controller.annotations() else { panic!("two markers expected") };
+        assert_eq!((first.center, last.center), ([50.0, 100.0], [190.0, 100.0]));
+
+        // 之后的标记依次减 1: 绘制为 "1, 2"
+        let rect = DesktopRect::new(10, 10, 290, 190);
+        let white = [255u8; 4].repeat((rect.width * rect.height) as usize);
+        let numbered = |numbers: [usize; 2]| {
+            let mut rgba = white.clone();
+            draw_annotations(&mut rgba, rect, &[first.annotations(numbers[0]), last.annotations(numbers[1])].concat());
+            rgba
+        };
+        let mut rgba = white.clone();
+        draw_marks(&mut rgba, rect, controller.annotations(), None);
+        assert!(rgba == numbered([1, 2]) && rgba != numbered([1, 3]));
+    }
+
+    #[test]
+    fn reselect_over_old_marks() {
+        let mut controller = SelectionController::new(DesktopRect::new(0, 0, 800, 600));
+        drag(&mut controller, (10.0, 10.0), (300.0, 200.0));
+        click(&mut controller, ToolbarItem::Tool(Tool::Spotlight));
+        drag(&mut controller, (50.0, 50.0), (100.0, 100.0));
+        click(&mut controller, ToolbarItem::Tool(Tool::Marker));
+        drag(&mut controller, (150.0, 100.0), (150.0, 100.0));
+        drag(&mut controller, (200.0, 100.0), (200.0, 100.0));
+        press(&mut controller, Key::Escape, 1);
+        assert_eq!(controller.annotations().len(), 3);
+
+        // 在选区外绘制覆盖旧标注的新选区: 不再变暗, 新的标记从 1 开始编号
+        drag(&mut controller, (400.0, 300.0), (5.0, 5.0));
+        click(&mut controller, ToolbarItem::Tool(Tool::Marker));
+        drag(&mut controller, (250.0, 150.0), (250.0, 150.0));
+        let [Mark::Marker(marker)] = controller.annotations() else { panic!("one marker expected") };
+
+        let rect = controller.desktop_rect().unwrap();
+        assert!(dimmed_rects(rect, controller.annotations()).is_empty());
+        let white = [255u8; 4].repeat((rect.width * rect.height) as usize);
+        let (mut rgba, mut first) = (white.clone(), white);
+        draw_marks(&mut rgba, rect, controller.annotations(), None);
+        draw_annotations(&mut first, rect, &marker.annotations(1));
+        assert!(rgba == first);
+    }
+
+    #[test]
+    fn draw_highlighter_brush() {
+        let mut controller = SelectionController::new(DesktopRect::new(100, 0, 800, 600));
+        drag(&mut controller, (10.0, 10.0), (300.0, 200.0));
+        click(&mut controller, ToolbarItem::Tool(Tool::Highlight));
+        click(&mut controller, ToolbarItem::Color(3));
+
+        // 笔迹随鼠标延伸 (桌面物理坐标), 松开时保存
+        controller.handle(InputEvent::CursorMoved { x: 50.0, y: 50.0 });
+        controller.handle(InputEvent::Button { button: Button::Left, pressed: true });
+        controller.handle(InputEvent::CursorMoved { x: 50.5, y: 50.0 });
+        controller.handle(InputEvent::CursorMoved { x: 120.0, y: 50.0 });
+        controller.handle(InputEvent::CursorMoved { x: 120.0, y: 90.0 });
+        assert_eq!(controller.highlighting().map(|highlight| highlight.points), Some(vec![[150.0, 50.0], [220.0, 50.0], [220.0, 90.0]]));
+        controller.handle(InputEvent::Button { button: Button::Left, pressed: false });
+        assert_eq!(controller.highlighting(), None);
+
+        let [Mark::Highlight(highlight)] = controller.annotations() else { panic!("one highlight expected") };
+        let [r, g, b, _] = PALETTE[3];
+        assert_eq!((highlight.width, highlight.color), (highlight_width(STROKE_WIDTHS[1]), [r, g, b, HIGHLIGHT_ALPHA]));
+
+        // 单击不添加笔迹
+        drag(&mut controller, (60.0, 60.0), (61.0, 61.0));
+        assert_eq!(controller.annotations().len(), 1);
+    }
+
+    #[test]
+    fn draw_spotlight() {
+        let mut controller = SelectionController::new(DesktopRect::new(100, 0, 800, 600));
+        drag(&mut controller, (10.0, 10.0), (300.0, 200.0));
+        click(&mut controller, ToolbarItem::Tool(Tool::Spotlight));
+
+        // 拖拽中只有区域 (约束在选区内), 松开后选区内聚光灯之外变暗; 过窄的区域不添加
+        controller.handle(InputEvent::CursorMoved { x: 50.0, y: 100.0 });
+        controller.handle(InputEvent::Button { button: Button::Left, pressed: true });
+        controller.handle(InputEvent::CursorMoved { x: 400.0, y: 50.0 });
+        assert_eq!(controller.spotlighting(), Some(DesktopRect::new(150, 50, 250, 50)));
+        controller.handle(InputEvent::Button { button: Button::Left, pressed: false });
+        drag(&mut controller, (60.0, 60.0), (200.0, 61.0));
+
+        assert_eq!(controller.annotations(), &[Mark::Spotlight(DesktopRect::new(150, 50, 250, 50))]);
+        let selection = controller.desktop_rect().unwrap();
+        let dimmed: u32 = dimmed_rects(selection, controller.annotations()).iter().map(|rect| rect.width * rect.height).sum();
+        assert_eq!(dimmed, 290 * 190 - 250 * 50);
+    }
+
+    /// 需要 CJK 字体, 如: `sudo apt-get install fonts-noto-cjk`
+    #[test]
+    #[ignore]
+    fn edit_text_labels() {
+        let font = text_font().expect("no CJK font").clone();
+        let mut controller = SelectionController::new(DesktopRect::new(100, 0, 800, 600)).with_font(Some(font));
+        drag(&mut controller, (10.0, 10.0), (300.0, 200.0));
+
+        let type_text = |controller: &mut SelectionController, text: &str| {
+            for ch in text.chars() {
+                controller.handle(InputEvent::Character { ch });
+            }
+        };
+        click(&mut controller, ToolbarItem::Tool(Tool::Text));
+        assert_eq!(controller.tool(), Some(Tool::Text));
+
+        // 单击放置文字, 编辑时按键 (如: 数字键、方向键) 只用于输入
+        drag(&mut controller, (50.0, 50.0), (50.0, 50.0));
+        type_text(&mut controller, "截图12");
+        press(&mut controller, Key::Left, 1);
+        press(&mut controller, Key::Backspace, 1);
+        assert_eq!(controller.editing().map(|label| label.text.as_str()), Some("截图1"));
+        assert_eq!(controller.selected().xywh(), Some((110, 10, 290, 190)));
+
+        // 编辑中修改样式同时修改文字; 'Enter' 结束编辑
+        click(&mut controller, ToolbarItem::Color(2));
+        press(&mut controller, Key::Enter, 1);
+        let [Mark::Text(label)] = controller.annotations() else { panic!("one label expected") };
+        assert_eq!((label.position, label.color, label.text.as_str()), (DesktopPoint::new(150, 50), PALETTE[2], "截图1"));
+        assert_eq!((controller.editing(), controller.exit()), (None, None));
+
+        // 单击已有的文字继续编辑; 空白的文字在结束编辑时移除
+        drag(&mut controller, (52.0, 52.0), (52.0, 52.0));
+        type_text(&mut controller, "!");
+        drag(&mut controller, (250.0, 150.0), (250.0, 150.0));
+        press(&mut controller, Key::Escape, 1);
+        let [Mark::Text(label)] = controller.annotations() else { panic!("one label expected") };
+        assert_eq!(label.text, "截图1!");
+        assert_eq!(controller.tool(), Some(Tool::Text));
+    }
+
+    #[test]
+    fn cancel_and_close() {
+        let mut controller = SelectionController::new(DesktopRect::new(0, 0, 100, 100));
+        drag(&mut controller, (10.0, 10.0), (20.0, 20.0));
+
+        // 'ESC': 先清除选区, 再次按下时退出
+        controller.handle(InputEvent::Key { key: Key::Escape, pressed: true });
+        assert_eq!((controller.rect(), controller.selected().xywh(), controller.exit()), (None, None, None));
+        controller.handle(InputEvent::Key { key: Key::Escape, pressed: true });
+
+        assert_eq!(controller.exit(), Some(ExitReason::Cancelled));
+        assert_eq!(controller.selected().xywh(), None);
+
+        // 结束后忽略后续事件
+        controller.handle(InputEvent::Key { key: Key::Enter, pressed: true });
+        assert_eq!(controller.exit(), Some(ExitReason::Cancelled));
+
+        let mut controller = SelectionController::new(DesktopRect::new(0, 0, 100, 100));
+        drag(&mut controller, (10.0, 10.0), (20.0, 20.0));
+        controller.handle(InputEvent::CloseRequested);
+
+        assert_eq!(controller.exit(), Some(ExitReason::Closed));
+        assert_eq!(controller.selected().xywh(), Some((10, 10, 10, 10)));
+
+        // 超时不确认已有的选区
+        let mut controller = SelectionController::new(DesktopRect::new(0, 0, 100, 100));
+        drag(&mut controller, (10.0, 10.0), (20.0, 20.0));
+        controller.handle(InputEvent::TimedOut);
+        controller.handle(InputEvent::Key { key: Key::Enter, pressed: true });
+
+        assert_eq!(controller.exit(), Some(ExitReason::TimedOut));
+    }
+}
@@ -1,68 +1,57 @@
+use std::borrow::Cow;
 use std::cell::RefCell;
 use std::rc::Rc;
-use glium::glutin::event::{ElementState, Event, KeyboardInput, MouseButton, VirtualKeyCode, WindowEvent};
+use ab_glyph::FontArc;
+use glium::glutin::dpi::PhysicalPosition;
+use glium::glutin::event::{Event, Ime, WindowEvent};
 use glium::glutin::event_loop::{ControlFlow, EventLoop};
 use glium::{Display, Surface, Texture2d};
 use imgui::{Context, ImColor32, StyleColor, TextureId, Textures};
 use imgui_glium_renderer::{Renderer, Texture};
 use imgui_winit_support::{HiDpiMode, WinitPlatform};
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use glium::backend::Facade;
-use glium::glutin::dpi::PhysicalPosition;
 use glium::glutin::platform::run_return::EventLoopExtRunReturn;
-use glium::texture::{RawImage2d};
-use glium::uniforms::SamplerBehavior;
+use glium::texture::{ClientFormat, RawImage2d};
+use glium::uniforms::{MagnifySamplerFilter, SamplerBehavior};
 use crate::declares::CaptureInfo;
-use crate::imgui_impl::prefab::{BoundingBox, create_screen_pair, SelectedArea};
-use crate::utils::clamp;
-
-/// 蒙层的颜色
-const MASK_COLOR: ImColor32 = ImColor32::from_rgba(0x00, 0x00, 0x00, 0x66);
-
-/// 计算范围约束后的点位
-fn calc_constrained_point(physical_point: PhysicalPosition<f64>, bounding: BoundingBox) -> [f32; 2] {
-    let (x, y, w, h) = bounding;
-
-    [
-        clamp(physical_point.x as i32, x, x + w) as f32,
-        clamp(physical_point.y as i32, y, y + h) as f32,
-    ]
-}
-
-/// 给定任意两点计算其包围的矩形的 '左上点' 和 '右下点',
-/// 即: \[xmin, ymin, xmax, ymax\]
-fn calc_select_area(p1: [f32; 2], p2: [f32; 2]) -> [f32; 4] {
-    let [x1, y1] = p1;
-    let [x2, y2] = p2;
-
-    [
-        if x1 < x2 { x1 } else { x2 },
-        if y1 < y2 { y1 } else { y2 },
-        if x1 > x2 { x1 } else { x2 },
-        if y1 > y2 { y1 } else { y2 },
-    ]
-}
-
-/// 给定大矩形(Rect1)宽高(wh)和小矩形(Rect2)任意两点(p1, p2),
+use crate::error::CaptureError;
+use crate::geometry::{DesktopRect, WindowPoint, WindowRect};
+use crate::imgui_impl::controller::{ClickTracker, InputEvent, SelectionController, SelectionMode};
+use crate::imgui_impl::draw::{draw_annotation, draw_candidate, draw_crosshair, draw_highlight, draw_label, draw_loupe, draw_marker, draw_redacting, draw_selection, draw_size_label, draw_text_label, draw_toolbar};
+use crate::imgui_impl::prefab::create_screen_pair;
+use crate::utils::annotate::{dimmed_rects, redactions, Highlight, Mark, DIM_COLOR};
+use crate::utils::{crop_captures, pixel_at};
+use crate::utils::redact::{apply_redactions, Redaction};
+use crate::utils::text::TextLabel;
+
+/// 蒙层的颜色 (同导出时聚光灯之外变暗的颜色)
+const MASK_COLOR: ImColor32 = ImColor32::from_rgba(DIM_COLOR[0], DIM_COLOR[1], DIM_COLOR[2], DIM_COLOR[3]);
+
+/// 屏幕纹理的 textureId + 屏幕区域 (桌面物理坐标)
+pub type ScreenTexture = (TextureId, DesktopRect);
+
+/// 给定大矩形(Rect1)宽高(wh)和小矩形(Rect2),
 /// 返回 'Rect2 - Rect1' 区域的四个矩形的 '左上点' 和 '右下点'
-fn calc_bounding_rect(wh: [f32; 2], p1: [f32; 2], p2: [f32; 2]) -> [[[f32; 2]; 2]; 4] {
+fn calc_bounding_rect(wh: [f32; 2], rect: WindowRect) -> [[[f32; 2]; 2]; 4] {
     let [w, h] = wh;
-    let [x1, y1, x2, y2] = calc_select_area(p1, p2);
+    let [x1, y1] = rect.min.to_array();
+    let [x2, y2] = rect.max.to_array();
 
-    return [
+    [
         [[0.0, 0.0], [x1, y2]],
         [[x1, 0.0], [w, y1]],
         [[0.0, y2], [x2, h]],
         [[x2, y1], [w, h]],
-    ];
+    ]
 }
 
 /// 载入图像纹理
 fn load_screen_images(
     gl_ctx: &impl Facade,
     renderer_textures: &mut Textures<Texture>,
-    screen_infos: Vec<CaptureInfo>,
-) -> Vec<(TextureId, i32, i32, u32, u32)> {
+    screen_infos: &[CaptureInfo],
+) -> Result<Vec<ScreenTexture>, CaptureError> {
     let mut texture_infos = vec![];
 
     for screen_info in screen_infos {
@@ -72,28 +61,184 @@ fn load_screen_images(
             rgba,
             ..
         } = screen_info;
-        let raw = RawImage2d::from_raw_rgba(rgba, (physical_width, physical_height));
-        let gl_texture = Texture2d::new(gl_ctx, raw).unwrap();
+        // 直接借用原始 rgba 阵列, 原始截图保留用于最终裁剪
+        let raw = RawImage2d {
+            data: Cow::Borrowed(rgba.as_slice()),
+            width: *physical_width,
+            height: *physical_height,
+            format: ClientFormat::U8U8U8U8,
+        };
+        let gl_texture = Texture2d::new(gl_ctx, raw)
+            .map_err(|err| CaptureError::Window(err.to_string()))?;
         let texture = Texture {
             texture: Rc::new(gl_texture),
-            sampler: SamplerBehavior::default(),
+            // 放大镜中按像素显示, 不做插值
+            sampler: SamplerBehavior {
+                magnify_filter: MagnifySamplerFilter::Nearest,
+                ..Default::default()
+            },
         };
         let texture_id = renderer_textures.insert(texture);
         texture_infos.push((
             texture_id,
-            physical_x,
-            physical_y,
-            physical_width,
-            physical_height,
+            DesktopRect::new(*physical_x, *physical_y, *physical_width, *physical_height),
         ));
     }
 
-    texture_infos
+    Ok(texture_infos)
+}
+
+/// 载入标注生成的 rgba 图像纹理
+fn load_rgba_texture(gl_ctx: &impl Facade, renderer_textures: &mut Textures<Texture>, rgba: Vec<u8>, width: u32, height: u32) -> Result<TextureId, CaptureError> {
+    let raw = RawImage2d {
+        data: Cow::Owned(rgba),
+        width,
+        height,
+        format: ClientFormat::U8U8U8U8,
+    };
+    let gl_texture = Texture2d::new(gl_ctx, raw)
+        .map_err(|err| CaptureError::Window(err.to_string()))?;
+
+    Ok(renderer_textures.insert(Texture {
+        texture: Rc::new(gl_texture),
+        sampler: SamplerBehavior {
+            magnify_filter: MagnifySamplerFilter::Nearest,
+            ..Default::default()
+        },
+    }))
+}
+
+/// 文字标注的纹理, 按文字框的内容缓存: (文字, textureId, 图像的宽高, 光标的位置)
+#[derive(Default)]
+struct TextTextures {
+    entries: Vec<(TextLabel, TextureId, [u32; 2], [f32; 2])>,
+}
+
+impl TextTextures {
+    /// 与当前的文字标注同步: 移除不再使用的纹理, 载入新的文字框图像
+    fn sync(&mut self, gl_ctx: &impl Facade, renderer_textures: &mut Textures<Texture>, font: &FontArc, labels: &[&TextLabel]) {
+        self.entries.retain(|(label, texture_id, ..)| {
+            let used = labels.contains(&label);
+            if !used {
+                renderer_textures.remove(*texture_id);
+            }
+            used
+        });
+
+        for &label in labels {
+            if self.find(label).is_some() {
+                continue;
+            }
+
+            let bitmap = label.render(font);
+            match load_rgba_texture(gl_ctx, renderer_textures, bitmap.rgba, bitmap.width, bitmap.height) {
+                Ok(texture_id) => self.entries.push((label.clone(), texture_id, [bitmap.width, bitmap.height], bitmap.caret)),
+                Err(err) => println!("Failed to load text texture: {err}"),
+            }
+        }
+    }
+
+    /// 文字框的 (textureId, 图像的宽高, 光标的位置)
+    fn find(&self, label: &TextLabel) -> Option<(TextureId, [u32; 2], [f32; 2])> {
+        self.entries
+            .iter()
+            .find(|(other, ..)| other == label)
+            .map(|&(_, texture_id, size, caret)| (texture_id, size, caret))
+    }
+}
+
+/// 荧光笔笔迹的纹理, 按笔迹缓存: (笔迹, textureId, 图像的区域 (桌面物理坐标))
+#[derive(Default)]
+struct HighlightTextures {
+    entries: Vec<(Highlight, TextureId, DesktopRect)>,
+}
+
+impl HighlightTextures {
+    /// 与当前的笔迹 (含正在绘制的) 同步: 移除不再使用的纹理, 载入新的笔迹图像
+    fn sync(&mut self, gl_ctx: &impl Facade, renderer_textures: &mut Textures<Texture>, highlights: &[&Highlight]) {
+        self.entries.retain(|(highlight, texture_id, _)| {
+            let used = highlights.contains(&highlight);
+            if !used {
+                renderer_textures.remove(*texture_id);
+            }
+            used
+        });
+
+        for &highlight in highlights {
+            if self.find(highlight).is_some() {
+                continue;
+            }
+
+            let Some((bounds, rgba)) = highlight.render() else { continue };
+            match load_rgba_texture(gl_ctx, renderer_textures, rgba, bounds.width, bounds.height) {
+                Ok(texture_id) => self.entries.push((highlight.clone(), texture_id, bounds)),
+                Err(err) => println!("Failed to load highlight texture: {err}"),
+            }
+        }
+    }
+
+    /// 笔迹的 (textureId, 图像的区域)
+    fn find(&self, highlight: &Highlight) -> Option<(TextureId, DesktopRect)> {
+        self.entries
+            .iter()
+            .find(|(other, ..)| other == highlight)
+            .map(|&(_, texture_id, bounds)| (texture_id, bounds))
+    }
+}
+
+/// 打码预览的纹理: 各打码区域 (与选区相交的部分) 的外接矩形内, 按导出时的方式打码后的截图
+#[derive(Default)]
+struct RedactTexture {
+    /// 生成纹理时的打码区域及选区 (桌面物理坐标)
+    source: (Vec<Redaction>, Option<DesktopRect>),
+    /// textureId + 纹理的区域 (桌面物理坐标)
+    texture: Option<(TextureId, DesktopRect)>,
+}
+
+impl RedactTexture {
+    /// 打码区域或选区变化时重新生成纹理
+    fn sync(
+        &mut self,
+        gl_ctx: &impl Facade,
+        renderer_textures: &mut Textures<Texture>,
+        captures: &[CaptureInfo],
+        redactions: Vec<Redaction>,
+        selection: Option<DesktopRect>,
+    ) {
+        let source = (redactions, selection);
+        if self.source == source {
+            return;
+        }
+        if let Some((texture_id, _)) = self.texture.take() {
+            renderer_textures.remove(texture_id);
+        }
+
+        let (redactions, selection) = &source;
+        let bounds = selection.and_then(|selection| {
+            redactions
+                .iter()
+                .filter_map(|redaction| redaction.rect.intersect(&selection))
+                .reduce(|a, b| {
+                    let (x1, y1) = (a.x.min(b.x), a.y.min(b.y));
+                    let (x2, y2) = ((a.x + a.width as i32).max(b.x + b.width as i32), (a.y + a.height as i32).max(b.y + b.height as i32));
+                    DesktopRect::new(x1, y1, (x2 - x1) as u32, (y2 - y1) as u32)
+                })
+        });
+        if let Some(bounds) = bounds {
+            let mut rgba = crop_captures(captures, bounds.xywh());
+            apply_redactions(&mut rgba, bounds, redactions);
+            match load_rgba_texture(gl_ctx, renderer_textures, rgba, bounds.width, bounds.height) {
+                Ok(texture_id) => self.texture = Some((texture_id, bounds)),
+                Err(err) => println!("Failed to load redaction texture: {err}"),
+            }
+        }
+        self.source = source;
+    }
 }
 
-pub struct System {
-    /// 主事件循环
-    pub event_loop: EventLoop<()>,
+pub struct System<'a> {
+    /// 主事件循环 (由 UI 线程持有)
+    pub event_loop: &'a mut EventLoop<()>,
     /// winit 平台相关
     pub platform: WinitPlatform,
     /// imgui 上下文
@@ -103,24 +248,21 @@ pub struct System {
     /// gilum 渲染
     pub renderer: Renderer,
 
-    // 点位坐标为 physical 坐标系
-    pub physical_xywh: BoundingBox,
+    /// 窗口覆盖的区域 (桌面物理坐标), 窗口内的点位均为相对其左上角的窗口物理坐标
+    pub bounding: DesktopRect,
     /// 各屏幕的纹理id及位置信息
-    pub screen_texture_list: Vec<(TextureId, i32, i32, u32, u32)>,
-    /// 是否正在绘制矩形
-    pub is_drawing_rect: bool,
-    /// 绘制的起点
-    start_point: Option<[f32; 2]>,
-    /// 绘制的终点
-    end_point: Option<[f32; 2]>,
-    /// 当前的鼠标位置
-    curr_point: Option<[f32; 2]>,
+    pub screen_texture_list: Vec<ScreenTexture>,
+    /// 原始截图, 用于读取放大镜中的像素颜色
+    pub captures: &'a [CaptureInfo],
+    /// 选择的状态
+    pub controller: SelectionController,
+    /// 选择的时限, 从窗口开始运行时计时
+    pub timeout: Option<Duration>,
 }
 
-impl System {
-    pub fn new(physical_xywh: BoundingBox, captures: Vec<CaptureInfo>) -> System {
-        // 事件循环
-        let event_loop = EventLoop::new();
+impl<'a> System<'a> {
+    pub fn new(event_loop: &'a mut EventLoop<()>, captures: &'a [CaptureInfo], controller: SelectionController, timeout: Option<Duration>) -> Result<System<'a>, CaptureError> {
+        let bounding = controller.bounding();
 
         // imgui 上下文
         let mut imgui = Context::create();
@@ -132,56 +274,60 @@ impl System {
         // display 和 renderer
         let (display, mut renderer) = create_screen_pair(
             &mut imgui,
-            &event_loop,
-            physical_xywh,
-        );
+            event_loop,
+            bounding,
+        )?;
 
         // 计算获取各屏幕图像的 textureId + xywh
         let screen_texture_list = load_screen_images(
             display.get_context(),
             renderer.textures(),
             captures,
-        );
+        )?;
 
         // 窗口附加到 winit
         platform.attach_window(
             imgui.io_mut(),
             display.gl_window().window(),
-            HiDpiMode::Default,
-            // HiDpiMode::Locked(1.0),
+            // 锁定为 1.0: imgui 坐标即窗口物理坐标, 不受各屏幕 scale_factor 影响
+            HiDpiMode::Locked(1.0),
         );
 
-        System {
+        // 允许输入法, 以便在文字标注中输入中文
+        display.gl_window().window().set_ime_allowed(true);
+
+        Ok(System {
             event_loop,
             platform,
             imgui,
             display,
             renderer,
-            physical_xywh,
+            bounding,
             screen_texture_list,
-            is_drawing_rect: false,
-            start_point: None,
-            end_point: None,
-            curr_point: None,
-        }
+            captures,
+            controller,
+            timeout,
+        })
     }
 
-    pub fn run(self) -> (i32, SelectedArea) {
+    pub fn run(self) -> (i32, SelectionController) {
         let System {
-            mut event_loop,
+            event_loop,
             mut platform,
             mut imgui,
             display,
             mut renderer,
-            physical_xywh,
+            bounding,
             screen_texture_list,
-            mut is_drawing_rect,
-            mut start_point,
-            mut end_point,
-            mut curr_point,
+            captures,
+            controller,
+            timeout,
         } = self;
+        // 文字标注使用的字体
+        let font = controller.font().cloned();
+
         // 存储选择结果
-        let result: Rc<RefCell<SelectedArea>> = Rc::new(RefCell::new(SelectedArea::empty()));
+        let result: Rc<RefCell<SelectionController>> = Rc::new(RefCell::new(controller));
 
         // 设置窗口背景色黑色
         imgui.style_mut().colors[StyleColor::WindowBg as usize] = [0.0, 0.0, 0.0, 1.0];
@@ -190,8 +336,20 @@ impl System {
             // 用于帧同步
             let mut last_frame = Instant::now();
 
+            // 超过时限时结束选择
+            let deadline = timeout.map(|timeout| last_frame + timeout);
+
             // 用于保存结果
-            let select_area = result.clone();
+            let controller = result.clone();
+
+            // 用于识别双击
+            let mut click_tracker = ClickTracker::default();
+
+            // 文字标注 / 荧光笔 / 打码预览的纹理, 及输入法候选框的位置
+            let mut text_textures = TextTextures::default();
+            let mut highlight_textures = HighlightTextures::default();
+            let mut redact_texture = RedactTexture::default();
+            let mut ime_position = None;
 
             move |event, _, control_flow| match event {
                 // region 和窗口事件相关的逻辑 (在此处更新 imgui 内部时间系统)
@@ -199,6 +357,11 @@ impl System {
                     let now = Instant::now();
                     imgui.io_mut().update_delta_time(now - last_frame);
                     last_frame = now;
+
+                    if deadline.is_some_and(|deadline| now >= deadline) {
+                        controller.borrow_mut().handle(InputEvent::TimedOut);
+                        *control_flow = ControlFlow::Exit;
+                    }
                 }
                 // endregion
                 // region 主事件队列被清空 ==> 通知绘制 ui
@@ -212,14 +375,51 @@ impl System {
                 // endregion
                 // region 绘制 ui
                 Event::RedrawRequested(_) => {
+                    // 载入文字标注的纹理
+                    if let Some(font) = &font {
+                        let controller_ref = controller.borrow();
+                        let labels: Vec<&TextLabel> = controller_ref
+                            .annotations()
+                            .iter()
+                            .filter_map(|mark| match mark {
+                                Mark::Text(label) => Some(label),
+                                _ => None,
+                            })
+                            .collect();
+                        text_textures.sync(display.get_context(), renderer.textures(), font, &labels);
+                    }
+
+                    // 载入荧光笔笔迹的纹理 (含正在绘制的)
+                    {
+                        let controller_ref = controller.borrow();
+                        let drawing = controller_ref.highlighting();
+                        let highlights: Vec<&Highlight> = controller_ref
+                            .annotations()
+                            .iter()
+                            .filter_map(|mark| match mark {
+                                Mark::Highlight(highlight) => Some(highlight),
+                                _ => None,
+                            })
+                            .chain(drawing.as_ref())
+                            .collect();
+                        highlight_textures.sync(display.get_context(), renderer.textures(), &highlights);
+                    }
+
+                    // 载入打码预览的纹理
+                    {
+                        let controller_ref = controller.borrow();
+                        let selection = controller_ref.desktop_rect();
+                        redact_texture.sync(display.get_context(), renderer.textures(), captures, redactions(controller_ref.annotations()), selection);
+                    }
+
                     // 开启新的一帧
                     let ui = imgui.new_frame();
-                    let (x, y, w, h) = physical_xywh;
+                    let (w, h) = (bounding.width, bounding.height);
 
                     // region 绘制屏幕图像
                     // 在目标位置绘制各屏幕图像
                     ui.window("screen_images")
-                        .position([x as f32, y as f32], imgui::Condition::Always)
+                        .position([0.0, 0.0], imgui::Condition::Always)
                         .size([w as f32, h as f32], imgui::Condition::Always)
                         .title_bar(false)
                         .resizable(false)
@@ -227,25 +427,21 @@ impl System {
                         .build(|| {
                             let draw_list = ui.get_window_draw_list();
 
-                            for screen_texture in &screen_texture_list {
-                                let (tid, sx, sy, sw, sh) = screen_texture;
+                            for (tid, screen_rect) in &screen_texture_list {
+                                let rect = screen_rect.to_window(bounding.origin());
                                 draw_list
-                                    .add_image(
-                                        tid.to_owned(),
-                                        [*sx as f32, *sy as f32],
-                                        [*sx as f32 + *sw as f32, *sy as f32 + *sh as f32],
-                                    )
+                                    .add_image(tid.to_owned(), rect.min.to_array(), rect.max.to_array())
                                     .build();
                             }
                         });
                     // endregion
 
                     // region 交互绘制矩形
-                    // 终点: 绘制中(当前点) / 绘制结束(结束点)
-                    let rect_end = if is_drawing_rect { curr_point } else { end_point };
+                    let controller_ref = controller.borrow();
+                    let selection_rect = controller_ref.rect();
                     // 透明窗口装填矩形选框交互功能
                     ui.window("bounding_mask")
-                        .position([x as f32, y as f32], imgui::Condition::Always)
+                        .position([0.0, 0.0], imgui::Condition::Always)
                         .size([w as f32, h as f32], imgui::Condition::Always)
                         .title_bar(false)
                         .resizable(false)
@@ -253,23 +449,120 @@ impl System {
                         .build(|| {
                             let draw_list = ui.get_window_draw_list();
 
-                            // 有选区: 绘制选区外蒙层
-                            if start_point.is_some() && rect_end.is_some() {
-                                for rect in calc_bounding_rect([w as f32, h as f32], start_point.unwrap(), rect_end.unwrap()) {
+                            // 有选区 (或鼠标下自动识别的区域): 绘制选区外蒙层
+                            if let Some(selection_rect) = selection_rect.or(controller_ref.candidate()) {
+                                for rect in calc_bounding_rect([w as f32, h as f32], selection_rect) {
                                     draw_list
                                         .add_rect(rect[0], rect[1], MASK_COLOR)
                                         .filled(true)
                                         .build();
                                 }
                             }
-                            // 无选区: 绘制全屏蒙层
-                            else {
+                            // 无选区: 绘制全屏蒙层 (取色时不绘制, 以免影响颜色)
+                            else if controller_ref.mode() == SelectionMode::Region {
                                 draw_list
                                     .add_rect([0.0, 0.0], [w as f32, h as f32], MASK_COLOR)
                                     .filled(true)
                                     .build();
                             }
+
+                            // 鼠标下自动识别的区域, 单击即选中
+                            if let Some(candidate) = controller_ref.candidate() {
+                                draw_candidate(&draw_list, candidate);
+
+                                if let Some(window) = controller_ref.hovered_window() {
+                                    draw_label(ui, &draw_list, candidate, &window.title);
+                                } else if let Some(index) = controller_ref.hovered_screen() {
+                                    draw_label(ui, &draw_list, candidate, &format!("Screen {}", index + 1));
+                                }
+                            }
+
+                            // 标注: 按 `draw_marks` 的顺序绘制, 裁剪到选区内
+                            if let (Some(rect), Some(desktop_rect)) = (selection_rect, controller_ref.desktop_rect()) {
+                                let origin = bounding.origin();
+                                let editing = controller_ref.editing();
+
+                                draw_list.with_clip_rect_intersect(rect.min.to_array(), rect.max.to_array(), || {
+                                    if let Some((texture_id, bounds)) = redact_texture.texture {
+                                        let bounds = bounds.to_window(origin);
+                                        draw_list.add_image(texture_id, bounds.min.to_array(), bounds.max.to_array()).build();
+                                    }
+                                    for dimmed in dimmed_rects(desktop_rect, controller_ref.annotations()) {
+                                        let dimmed = dimmed.to_window(origin);
+                                        draw_list
+                                            .add_rect(dimmed.min.to_array(), dimmed.max.to_array(), MASK_COLOR)
+                                            .filled(true)
+                                            .build();
+                                    }
+
+                                    let mut number = 0;
+                                    for mark in controller_ref.annotations() {
+                                        match mark {
+                                            Mark::Shape(annotation) => draw_annotation(&draw_list, annotation, origin),
+                                            Mark::Text(label) => if let Some((texture_id, size, caret)) = text_textures.find(label) {
+                                                let caret = editing.is_some_and(|editing| std::ptr::eq(editing, label)).then_some(caret);
+                                                draw_text_label(&draw_list, texture_id, label.position.to_window(origin), size, caret);
+                                            },
+                                            Mark::Marker(marker) => {
+                                                number += 1;
+                                                draw_marker(&draw_list, marker, number, origin);
+                                            }
+                                            Mark::Highlight(highlight) => if let Some((texture_id, bounds)) = highlight_textures.find(highlight) {
+                                                draw_highlight(&draw_list, texture_id, bounds.to_window(origin));
+                                            },
+                                            Mark::Redact(_) | Mark::Spotlight(_) => {}
+                                        }
+                                    }
+                                    if let Some(annotation) = controller_ref.drawing() {
+                                        draw_annotation(&draw_list, &annotation, origin);
+                                    }
+                                    if let Some((texture_id, bounds)) = controller_ref.highlighting().and_then(|highlight| highlight_textures.find(&highlight)) {
+                                        draw_highlight(&draw_list, texture_id, bounds.to_window(origin));
+                                    }
+                                    if let Some(area) = controller_ref.redacting().map(|redaction| redaction.rect).or(controller_ref.spotlighting()) {
+                                        draw_redacting(&draw_list, area.to_window(origin));
+                                    }
+                                });
+                            }
+
+                            // 选框 + 控制点 + 尺寸标签
+                            if let (Some(rect), Some(desktop_rect)) = (selection_rect, controller_ref.desktop_rect()) {
+                                draw_selection(&draw_list, rect, !controller_ref.is_creating(), controller_ref.hovered_handle());
+                                draw_size_label(ui, &draw_list, rect, desktop_rect);
+                            }
+
+                            // 标注工具栏
+                            draw_toolbar(&draw_list, &controller_ref.toolbar(), controller_ref.tool(), controller_ref.style());
+
+                            // 十字准线
+                            if let Some(cursor) = controller_ref.cursor() {
+                                draw_crosshair(&draw_list, cursor, [w as f32, h as f32]);
+                            }
+
+                            // 放大镜: 以鼠标所在屏幕为边界翻转
+                            if let (Some(cursor), Some(pixel)) = (controller_ref.cursor(), controller_ref.cursor_pixel()) {
+                                let bounds = screen_texture_list
+                                    .iter()
+                                    .map(|(_, screen_rect)| screen_rect.to_window(bounding.origin()))
+                                    .find(|screen_rect| screen_rect.contains(cursor))
+                                    .unwrap_or(WindowRect::from_points(WindowPoint::new(0.0, 0.0), WindowPoint::new(w as f32, h as f32)));
+
+                                draw_loupe(ui, &draw_list, &screen_texture_list, cursor, bounds, pixel, pixel_at(captures, pixel.x, pixel.y));
+                            }
                         });
+
+                    // 输入法候选框跟随正在编辑的文字
+                    let caret = controller_ref.editing().and_then(|label| {
+                        let (_, [_, h], [x, _]) = text_textures.find(label)?;
+                        let min = label.position.to_window(bounding.origin());
+                        Some((min.x + x, min.y + h as f32))
+                    });
+                    if caret.is_some() && caret != ime_position {
+                        let (x, y) = caret.unwrap_or_default();
+                        display.gl_window().window().set_ime_position(PhysicalPosition::new(x as f64, y as f64));
+                    }
+                    ime_position = caret;
+                    drop(controller_ref);
                     // endregion
 
                     let mut frame = display.draw();
@@ -279,107 +572,37 @@ impl System {
                         .render(&mut frame, imgui.render())
                         .expect("Rendering failed");
                     frame.finish().expect("Failed to swap buffers");
-
-                    // 缓存捕获的区域
-                    if start_point.is_some() && end_point.is_some() && start_point.unwrap() != end_point.unwrap() {
-                        let p1p2 = calc_select_area(start_point.unwrap(), end_point.unwrap());
-
-                        // 更新目标区域
-                        if select_area.borrow().check(p1p2) {
-                            let [x1, y1, x2, y2] = p1p2;
-                            let (_, pixel_h) = display.get_framebuffer_dimensions();
-                            // 获取屏幕帧的 rgba 阵列
-                            let frame_pixels: Vec<Vec<(u8, u8, u8, u8)>> = display.read_front_buffer().unwrap();
-                            // 选中区域的 rgba 阵列
-                            let mut selected_pixels: Vec<Vec<(u8, u8, u8, u8)>> = vec![];
-                            for y in (y1 as usize)..(y2 as usize) {
-                                let mut row = vec![(0u8, 0u8, 0u8, 0u8); (x2 as usize) - (x1 as usize)];
-                                // NOTE: read_front_buffer 获取的图像的y方向是反的, 需要反向拿取
-                                row.clone_from_slice(&frame_pixels[pixel_h as usize - y][(x1 as usize)..(x2 as usize)]);
-                                selected_pixels.push(row);
-                            }
-
-                            let y = selected_pixels.len();
-                            let x = selected_pixels[0].len();
-
-                            // 更新结果
-                            select_area.borrow_mut().update(p1p2, selected_pixels);
-
-                            println!("Update Capture! p1p2: {:?}; buffer: {} x {} x 4", p1p2, x, y);
-                        }
-                    }
                 }
                 // endregion
-                // region 处理鼠标按下和松开事件
-                Event::WindowEvent {
-                    event: WindowEvent::MouseInput { button: MouseButton::Left, state, .. }, ..
-                } => {
-                    if state == ElementState::Pressed {
-                        // 按下: 设置 flag -> 设置起点 -> 重置终点
-                        is_drawing_rect = true;
-                        start_point = curr_point;
-                        end_point = None
-                    } else {
-                        // 释放: 设置 flag -> 设置终点 -> 计算区域
-                        is_drawing_rect = false;
-                        end_point = curr_point;
-                    }
-                }
-                // endregion
-                // region 处理鼠标移动事件
-                Event::WindowEvent {
-                    event: WindowEvent::CursorMoved { position, .. }, ..
-                } => {
-                    // 更新当前点位, 处理边界问题
-                    curr_point = Some(calc_constrained_point(position, physical_xywh))
-                }
-                // endregion
-                // region 处理按键事件: 'ESC'
-                Event::WindowEvent {
-                    event: WindowEvent::KeyboardInput {
-                        input: KeyboardInput {
-                            state: ElementState::Pressed,
-                            virtual_keycode: Some(VirtualKeyCode::Escape),
-                            ..
-                        }, ..
-                    }, ..
-                } => {
-                    println!("Exit (cause 'ESC' was pressed)");
-                    select_area.borrow_mut().clear();
-                    *control_flow = ControlFlow::Exit;
-                }
-                // endregion
-                // region 处理按键事件: 'Enter'
-                Event::WindowEvent {
-                    event: WindowEvent::KeyboardInput {
-                        input: KeyboardInput {
-                            state: ElementState::Pressed,
-                            virtual_keycode: Some(VirtualKeyCode::Return),
-                            ..
-                        }, ..
-                    }, ..
-                } => {
-                    println!("Exit (cause 'Enter' was pressed)");
-                    *control_flow = ControlFlow::Exit;
-                }
-                // endregion
-                // region 关闭窗口
-                Event::WindowEvent {
-                    event: WindowEvent::CloseRequested, ..
-                } => {
-                    println!("Exit (cause 'WindowEvent::CloseRequested' was sent)");
-                    *control_flow = ControlFlow::Exit;
-                }
-                // endregion
-                // region 其他事件
+                // region 其他事件: 交互相关的窗口事件交给状态机处理, 其余交给 imgui
                 event => {
-                    platform.handle_event(imgui.io_mut(), display.gl_window().window(), &event);
+                    let inputs: Vec<InputEvent> = match &event {
+                        // 输入法提交的文字逐字输入
+                        Event::WindowEvent { event: WindowEvent::Ime(Ime::Commit(text)), .. } => text.chars().map(|ch| InputEvent::Character { ch }).collect(),
+                        Event::WindowEvent { event, .. } => InputEvent::from_window_event(event).into_iter().collect(),
+                        _ => vec![],
+                    };
+
+                    if inputs.is_empty() {
+                        platform.handle_event(imgui.io_mut(), display.gl_window().window(), &event);
+                    }
+                    for input in inputs {
+                        let mut controller = controller.borrow_mut();
+                        controller.handle(click_tracker.track(input, Instant::now()));
+
+                        if controller.exit().is_some() {
+                            *control_flow = ControlFlow::Exit;
+                        }
+                    }
                 }
                 // endregion
             }
         });
 
-        let v = (exit_code, result.borrow().to_owned());
-        v
+        let controller = Rc::try_unwrap(result)
+            .ok()
+            .expect("event loop still holds the selection state")
+            .into_inner();
+        (exit_code, controller)
     }
 }
\ No newline at end of file
@@ -0,0 +1,378 @@
+use imgui::{DrawListMut, ImColor32, TextureId, Ui};
+use crate::geometry::{DesktopPoint, DesktopRect, WindowPoint, WindowRect};
+use crate::imgui_impl::controller::Handle;
+use crate::imgui_impl::core::ScreenTexture;
+use crate::imgui_impl::toolbar::{toolbar_background, Tool, ToolbarItem, HIGHLIGHT_ALPHA, PALETTE, STROKE_WIDTHS};
+use crate::utils::annotate::{Annotation, AnnotationStyle, Shape, StepMarker};
+use crate::utils::redact::RedactKind;
+
+/// 选框及控制点的颜色
+const SELECTION_COLOR: ImColor32 = ImColor32::from_rgba(0x1e, 0x90, 0xff, 0xff);
+
+/// 自动识别区域的边框颜色
+const CANDIDATE_COLOR: ImColor32 = ImColor32::from_rgba(0x1e, 0x90, 0xff, 0x99);
+
+/// 十字准线的颜色
+const CROSSHAIR_COLOR: ImColor32 = ImColor32::from_rgba(0xff, 0xff, 0xff, 0x99);
+
+/// 尺寸标签的背景色
+const LABEL_BG_COLOR: ImColor32 = ImColor32::from_rgba(0x00, 0x00, 0x00, 0xb0);
+
+/// 尺寸标签的文字颜色
+const LABEL_TEXT_COLOR: ImColor32 = ImColor32::from_rgba(0xff, 0xff, 0xff, 0xff);
+
+/// 放大镜网格线的颜色
+const GRID_COLOR: ImColor32 = ImColor32::from_rgba(0x80, 0x80, 0x80, 0x66);
+
+/// 控制点的边长 (鼠标悬停时放大)
+const HANDLE_SIZE: f32 = 7.0;
+
+/// 尺寸标签的内边距
+const LABEL_PADDING: f32 = 4.0;
+
+/// 放大镜显示的像素数 (边长, 奇数以便鼠标所在像素居中)
+const LOUPE_PIXELS: i32 = 15;
+
+/// 放大镜的放大倍数
+const LOUPE_ZOOM: f32 = 8.0;
+
+/// 放大镜与鼠标的距离
+const LOUPE_OFFSET: f32 = 20.0;
+
+/// 绘制椭圆使用的线段数
+const ELLIPSE_SEGMENTS: usize = 64;
+
+/// 工具栏图标的颜色 rgba
+const ICON_COLOR: [u8; 4] = [0xff, 0xff, 0xff, 0xff];
+
+/// 工具栏按钮中图标的内边距
+const ICON_PADDING: f32 = 7.0;
+
+/// 文字工具的图标 (imgui 默认字体的字符), 相对按钮左上角的位置
+const TEXT_ICON: (&str, [f32; 2]) = ("T", [10.0, 7.0]);
+
+/// 绘制选框及 8 个控制点 (`hovered` 为鼠标所在的控制点)
+pub fn draw_selection(draw_list: &DrawListMut, rect: WindowRect, show_handles: bool, hovered: Option<Handle>) {
+    draw_list
+        .add_rect(rect.min.to_array(), rect.max.to_array(), SELECTION_COLOR)
+        .thickness(1.0)
+        .build();
+
+    if !show_handles {
+        return;
+    }
+
+    for handle in Handle::ALL {
+        let WindowPoint { x, y } = handle.position(rect);
+        let half = if hovered == Some(handle) { HANDLE_SIZE } else { HANDLE_SIZE / 2.0 };
+
+        draw_list
+            .add_rect([x - half, y - half], [x + half, y + half], SELECTION_COLOR)
+            .filled(true)
+            .build();
+    }
+}
+
+/// 绘制鼠标下自动识别的区域
+pub fn draw_candidate(draw_list: &DrawListMut, rect: WindowRect) {
+    draw_list
+        .add_rect(rect.min.to_array(), rect.max.to_array(), CANDIDATE_COLOR)
+        .thickness(2.0)
+        .build();
+}
+
+/// 在选框上方 (空间不足时为内部) 绘制位置及尺寸 (桌面物理坐标)
+pub fn draw_size_label(ui: &Ui, draw_list: &DrawListMut, rect: WindowRect, desktop_rect: DesktopRect) {
+    let DesktopRect { x, y, width, height } = desktop_rect;
+    draw_label(ui, draw_list, rect, &format!("{x}, {y}  {width} x {height}"));
+}
+
+/// 在矩形上方 (空间不足时为内部) 绘制带背景的文字
+pub fn draw_label(ui: &Ui, draw_list: &DrawListMut, rect: WindowRect, text: &str) {
+    let [text_w, text_h] = ui.calc_text_size(text);
+    let (label_w, label_h) = (text_w + LABEL_PADDING * 2.0, text_h + LABEL_PADDING * 2.0);
+    let label_y = if rect.min.y >= label_h { rect.min.y - label_h } else { rect.min.y };
+    let min = [rect.min.x, label_y];
+
+    draw_list
+        .add_rect(min, [min[0] + label_w, min[1] + label_h], LABEL_BG_COLOR)
+        .filled(true)
+        .build();
+    draw_list.add_text([min[0] + LABEL_PADDING, min[1] + LABEL_PADDING], LABEL_TEXT_COLOR, text);
+}
+
+/// 绘制穿过鼠标位置的十字准线
+pub fn draw_crosshair(draw_list: &DrawListMut, cursor: WindowPoint, wh: [f32; 2]) {
+    let [w, h] = wh;
+
+    draw_list.add_line([0.0, cursor.y], [w, cursor.y], CROSSHAIR_COLOR).build();
+    draw_list.add_line([cursor.x, 0.0], [cursor.x, h], CROSSHAIR_COLOR).build();
+}
+
+/// 放大镜的左上角: 默认位于鼠标右下方, 超出屏幕 (`bounds`) 时翻转到另一侧
+pub fn calc_loupe_origin(cursor: WindowPoint, size: [f32; 2], bounds: WindowRect) -> WindowPoint {
+    let [w, h] = size;
+    let x = if cursor.x + LOUPE_OFFSET + w <= bounds.max.x { cursor.x + LOUPE_OFFSET } else { cursor.x - LOUPE_OFFSET - w };
+    let y = if cursor.y + LOUPE_OFFSET + h <= bounds.max.y { cursor.y + LOUPE_OFFSET } else { cursor.y - LOUPE_OFFSET - h };
+
+    WindowPoint::new(x.max(bounds.min.x), y.max(bounds.min.y))
+}
+
+/// 绘制放大镜: 鼠标周围像素的放大图 + 像素网格 + 鼠标所在像素的坐标 (桌面物理坐标) 及颜色
+///
+/// `bounds` 为鼠标所在屏幕的区域 (窗口物理坐标), `color` 取自原始截图
+pub fn draw_loupe(
+    ui: &Ui,
+    draw_list: &DrawListMut,
+    screens: &[ScreenTexture],
+    cursor: WindowPoint,
+    bounds: WindowRect,
+    pixel: DesktopPoint,
+    color: Option<[u8; 4]>,
+) {
+    let half = LOUPE_PIXELS / 2;
+    let crop = DesktopRect::new(pixel.x - half, pixel.y - half, LOUPE_PIXELS as u32, LOUPE_PIXELS as u32);
+    let zoomed = LOUPE_PIXELS as f32 * LOUPE_ZOOM;
+
+    let text = match color {
+        Some([r, g, b, _]) => format!("{}, {}\nRGB({r}, {g}, {b})  #{r:02X}{g:02X}{b:02X}", pixel.x, pixel.y),
+        None => format!("{}, {}", pixel.x, pixel.y),
+    };
+    let [text_w, text_h] = ui.calc_text_size(&text);
+    let size = [zoomed.max(text_w + LABEL_PADDING * 2.0), zoomed + text_h + LABEL_PADDING * 2.0];
+    let WindowPoint { x: ox, y: oy } = calc_loupe_origin(cursor, size, bounds);
+
+    draw_list
+        .add_rect([ox, oy], [ox + size[0], oy + size[1]], LABEL_BG_COLOR)
+        .filled(true)
+        .build();
+
+    // 各屏幕纹理中与放大区域相交的部分
+    for (tid, screen) in screens {
+        let Some(part) = crop.intersect(screen) else { continue };
+        let (sw, sh) = (screen.width as f32, screen.height as f32);
+        let uv_min = [(part.x - screen.x) as f32 / sw, (part.y - screen.y) as f32 / sh];
+        let uv_max = [uv_min[0] + part.width as f32 / sw, uv_min[1] + part.height as f32 / sh];
+        let min = [ox + (part.x - crop.x) as f32 * LOUPE_ZOOM, oy + (part.y - crop.y) as f32 * LOUPE_ZOOM];
+        let max = [min[0] + part.width as f32 * LOUPE_ZOOM, min[1] + part.height as f32 * LOUPE_ZOOM];
+
+        draw_list
+            .add_image(*tid, min, max)
+            .uv_min(uv_min)
+            .uv_max(uv_max)
+            .build();
+    }
+
+    // 像素网格
+    for i in 0..=LOUPE_PIXELS {
+        let d = i as f32 * LOUPE_ZOOM;
+        draw_list.add_line([ox + d, oy], [ox + d, oy + zoomed], GRID_COLOR).build();
+        draw_list.add_line([ox, oy + d], [ox + zoomed, oy + d], GRID_COLOR).build();
+    }
+
+    // 鼠标所在像素 + 边框
+    let center = [ox + half as f32 * LOUPE_ZOOM, oy + half as f32 * LOUPE_ZOOM];
+    draw_list
+        .add_rect(center, [center[0] + LOUPE_ZOOM, center[1] + LOUPE_ZOOM], SELECTION_COLOR)
+        .build();
+    draw_list
+        .add_rect([ox, oy], [ox + zoomed, oy + zoomed], SELECTION_COLOR)
+        .build();
+
+    draw_list.add_text([ox + LABEL_PADDING, oy + zoomed + LABEL_PADDING], LABEL_TEXT_COLOR, text);
+}
+
+/// 绘制标注 (桌面物理坐标, `origin` 为窗口左上角的桌面坐标)
+pub fn draw_annotation(draw_list: &DrawListMut, annotation: &Annotation, origin: DesktopPoint) {
+    let to_window = |[x, y]: [f32; 2]| [x - origin.x as f32, y - origin.y as f32];
+    let AnnotationStyle { color: [r, g, b, a], stroke_width, fill } = annotation.style;
+    let color = ImColor32::from_rgba(r, g, b, a);
+    let (from, to) = (to_window(annotation.from), to_window(annotation.to));
+    let (min, max) = ([from[0].min(to[0]), from[1].min(to[1])], [from[0].max(to[0]), from[1].max(to[1])]);
+
+    match annotation.shape {
+        Shape::Rect => {
+            if fill {
+                draw_list.add_rect(min, max, color).filled(true).build();
+            }
+            draw_list.add_rect(min, max, color).thickness(stroke_width).build();
+        }
+        Shape::Ellipse => {
+            let center = [(min[0] + max[0]) / 2.0, (min[1] + max[1]) / 2.0];
+            let radius = [(max[0] - min[0]) / 2.0, (max[1] - min[1]) / 2.0];
+            let points: Vec<[f32; 2]> = (0..=ELLIPSE_SEGMENTS)
+                .map(|i| {
+                    let angle = i as f32 / ELLIPSE_SEGMENTS as f32 * std::f32::consts::TAU;
+                    [center[0] + radius[0] * angle.cos(), center[1] + radius[1] * angle.sin()]
+                })
+                .collect();
+
+            if fill {
+                draw_list.add_polyline(points.clone(), color).filled(true).build();
+            }
+            draw_list.add_polyline(points, color).thickness(stroke_width).build();
+        }
+        Shape::Line => draw_list.add_line(from, to, color).thickness(stroke_width).build(),
+        Shape::Arrow => {
+            let (base, [left, tip, right]) = annotation.arrow_head();
+            draw_list.add_line(from, to_window(base), color).thickness(stroke_width).build();
+            draw_list.add_triangle(to_window(left), to_window(tip), to_window(right), color).filled(true).build();
+        }
+    }
+}
+
+/// 绘制文字标注的图像 (`min` 为文字框的左上角, `size` 为图像的宽高), `caret` 不为 `None` 时在该处 (相对左上角) 绘制光标
+pub fn draw_text_label(draw_list: &DrawListMut, texture: TextureId, min: WindowPoint, size: [u32; 2], caret: Option<[f32; 2]>) {
+    let [w, h] = size.map(|value| value as f32);
+    draw_list
+        .add_image(texture, min.to_array(), [min.x + w, min.y + h])
+        .build();
+
+    if let Some([x, y]) = caret {
+        draw_list
+            .add_line([min.x + x, min.y + y], [min.x + x, min.y + h - y], SELECTION_COLOR)
+            .thickness(2.0)
+            .build();
+    }
+}
+
+/// 绘制编号为 `number` 的编号标记 (桌面物理坐标)
+pub fn draw_marker(draw_list: &DrawListMut, marker: &StepMarker, number: usize, origin: DesktopPoint) {
+    for annotation in marker.annotations(number) {
+        draw_annotation(draw_list, &annotation, origin);
+    }
+}
+
+/// 绘制荧光笔笔迹的图像 (`bounds` 为图像的区域)
+pub fn draw_highlight(draw_list: &DrawListMut, texture: TextureId, bounds: WindowRect) {
+    draw_list
+        .add_image(texture, bounds.min.to_array(), bounds.max.to_array())
+        .build();
+}
+
+/// 绘制正在拖拽的打码 / 聚光灯区域的边框 (松开后才显示效果)
+pub fn draw_redacting(draw_list: &DrawListMut, rect: WindowRect) {
+    draw_list
+        .add_rect(rect.min.to_array(), rect.max.to_array(), SELECTION_COLOR)
+        .thickness(1.0)
+        .build();
+}
+
+/// 绘制标注工具栏, 高亮当前的工具及样式
+pub fn draw_toolbar(draw_list: &DrawListMut, layout: &[(ToolbarItem, WindowRect)], tool: Option<Tool>, style: AnnotationStyle) {
+    let Some(background) = toolbar_background(layout) else { return };
+    draw_list
+        .add_rect(background.min.to_array(), background.max.to_array(), LABEL_BG_COLOR)
+        .filled(true)
+        .build();
+
+    let icon_style = AnnotationStyle { color: ICON_COLOR, stroke_width: 2.0, fill: false };
+
+    for &(item, rect) in layout {
+        let min = [rect.min.x + ICON_PADDING, rect.min.y + ICON_PADDING];
+        let max = [rect.max.x - ICON_PADDING, rect.max.y - ICON_PADDING];
+        // 图标使用窗口物理坐标
+        let icon = |shape: Shape, from: [f32; 2], to: [f32; 2], style: AnnotationStyle| {
+            draw_annotation(draw_list, &Annotation { shape, from, to, style }, DesktopPoint::new(0, 0));
+        };
+        let middle = (min[1] + max[1]) / 2.0;
+
+        let active = match item {
+            ToolbarItem::Tool(Tool::Shape(shape)) => {
+                icon(shape, min, max, icon_style);
+                tool == Some(Tool::Shape(shape))
+            }
+            ToolbarItem::Tool(Tool::Text) => {
+                let (text, [dx, dy]) = TEXT_ICON;
+                draw_list.add_text([rect.min.x + dx, rect.min.y + dy], LABEL_TEXT_COLOR, text);
+                tool == Some(Tool::Text)
+            }
+            ToolbarItem::Tool(Tool::Redact(kind)) => {
+                let center = [(min[0] + max[0]) / 2.0, middle];
+                match kind {
+                    // 马赛克: 棋盘格
+                    RedactKind::Pixelate => {
+                        let checker = AnnotationStyle { fill: true, stroke_width: 1.0, ..icon_style };
+                        icon(Shape::Rect, min, center, checker);
+                        icon(Shape::Rect, center, max, checker);
+                        icon(Shape::Rect, min, max, AnnotationStyle { stroke_width: 1.0, ..icon_style });
+                    }
+                    // 模糊: 半透明的圆
+                    RedactKind::Blur => {
+                        let [r, g, b, _] = ICON_COLOR;
+                        icon(Shape::Ellipse, min, max, AnnotationStyle { color: [r, g, b, 0x80], fill: true, ..icon_style });
+                        icon(Shape::Ellipse, min, max, icon_style);
+                    }
+                    // 填充: 黑色方块
+                    RedactKind::Fill => {
+                        icon(Shape::Rect, min, max, AnnotationStyle { color: [0x00, 0x00, 0x00, 0xff], fill: true, ..icon_style });
+                        icon(Shape::Rect, min, max, AnnotationStyle { stroke_width: 1.0, ..icon_style });
+                    }
+                }
+                tool == Some(Tool::Redact(kind))
+            }
+            // 编号标记: 白色圆 + 编号 1
+            ToolbarItem::Tool(Tool::Marker) => {
+                let radius = (max[0] - min[0]) / 2.0;
+                let marker = StepMarker { center: [min[0] + radius, middle], radius, color: ICON_COLOR };
+                draw_marker(draw_list, &marker, 1, DesktopPoint::new(0, 0));
+                tool == Some(Tool::Marker)
+            }
+            // 荧光笔: 半透明的粗线
+            ToolbarItem::Tool(Tool::Highlight) => {
+                let [r, g, b, _] = ICON_COLOR;
+                icon(Shape::Line, [min[0], max[1]], [max[0], min[1]], AnnotationStyle { color: [r, g, b, HIGHLIGHT_ALPHA], stroke_width: 6.0, ..icon_style });
+                tool == Some(Tool::Highlight)
+            }
+            // 聚光灯: 变暗的背景中的亮块
+            ToolbarItem::Tool(Tool::Spotlight) => {
+                let [r, g, b, _] = ICON_COLOR;
+                let inset = (max[0] - min[0]) / 4.0;
+                icon(Shape::Rect, min, max, AnnotationStyle { color: [r, g, b, 0x50], fill: true, ..icon_style });
+                icon(Shape::Rect, [min[0] + inset, min[1] + inset], [max[0] - inset, max[1] - inset], AnnotationStyle { fill: true, stroke_width: 1.0, ..icon_style });
+                tool == Some(Tool::Spotlight)
+            }
+            ToolbarItem::Color(index) => {
+                icon(Shape::Rect, min, max, AnnotationStyle { color: PALETTE[index], stroke_width: 1.0, fill: true });
+                style.color == PALETTE[index]
+            }
+            ToolbarItem::Width(index) => {
+                icon(Shape::Line, [min[0], middle], [max[0], middle], AnnotationStyle { stroke_width: STROKE_WIDTHS[index], ..icon_style });
+                style.stroke_width == STROKE_WIDTHS[index]
+            }
+            ToolbarItem::Fill => {
+                icon(Shape::Rect, min, max, AnnotationStyle { fill: true, ..icon_style });
+                style.fill
+            }
+            ToolbarItem::Undo => {
+                icon(Shape::Arrow, [max[0], middle], [min[0], middle], icon_style);
+                false
+            }
+        };
+
+        if active {
+            draw_list
+                .add_rect(rect.min.to_array(), rect.max.to_array(), SELECTION_COLOR)
+                .thickness(2.0)
+                .build();
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use crate::geometry::{WindowPoint, WindowRect};
+    use crate::imgui_impl::draw::calc_loupe_origin;
+
+    #[test]
+    fn loupe_flips_near_edges() {
+        let bounds = WindowRect::from_points(WindowPoint::new(0.0, 0.0), WindowPoint::new(400.0, 300.0));
+
+        // 默认位于右下方
+        assert_eq!(calc_loupe_origin(WindowPoint::new(100.0, 100.0), [120.0, 150.0], bounds), WindowPoint::new(120.0, 120.0));
+        // 靠近右下边缘时翻转到左上方
+        assert_eq!(calc_loupe_origin(WindowPoint::new(390.0, 290.0), [120.0, 150.0], bounds), WindowPoint::new(250.0, 120.0));
+        // 屏幕过小时不超出左上边界
+        assert_eq!(calc_loupe_origin(WindowPoint::new(100.0, 150.0), [120.0, 150.0], bounds), WindowPoint::new(120.0, 0.0));
+    }
+}
@@ -1,15 +1,30 @@
 use std::cmp::{max, min};
-use crate::declares::{CaptureInfo, SelectedImage};
-use crate::imgui_impl::prefab::BoundingBox;
+use std::sync::Arc;
+use std::time::Duration;
+use crate::declares::{CaptureInfo, Gestures, OutputFormat, PickedColor, SelectedImage, SelectedScreen, SelectionOutcome, WindowInfo};
+use crate::error::CaptureError;
+use crate::geometry::DesktopRect;
+use crate::imgui_impl::controller::{ExitReason, SelectionController, SelectionMode};
+use crate::imgui_impl::prefab::run_on_ui_thread;
+use crate::utils::{average_color, MAX_SAMPLE_SIZE};
+use crate::utils::detect::EdgeMap;
+use crate::utils::text::text_font;
+use crate::window_list::list_windows;
+
+pub use crate::imgui_impl::replay::ReplayScript;
 
 mod core;
 mod prefab;
+mod controller;
+mod draw;
+mod replay;
+mod toolbar;
 
 pub struct ImguiImpl {}
 
 impl ImguiImpl {
     /// 计算将所有屏幕盖住的窗口的 xywh
-    fn calc_bounding(captures: &Vec<CaptureInfo>) -> BoundingBox {
+    fn calc_bounding(captures: &[CaptureInfo]) -> DesktopRect {
         let (mut xl, mut yl, mut xh, mut yh) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
 
         for capture in captures {
@@ -19,24 +34,202 @@ impl ImguiImpl {
             yh = max(yh, capture.physical_y + capture.physical_height as i32);
         }
 
-        (xl, yl, xh - xl, yh - yl)
+        DesktopRect::new(xl, yl, (xh - xl) as u32, (yh - yl) as u32)
     }
 
-    /// 传入图像信息开始交互式选择区域
-    pub fn bounding(captures: Vec<CaptureInfo>) -> Option<SelectedImage> {
-        let system = core::System::new(ImguiImpl::calc_bounding(&captures), captures);
-        let (exit_code, select_area) = system.run();
-
-        println!("Task End. (exit_code = {exit_code}, p1p2 = {:?}, valid = {})", select_area.p1p2, select_area.valid);
+    /// 计算选区与各屏幕相交的部分
+    fn calc_selected_screens(captures: &[CaptureInfo], rect: DesktopRect) -> Vec<SelectedScreen> {
+        captures
+            .iter()
+            .filter_map(|capture| {
+                let screen = capture.physical_rect();
+                let part = rect.intersect(&screen)?;
+                let logical = part.to_logical(&screen, capture.scale_factor);
 
-        if select_area.valid {
-            let [x1, y1, x2, y2] = select_area.p1p2.unwrap();
-            Some(SelectedImage {
-                p1p2: vec![x1 as f64, y1 as f64, x2 as f64, y2 as f64],
-                buffer: select_area.get_buffer(),
+                Some(SelectedScreen {
+                    screen_id: capture.screen_id,
+                    scale_factor: capture.scale_factor,
+                    physical_rect: vec![part.x as f64, part.y as f64, part.width as f64, part.height as f64],
+                    logical_rect: vec![logical.x, logical.y, logical.width, logical.height],
+                })
             })
-        } else {
-            None
+            .collect()
+    }
+
+    /// 创建选择的状态机
+    ///
+    /// 仅选择区域时: `snap` 为是否检测截图中的边缘用于吸附, `windows` 中可见的窗口可悬停选中, 有 CJK 字体时可添加文字
+    fn create_controller(captures: &[CaptureInfo], mode: SelectionMode, snap: bool, windows: Vec<WindowInfo>) -> Result<SelectionController, CaptureError> {
+        if captures.is_empty() {
+            return Err(CaptureError::NoDisplays(String::from("no screen to select from")));
+        }
+
+        let controller = SelectionController::new(ImguiImpl::calc_bounding(captures))
+            .with_mode(mode)
+            .with_screens(captures.iter().map(CaptureInfo::physical_rect).collect());
+        if mode != SelectionMode::Region {
+            return Ok(controller);
         }
+
+        let controller = controller
+            .with_windows(windows.into_iter().filter(|window| window.visible).collect())
+            .with_font(text_font().cloned());
+        Ok(match snap {
+            true => controller.with_edges(EdgeMap::detect(captures)),
+            false => controller,
+        })
+    }
+
+    /// 在 UI 线程中打开选择窗口, 直到选择结束 (`timeout` 为选择的时限)
+    fn run_window(captures: &Arc<Vec<CaptureInfo>>, mode: SelectionMode, gestures: Gestures, timeout: Option<Duration>) -> Result<SelectionController, CaptureError> {
+        // 获取窗口列表失败时 (如非 X11 平台) 只是无法悬停选中窗口
+        let windows = list_windows().unwrap_or_else(|err| {
+            println!("{err}");
+            vec![]
+        });
+        let controller = ImguiImpl::create_controller(captures, mode, true, windows)?.with_gestures(gestures);
+        let captures = captures.clone();
+        let (exit_code, controller) = run_on_ui_thread(move |event_loop| {
+            core::System::new(event_loop, &captures, controller, timeout).map(core::System::run)
+        })??;
+
+        println!("Task End. (exit_code = {exit_code}, exit = {:?})", controller.exit());
+
+        Ok(controller)
+    }
+
+    /// 回放输入事件脚本 (无需窗口), 脚本中 `snap` 为 `true` 时与窗口一样吸附到边缘
+    fn run_replay(captures: &[CaptureInfo], script: &ReplayScript, mode: SelectionMode) -> Result<SelectionController, CaptureError> {
+        let mut controller = ImguiImpl::create_controller(captures, mode, script.snap, script.windows.clone())?.with_gestures(script.gestures);
+        for event in &script.events {
+            controller.handle(*event);
+        }
+
+        Ok(controller)
+    }
+
+    /// 传入图像信息开始交互式选择区域, 选区按 `output` 编码
+    ///
+    /// `gestures` 为鼠标 / 按键的行为, `timeout` 为选择的时限
+    pub fn bounding(captures: Vec<CaptureInfo>, output: OutputFormat, gestures: Gestures, timeout: Option<Duration>) -> SelectionOutcome {
+        let captures = Arc::new(captures);
+        ImguiImpl::run_window(&captures, SelectionMode::Region, gestures, timeout)
+            .map(|controller| ImguiImpl::outcome(&captures, &controller, output))
+            .into()
+    }
+
+    /// 回放输入事件脚本 (无需窗口), 结果与 `bounding` 一致
+    pub fn replay_outcome(captures: Vec<CaptureInfo>, script: &ReplayScript, output: OutputFormat) -> SelectionOutcome {
+        ImguiImpl::run_replay(&captures, script, SelectionMode::Region)
+            .map(|controller| ImguiImpl::outcome(&captures, &controller, output))
+            .into()
+    }
+
+    /// 回放输入事件脚本, 未确认的结果都视为 `Cancelled`
+    #[cfg(test)]
+    pub fn replay(captures: Vec<CaptureInfo>, script: &ReplayScript, output: OutputFormat) -> Result<SelectedImage, CaptureError> {
+        ImguiImpl::replay_outcome(captures, script, output).into_result()
+    }
+
+    /// 传入图像信息开始交互式取色, 颜色取自原始截图中以拾取点为中心的 `sample_size` x `sample_size` 区域 (上限 `MAX_SAMPLE_SIZE`)
+    pub fn pick_color(captures: Vec<CaptureInfo>, sample_size: u32) -> Result<PickedColor, CaptureError> {
+        let captures = Arc::new(captures);
+        let controller = ImguiImpl::run_window(&captures, SelectionMode::Color, Gestures::default(), None)?;
+        ImguiImpl::finish_color(&captures, &controller, sample_size)
+    }
+
+    /// 回放输入事件脚本 (无需窗口), 结果与 `pick_color` 一致
+    pub fn replay_pick_color(captures: Vec<CaptureInfo>, script: &ReplayScript, sample_size: u32) -> Result<PickedColor, CaptureError> {
+        let controller = ImguiImpl::run_replay(&captures, script, SelectionMode::Color)?;
+        ImguiImpl::finish_color(&captures, &controller, sample_size)
+    }
+
+    /// 计算拾取点的颜色, 未拾取时为 `Cancelled`
+    fn finish_color(captures: &[CaptureInfo], controller: &SelectionController, sample_size: u32) -> Result<PickedColor, CaptureError> {
+        let point = controller.picked().ok_or(CaptureError::Cancelled)?;
+        let sample_size = sample_size.clamp(1, MAX_SAMPLE_SIZE);
+        let rgb = average_color(captures, point.x, point.y, sample_size)
+            .ok_or_else(|| CaptureError::NoDisplays(format!("no screen contains point ({}, {})", point.x, point.y)))?;
+
+        Ok(PickedColor::new(point.x, point.y, sample_size, rgb))
+    }
+
+    /// 按结束的原因区分结果, 确认时裁剪选区
+    fn outcome(captures: &[CaptureInfo], controller: &SelectionController, output: OutputFormat) -> SelectionOutcome {
+        match controller.exit() {
+            Some(ExitReason::Confirmed) => match ImguiImpl::finish(captures, controller, output) {
+                Ok(selected) => SelectionOutcome::Confirmed(selected),
+                Err(CaptureError::Cancelled) => SelectionOutcome::Cancelled,
+                Err(err) => SelectionOutcome::Error(err),
+            },
+            Some(ExitReason::Closed) => SelectionOutcome::Closed,
+            Some(ExitReason::TimedOut) => SelectionOutcome::TimedOut,
+            // 回放脚本结束时仍未退出的视为取消
+            Some(ExitReason::Cancelled) | None => SelectionOutcome::Cancelled,
+        }
+    }
+
+    /// 从原始截图中裁剪选区, 按物理分辨率绘制标注后编码, 无有效选区时为 `Cancelled`
+    fn finish(captures: &[CaptureInfo], controller: &SelectionController, output: OutputFormat) -> Result<SelectedImage, CaptureError> {
+        let select_area = controller.selected();
+
+        match select_area.rect.filter(|_| select_area.valid) {
+            Some(rect) => {
+                let (x, y, w, h) = rect.xywh();
+                Ok(SelectedImage {
+                    p1p2: vec![x as f64, y as f64, (x + w as i32) as f64, (y + h as i32) as f64],
+                    screens: ImguiImpl::calc_selected_screens(captures, rect),
+                    window: controller.selected_window().cloned(),
+                    screen_id: controller.selected_screen().and_then(|index| captures.get(index)).map(|capture| capture.screen_id),
+                    buffer: select_area.get_buffer(captures, controller.annotations(), controller.font(), output)?,
+                    stride: output.raw_stride(w),
+                    failures: vec![],
+                })
+            }
+            None => Err(CaptureError::Cancelled),
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use crate::declares::{CaptureBackend, CaptureInfo, OutputFormat};
+    use crate::geometry::DesktopRect;
+    use crate::imgui_impl::ImguiImpl;
+    use crate::mock_impl::{solid_screen, MockImpl};
+
+    #[test]
+    fn bounding_of_multi_screens() {
+        // 主屏 + 左上方偏移的副屏
+        let backend = MockImpl::new(vec![
+            solid_screen(1, (0, 0, 1920, 1080), [0; 4]),
+            solid_screen(2, (-1280, -200, 1280, 1024), [0; 4]),
+        ]);
+
+        assert_eq!(ImguiImpl::calc_bounding(&backend.capture_all().unwrap().captures), DesktopRect::new(-1280, -200, 3200, 1280));
+    }
+
+    #[test]
+    fn selection_across_mixed_dpi_screens() {
+        // 左: 100% 缩放, 右: 150% 缩放
+        let captures = vec![
+            solid_screen(1, (0, 0, 1920, 1080), [0; 4]),
+            CaptureInfo { scale_factor: 1.5, ..solid_screen(2, (1920, 0, 2880, 1620), [0; 4]) },
+        ];
+        let screens = ImguiImpl::calc_selected_screens(&captures, DesktopRect::new(1820, 150, 400, 300));
+
+        assert_eq!(screens.len(), 2);
+        assert_eq!((screens[0].screen_id, screens[0].physical_rect.clone()), (1, vec![1820.0, 150.0, 100.0, 300.0]));
+        assert_eq!((screens[1].screen_id, screens[1].scale_factor), (2, 1.5));
+        assert_eq!(screens[1].physical_rect, vec![1920.0, 150.0, 300.0, 300.0]);
+        assert_eq!(screens[1].logical_rect, vec![0.0, 100.0, 200.0, 200.0]);
+
+        // 仅位于右屏
+        assert_eq!(ImguiImpl::calc_selected_screens(&captures, DesktopRect::new(3000, 0, 10, 10)).len(), 1);
+    }
+
+    #[test]
+    fn bounding_without_screens() {
+        assert_eq!(ImguiImpl::bounding(vec![], OutputFormat::default(), Default::default(), None).into_result().unwrap_err().code(), "NO_DISPLAYS");
     }
-}
\ No newline at end of file
+}
@@ -1,18 +1,84 @@
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::{mpsc, Mutex, OnceLock};
+use std::sync::mpsc::Sender;
+use std::thread;
+use ab_glyph::FontArc;
 use glium::{Display, glutin};
 use glium::glutin::dpi::{PhysicalPosition, PhysicalSize};
-use glium::glutin::event_loop::{EventLoopWindowTarget};
-use glium::glutin::platform::windows::WindowBuilderExtWindows;
+use glium::glutin::event_loop::{EventLoop, EventLoopBuilder, EventLoopWindowTarget};
 use glium::glutin::window::WindowBuilder;
-use image::{ImageBuffer, ImageOutputFormat, Rgba};
-use image::DynamicImage::ImageRgba8;
 use imgui_glium_renderer::Renderer;
+use crate::declares::{CaptureInfo, OutputFormat};
+use crate::error::CaptureError;
+use crate::geometry::DesktopRect;
+use crate::utils::{crop_captures, encode_image};
+use crate::utils::annotate::{draw_marks, Mark};
 
 const TITLE: &str = "截图";
 
-/// 物理坐标系下的 xywh
-pub type BoundingBox = (i32, i32, i32, i32);
+/// 在 UI 线程中执行的任务
+type UiJob = Box<dyn FnOnce(&mut EventLoop<()>) + Send>;
 
-/// 窗口预制件 (使用逻辑坐标)
+/// UI 线程的任务队列, 首次使用时启动 UI 线程
+static UI_THREAD: OnceLock<Mutex<Sender<UiJob>>> = OnceLock::new();
+
+/// 在常驻的 UI 线程中执行 `job` 并等待结果, 多个任务按调用顺序依次执行
+///
+/// winit 的事件循环在每个进程中只能创建一次, 因此由 UI 线程持有唯一的事件循环, 所有选择窗口都在该线程中创建;
+/// 任务 panic 时只结束该任务, UI 线程继续执行之后的任务
+pub fn run_on_ui_thread<T: Send + 'static>(job: impl FnOnce(&mut EventLoop<()>) -> T + Send + 'static) -> Result<T, CaptureError> {
+    let queue = UI_THREAD.get_or_init(|| {
+        let (sender, receiver) = mpsc::channel::<UiJob>();
+        let spawned = thread::Builder::new()
+            .name(String::from("screen_capture_ui"))
+            .spawn(move || {
+                let mut event_loop = create_event_loop();
+                for job in receiver {
+                    let _ = panic::catch_unwind(AssertUnwindSafe(|| job(&mut event_loop)));
+                }
+            });
+        if let Err(err) = spawned {
+            println!("Failed to spawn UI thread: {err}");
+        }
+        Mutex::new(sender)
+    });
+
+    let (result_sender, result_receiver) = mpsc::channel();
+    queue
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+        .send(Box::new(move |event_loop| {
+            let _ = result_sender.send(job(event_loop));
+        }))
+        .map_err(|_| CaptureError::Window(String::from("UI thread is not running")))?;
+
+    result_receiver
+        .recv()
+        .map_err(|_| CaptureError::Window(String::from("selection window panicked")))
+}
+
+/// 事件循环
+///
+/// 在 UI 线程 (非主线程) 中创建, 以便不阻塞 node 的主线程 (macOS 不支持)
+fn create_event_loop() -> EventLoop<()> {
+    let mut builder = EventLoopBuilder::new();
+
+    #[cfg(target_os = "windows")]
+    {
+        use glium::glutin::platform::windows::EventLoopBuilderExtWindows;
+        builder.with_any_thread(true);
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))]
+    {
+        use glium::glutin::platform::unix::EventLoopBuilderExtUnix;
+        builder.with_any_thread(true);
+    }
+
+    builder.build()
+}
+
+/// 窗口预制件 (使用桌面物理坐标)
 ///
 /// - 标题 "截图"
 /// - 无边框
@@ -21,11 +87,11 @@ pub type BoundingBox = (i32, i32, i32, i32);
 /// - 禁用改变大小
 /// - 指定尺寸+位置
 pub fn create_screen_pair(
-    mut ctx: &mut imgui::Context,
+    ctx: &mut imgui::Context,
     event_loop: &EventLoopWindowTarget<()>,
-    physical_xywh: BoundingBox,
-) -> (Display, Renderer) {
-    let (x, y, w, h) = physical_xywh;
+    bounding: DesktopRect,
+) -> Result<(Display, Renderer), CaptureError> {
+    let (x, y, w, h) = bounding.xywh();
 
     let position = PhysicalPosition::new(x, y);
     let inner_size = PhysicalSize::new(w, h);
@@ -34,78 +100,176 @@ pub fn create_screen_pair(
         .with_title(String::from(TITLE))
         .with_decorations(false)
         .with_always_on_top(true)
-        .with_skip_taskbar(true)
         .with_resizable(false)
         .with_position(position)
         .with_inner_size(inner_size);
+    let builder = with_platform_attributes(builder, event_loop)?;
 
     let display = Display::new(
         builder,
         glutin::ContextBuilder::new().with_vsync(true),
         event_loop,
-    ).unwrap();
+    ).map_err(|err| CaptureError::Window(err.to_string()))?;
+
+    grab_keyboard(&display);
+
+    let renderer = Renderer::init(ctx, &display)
+        .map_err(|err| CaptureError::Window(err.to_string()))?;
+
+    Ok((display, renderer))
+}
+
+/// 平台相关的窗口属性: 无任务栏
+#[cfg(target_os = "windows")]
+fn with_platform_attributes(builder: WindowBuilder, _event_loop: &EventLoopWindowTarget<()>) -> Result<WindowBuilder, CaptureError> {
+    use glium::glutin::platform::windows::WindowBuilderExtWindows;
+
+    Ok(builder.with_skip_taskbar(true))
+}
+
+/// 平台相关的窗口属性
+///
+/// - X11: override-redirect, 绕过窗口管理器, 窗口按指定位置覆盖整个虚拟桌面, 且不出现在任务栏中
+/// - Wayland: 客户端无法指定窗口位置, 以无边框全屏覆盖当前屏幕;
+///   全屏窗口只能覆盖一个屏幕, 无法与按所有屏幕外接矩形排列的截图对齐, 因此有多个屏幕时直接拒绝
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))]
+fn with_platform_attributes(builder: WindowBuilder, event_loop: &EventLoopWindowTarget<()>) -> Result<WindowBuilder, CaptureError> {
+    use glium::glutin::platform::unix::{EventLoopWindowTargetExtUnix, WindowBuilderExtUnix, XWindowType};
+    use glium::glutin::window::Fullscreen;
+
+    let builder = builder.with_name("screen_capture", "screen_capture");
+
+    if event_loop.is_wayland() {
+        let monitors = event_loop.available_monitors().count();
+        if monitors > 1 {
+            return Err(CaptureError::Window(format!("interactive selection across {monitors} monitors is not supported on Wayland")));
+        }
+        Ok(builder.with_fullscreen(Some(Fullscreen::Borderless(None))))
+    } else {
+        Ok(builder
+            .with_override_redirect(true)
+            .with_x11_window_type(vec![XWindowType::Utility]))
+    }
+}
 
-    let renderer = Renderer::init(&mut ctx, &display).unwrap();
+#[cfg(not(any(target_os = "windows", target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd")))]
+fn with_platform_attributes(builder: WindowBuilder, _event_loop: &EventLoopWindowTarget<()>) -> Result<WindowBuilder, CaptureError> {
+    Ok(builder)
+}
+
+/// 抢占键盘焦点
+///
+/// X11 下 override-redirect 窗口不受窗口管理器管理, 不会自动获得焦点, 需要主动抢占键盘 (窗口映射前会失败, 因此重试若干次);
+/// 窗口销毁时自动释放
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))]
+fn grab_keyboard(display: &Display) {
+    use std::thread::sleep;
+    use std::time::Duration;
+    use glium::glutin::platform::unix::WindowExtUnix;
+    use x11_dl::xlib;
+
+    let gl_window = display.gl_window();
+    let window = gl_window.window();
+    let (Some(x_display), Some(x_window)) = (window.xlib_display(), window.xlib_window()) else {
+        // Wayland: 全屏窗口由合成器赋予焦点
+        window.focus_window();
+        return;
+    };
+    let Ok(xlib) = xlib::Xlib::open() else {
+        println!("Failed to load libX11, keyboard is not grabbed");
+        return;
+    };
+    let x_display = x_display as *mut xlib::Display;
+
+    for _ in 0..50 {
+        // SAFETY: display 和 window 由 winit 创建, 在窗口存活期间有效
+        let grabbed = unsafe {
+            let status = (xlib.XGrabKeyboard)(x_display, x_window, xlib::True, xlib::GrabModeAsync, xlib::GrabModeAsync, xlib::CurrentTime);
+            if status == xlib::GrabSuccess {
+                (xlib.XSetInputFocus)(x_display, x_window, xlib::RevertToParent, xlib::CurrentTime);
+                (xlib.XFlush)(x_display);
+            }
+            status == xlib::GrabSuccess
+        };
+        if grabbed {
+            return;
+        }
+        sleep(Duration::from_millis(10));
+    }
 
-    (display, renderer)
+    println!("Failed to grab keyboard");
 }
 
+#[cfg(not(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd")))]
+fn grab_keyboard(display: &Display) {
+    display.gl_window().window().focus_window();
+}
 
 /// 交互式选择选中的区域
+///
+/// 只记录区域, 确认后再从原始截图中裁剪像素
 #[derive(Debug, Clone)]
 pub struct SelectedArea {
     /// 是否有效
     pub valid: bool,
-    /// 即: \[xmin, ymin, xmax, ymax\]
-    pub p1p2: Option<[f32; 4]>,
-    /// 目标区域的 rgba 阵列
-    pub rgba: Vec<Vec<(u8, u8, u8, u8)>>,
+    /// 选中区域 (桌面物理坐标)
+    pub rect: Option<DesktopRect>,
 }
 
 impl SelectedArea {
     pub fn empty() -> SelectedArea {
-        SelectedArea { valid: false, p1p2: None, rgba: vec![] }
+        SelectedArea { valid: false, rect: None }
     }
 
-    /// 检查是否需要更新 (点位不同)
-    pub fn check(&self, other_p1p2: [f32; 4]) -> bool {
-        self.p1p2.map_or(true, |p1p2| p1p2 != other_p1p2)
+    /// 检查是否需要更新 (已清除或区域不同)
+    pub fn check(&self, other: DesktopRect) -> bool {
+        !self.valid || self.rect != Some(other)
     }
 
     pub fn clear(&mut self) {
         self.valid = false;
     }
 
-    pub fn update(&mut self, p1p2: [f32; 4], rgba: Vec<Vec<(u8, u8, u8, u8)>>) {
+    pub fn update(&mut self, rect: DesktopRect) {
         self.valid = true;
-        self.p1p2 = Some(p1p2);
-        self.rgba = rgba;
+        self.rect = Some(rect);
     }
 
-    pub fn get_buffer(&self) -> Vec<u8> {
-        if !self.valid {
-            vec![]
-        } else {
-            let [x1, y1, x2, y2] = self.p1p2.unwrap();
-            let mut image_buf = ImageBuffer::new((x2 - x1) as u32, (y2 - y1) as u32);
-
-            for (x, y, pixel) in image_buf.enumerate_pixels_mut() {
-                let (r, g, b, a) = self.rgba[y as usize][x as usize];
-                *pixel = Rgba::from([r, g, b, a]);
-            }
+    /// 选中区域的 xywh (桌面物理坐标)
+    pub fn xywh(&self) -> Option<(i32, i32, u32, u32)> {
+        self.rect
+            .filter(|_| self.valid)
+            .map(|rect| rect.xywh())
+    }
 
-            // TEST: 方便查看结果
-            // image_buf.save("./screen.png");
+    /// 从原始截图中裁剪选中区域的 rgba 阵列 (行优先)
+    pub fn crop(&self, captures: &[CaptureInfo]) -> Vec<u8> {
+        match self.xywh() {
+            Some(xywh) => crop_captures(captures, xywh),
+            None => vec![],
+        }
+    }
 
-            let mut bytes = vec![];
-            ImageRgba8(image_buf).write_to(&mut bytes, ImageOutputFormat::Png).unwrap();
-            bytes
+    /// 选中区域绘制标注 (桌面物理坐标, 文字使用 `font`) 后按 `output` 编码的 buffer
+    pub fn get_buffer(&self, captures: &[CaptureInfo], marks: &[Mark], font: Option<&FontArc>, output: OutputFormat) -> Result<Vec<u8>, CaptureError> {
+        match self.rect.filter(|_| self.valid) {
+            Some(rect) => {
+                let mut rgba = self.crop(captures);
+                draw_marks(&mut rgba, rect, marks, font);
+                encode_image(&rgba, rect.width, rect.height, output)
+            }
+            None => Ok(vec![]),
         }
     }
 }
 
 #[cfg(test)]
 mod unit_test {
+    use crate::declares::CaptureInfo;
+    use crate::error::CaptureError;
+    use crate::geometry::DesktopRect;
+    use crate::imgui_impl::prefab::{create_screen_pair, run_on_ui_thread, SelectedArea};
+
     #[test]
     fn tt() {
         let a = vec![1, 2, 3, 4];
@@ -113,4 +277,49 @@ mod unit_test {
 
         println!("equal: {}", a == b);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn crop_from_original_captures() {
+        let capture = CaptureInfo {
+            screen_id: 1,
+            scale_factor: 1.0,
+            physical_x: 0,
+            physical_y: 0,
+            physical_width: 3,
+            physical_height: 2,
+            buffer: None,
+            stride: None,
+            rgba: (0..24).collect(),
+        };
+        let captures = &[capture];
+        let mut area = SelectedArea::empty();
+        area.update(DesktopRect::new(1, 0, 2, 2));
+
+        // 第 0 行第 1~2 列 + 第 1 行第 1~2 列
+        assert_eq!(area.crop(captures), vec![4, 5, 6, 7, 8, 9, 10, 11, 16, 17, 18, 19, 20, 21, 22, 23]);
+
+        // 选区首行为 y = 0 时不越界
+        area.update(DesktopRect::new(0, 0, 1, 1));
+        assert_eq!(area.crop(captures), vec![0, 1, 2, 3]);
+
+        area.clear();
+        assert!(area.crop(captures).is_empty());
+    }
+
+    /// 需要图形环境, 如: `LIBGL_ALWAYS_SOFTWARE=1 xvfb-run -a cargo test -- --ignored`
+    #[test]
+    #[ignore]
+    fn create_overlay_window() {
+        // 多次打开窗口时复用 UI 线程中唯一的事件循环
+        for _ in 0..2 {
+            let size = run_on_ui_thread(|event_loop| {
+                let mut imgui = imgui::Context::create();
+                let (display, _renderer) = create_screen_pair(&mut imgui, event_loop, DesktopRect::new(0, 0, 64, 48))?;
+                let size = display.gl_window().window().inner_size();
+                Ok::<_, CaptureError>((size.width, size.height))
+            });
+
+            assert_eq!(size.unwrap().unwrap(), (64, 48));
+        }
+    }
+}
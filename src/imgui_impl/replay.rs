@@ -0,0 +1,256 @@
+use std::fs;
+use std::path::Path;
+use serde::Deserialize;
+use crate::declares::{Gestures, WindowInfo};
+use crate::imgui_impl::controller::InputEvent;
+
+/// 录制的输入事件脚本, 用于在没有窗口的环境中回放交互式选择
+///
+/// ```json
+/// {
+///   "events": [
+///     { "type": "cursor_moved", "x": 10, "y": 20 },
+///     { "type": "button", "button": "left", "pressed": true },
+///     { "type": "cursor_moved", "x": 110, "y": 80 },
+///     { "type": "button", "button": "left", "pressed": false },
+///     { "type": "key", "key": "enter", "pressed": true }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReplayScript {
+    /// 按顺序回放的事件 (坐标为选择窗口的物理坐标)
+    pub events: Vec<InputEvent>,
+    /// 是否检测截图中的边缘用于吸附及自动识别区域, 默认不启用, 以便按脚本中的坐标精确回放
+    #[serde(default)]
+    pub snap: bool,
+    /// 可悬停选中的顶层窗口 (由上到下, 桌面物理坐标), 代替真实的窗口列表
+    #[serde(default)]
+    pub windows: Vec<WindowInfo>,
+    /// 鼠标 / 按键的行为, 默认全部启用
+    #[serde(default)]
+    pub gestures: Gestures,
+}
+
+#[allow(unused)]
+impl ReplayScript {
+    /// 解析 json 脚本
+    pub fn parse(content: &str) -> Result<ReplayScript, String> {
+        serde_json::from_str(content).map_err(|err| format!("Failed to parse replay script: {err}"))
+    }
+
+    /// 读取 json 脚本文件
+    pub fn from_file(path: impl AsRef<Path>) -> Result<ReplayScript, String> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read replay script '{}': {err}", path.display()))?;
+
+        ReplayScript::parse(&content)
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use napi::bindgen_prelude::Either3;
+    use crate::declares::{CaptureBackend, CaptureInfo, ImageFormat, OutputFormat};
+    use crate::imgui_impl::{ImguiImpl, ReplayScript};
+    use crate::mock_impl::{solid_screen, MockImpl};
+
+    /// 左: 红色 4x4 屏幕 (x = -4), 右: 蓝色 4x4 屏幕 (x = 0)
+    fn captures() -> Vec<CaptureInfo> {
+        let backend = MockImpl::new(vec![
+            solid_screen(1, (-4, 0, 4, 4), [255, 0, 0, 255]),
+            solid_screen(2, (0, 0, 4, 4), [0, 0, 255, 255]),
+        ]);
+        backend.capture_all().unwrap().captures
+    }
+
+    fn drag_script(finish: &str) -> String {
+        format!(r#"{{
+            "events": [
+                {{ "type": "cursor_moved", "x": 3, "y": 1 }},
+                {{ "type": "button", "button": "left", "pressed": true }},
+                {{ "type": "cursor_moved", "x": 20, "y": 2 }},
+                {{ "type": "button", "button": "left", "pressed": false }},
+                {finish}
+            ]
+        }}"#)
+    }
+
+    #[test]
+    fn replay_drag_across_screens() {
+        let script = ReplayScript::parse(&drag_script(r#"{ "type": "key", "key": "enter", "pressed": true }"#)).unwrap();
+        let selected = ImguiImpl::replay(captures(), &script, OutputFormat::from(ImageFormat::RawRgba)).unwrap();
+
+        // 窗口左上角为 (-4, 0), 终点被约束在窗口内
+        assert_eq!(selected.p1p2, vec![-1.0, 1.0, 4.0, 2.0]);
+        assert_eq!(selected.screens.iter().map(|screen| screen.screen_id).collect::<Vec<_>>(), vec![1, 2]);
+        assert_eq!(&selected.buffer[..4], &[255, 0, 0, 255]);
+        assert_eq!(&selected.buffer[4..8], &[0, 0, 255, 255]);
+    }
+
+    #[test]
+    fn replay_cancel() {
+        let script = ReplayScript::parse(&drag_script(r#"{ "type": "key", "key": "escape", "pressed": true }"#)).unwrap();
+
+        assert_eq!(ImguiImpl::replay(captures(), &script, OutputFormat::default()).unwrap_err().code(), "CANCELLED");
+    }
+
+    #[test]
+    fn replay_escape_then_reselect() {
+        // 默认: 第一次 'ESC' 只清除选区, 之后可重新选择
+        let events = r#"{ "type": "key", "key": "escape", "pressed": true },
+            { "type": "key", "key": { "digit": 1 }, "pressed": true },
+            { "type": "key", "key": "enter", "pressed": true }"#;
+        let selected = ImguiImpl::replay(captures(), &ReplayScript::parse(&drag_script(events)).unwrap(), OutputFormat::default()).unwrap();
+        assert_eq!(selected.screen_id, Some(1));
+
+        // 关闭 `escape_clears_first` 时直接退出
+        let script = drag_script(events).replacen('{', r#"{ "gestures": { "escape_clears_first": false },"#, 1);
+        let script = ReplayScript::parse(&script).unwrap();
+        assert!(!script.gestures.escape_clears_first && script.gestures.right_click_clears);
+        assert_eq!(ImguiImpl::replay(captures(), &script, OutputFormat::default()).unwrap_err().code(), "CANCELLED");
+    }
+
+    #[test]
+    fn replay_outcomes() {
+        let outcome = |finish: &str| {
+            let script = ReplayScript::parse(&drag_script(finish)).unwrap();
+            ImguiImpl::replay_outcome(captures(), &script, OutputFormat::default())
+        };
+
+        assert_eq!(outcome(r#"{ "type": "key", "key": "enter", "pressed": true }"#).kind(), "confirmed");
+        assert_eq!(outcome(r#"{ "type": "button", "button": "right", "pressed": true },
+            { "type": "button", "button": "right", "pressed": true }"#).kind(), "cancelled");
+        assert_eq!(outcome(r#"{ "type": "close_requested" }"#).kind(), "closed");
+        assert_eq!(outcome(r#"{ "type": "timed_out" }"#).kind(), "timed_out");
+
+        // 'Enter' 时没有有效的选区
+        let script = ReplayScript::parse(r#"{ "events": [{ "type": "key", "key": "enter", "pressed": true }] }"#).unwrap();
+        assert_eq!(ImguiImpl::replay_outcome(captures(), &script, OutputFormat::default()).kind(), "cancelled");
+
+        // js 侧按 `kind` 区分
+        let Either3::C(failed) = ImguiImpl::replay_outcome(vec![], &script, OutputFormat::default()).into() else { panic!("expected a failed outcome") };
+        assert_eq!((failed.kind.as_str(), failed.code.as_str()), ("error", "NO_DISPLAYS"));
+    }
+
+    #[test]
+    fn replay_annotate_selection() {
+        // 选区下方的工具栏: 第一个按钮为矩形工具, 位于 (4, 110) ~ (32, 138)
+        let script = ReplayScript::parse(r#"{
+            "events": [
+                { "type": "cursor_moved", "x": 10, "y": 10 },
+                { "type": "button", "button": "left", "pressed": true },
+                { "type": "cursor_moved", "x": 200, "y": 100 },
+                { "type": "button", "button": "left", "pressed": false },
+                { "type": "cursor_moved", "x": 18, "y": 124 },
+                { "type": "button", "button": "left", "pressed": true },
+                { "type": "button", "button": "left", "pressed": false },
+                { "type": "cursor_moved", "x": 50, "y": 30 },
+                { "type": "button", "button": "left", "pressed": true },
+                { "type": "cursor_moved", "x": 150, "y": 80 },
+                { "type": "button", "button": "left", "pressed": false },
+                { "type": "key", "key": "enter", "pressed": true }
+            ]
+        }"#).unwrap();
+        let captures = vec![solid_screen(1, (0, 0, 800, 800), [255, 255, 255, 255])];
+        let selected = ImguiImpl::replay(captures, &script, OutputFormat::from(ImageFormat::RawRgba)).unwrap();
+
+        // 标注按原始分辨率绘制在选区 (10, 10, 190, 90) 中
+        let pixel = |x: usize, y: usize| &selected.buffer[(y * 190 + x) * 4..(y * 190 + x) * 4 + 4];
+        assert_eq!(selected.p1p2, vec![10.0, 10.0, 200.0, 100.0]);
+        assert_eq!((pixel(40, 40), pixel(90, 20)), (&[0xff, 0x3b, 0x30, 0xff][..], &[0xff, 0x3b, 0x30, 0xff][..]));
+        assert_eq!((pixel(90, 45), pixel(20, 20)), (&[255, 255, 255, 255][..], &[255, 255, 255, 255][..]));
+    }
+
+    #[test]
+    fn replay_redact_selection() {
+        // 工具栏的第 8 个按钮为填充打码, 位于 (228, 110) ~ (256, 138)
+        let script = ReplayScript::parse(r#"{
+            "events": [
+                { "type": "cursor_moved", "x": 10, "y": 10 },
+                { "type": "button", "button": "left", "pressed": true },
+                { "type": "cursor_moved", "x": 200, "y": 100 },
+                { "type": "button", "button": "left", "pressed": false },
+                { "type": "cursor_moved", "x": 242, "y": 124 },
+                { "type": "button", "button": "left", "pressed": true },
+                { "type": "button", "button": "left", "pressed": false },
+                { "type": "cursor_moved", "x": 50, "y": 30 },
+                { "type": "button", "button": "left", "pressed": true },
+                { "type": "cursor_moved", "x": 150, "y": 80 },
+                { "type": "button", "button": "left", "pressed": false },
+                { "type": "key", "key": "enter", "pressed": true }
+            ]
+        }"#).unwrap();
+        let captures = vec![solid_screen(1, (0, 0, 800, 800), [255, 255, 255, 255])];
+        let selected = ImguiImpl::replay(captures, &script, OutputFormat::from(ImageFormat::RawRgba)).unwrap();
+
+        // 打码区域 (50, 30, 100, 50) 的像素被当前颜色覆盖
+        let pixel = |x: usize, y: usize| &selected.buffer[(y * 190 + x) * 4..(y * 190 + x) * 4 + 4];
+        assert_eq!((pixel(40, 20), pixel(139, 69)), (&[0xff, 0x3b, 0x30, 0xff][..], &[0xff, 0x3b, 0x30, 0xff][..]));
+        assert_eq!((pixel(39, 20), pixel(140, 70)), (&[255, 255, 255, 255][..], &[255, 255, 255, 255][..]));
+    }
+
+    #[test]
+    fn invalid_script() {
+        assert!(ReplayScript::parse(r#"{ "events": [{ "type": "teleport" }] }"#).is_err());
+        assert!(ReplayScript::from_file("not_exists.json").is_err());
+    }
+
+    #[test]
+    fn replay_pick_color() {
+        let script = ReplayScript::parse(r#"{
+            "events": [
+                { "type": "cursor_moved", "x": 4, "y": 1 },
+                { "type": "button", "button": "left", "pressed": true }
+            ]
+        }"#).unwrap();
+
+        // 拾取点为蓝色屏幕的 (0, 1), 2x2 采样区域一半在红色屏幕上
+        let picked = ImguiImpl::replay_pick_color(captures(), &script, 1).unwrap();
+        assert_eq!((picked.x, picked.y, picked.hex.as_str()), (0, 1, "#0000FF"));
+
+        let picked = ImguiImpl::replay_pick_color(captures(), &script, 2).unwrap();
+        assert_eq!((picked.hex.as_str(), picked.rgb.clone(), picked.hsl[0]), ("#800080", vec![128, 0, 128], 300.0));
+    }
+
+    #[test]
+    fn replay_click_window() {
+        // 上层窗口部分位于屏幕外, 选区为屏幕内的部分
+        let script = ReplayScript::parse(r#"{
+            "windows": [
+                { "window_id": 7, "title": "top", "physical_x": -2, "physical_y": 1, "physical_width": 8, "physical_height": 5 },
+                { "window_id": 3, "title": "bottom", "physical_x": -4, "physical_y": 0, "physical_width": 8, "physical_height": 4 }
+            ],
+            "events": [
+                { "type": "cursor_moved", "x": 1, "y": 0 },
+                { "type": "cursor_moved", "x": 3, "y": 2 },
+                { "type": "button", "button": "left", "pressed": true },
+                { "type": "button", "button": "left", "pressed": false },
+                { "type": "key", "key": "enter", "pressed": true }
+            ]
+        }"#).unwrap();
+        let selected = ImguiImpl::replay(captures(), &script, OutputFormat::from(ImageFormat::RawRgba)).unwrap();
+        let window = selected.window.unwrap();
+
+        assert_eq!((window.window_id, window.title.as_str()), (7, "top"));
+        assert_eq!(window.physical_rect().xywh(), (-2, 1, 8, 5));
+        assert_eq!(selected.p1p2, vec![-2.0, 1.0, 4.0, 4.0]);
+        assert_eq!(selected.screen_id, None);
+    }
+
+    #[test]
+    fn replay_select_screen() {
+        // 数字键 2 选中第二个屏幕
+        let script = ReplayScript::parse(r#"{
+            "events": [
+                { "type": "key", "key": { "digit": 2 }, "pressed": true },
+                { "type": "key", "key": "enter", "pressed": true }
+            ]
+        }"#).unwrap();
+        let selected = ImguiImpl::replay(captures(), &script, OutputFormat::from(ImageFormat::RawRgba)).unwrap();
+
+        assert_eq!(selected.screen_id, Some(2));
+        assert_eq!(selected.p1p2, vec![0.0, 0.0, 4.0, 4.0]);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::geometry::{WindowPoint, WindowRect};
+use crate::utils::annotate::{AnnotationStyle, Shape};
+use crate::utils::redact::RedactKind;
+
+/// 可选的标注颜色
+pub const PALETTE: [[u8; 4]; 5] = [
+    [0xff, 0x3b, 0x30, 0xff],
+    [0xff, 0xcc, 0x00, 0xff],
+    [0x34, 0xc7, 0x59, 0xff],
+    [0x1e, 0x90, 0xff, 0xff],
+    [0xff, 0xff, 0xff, 0xff],
+];
+
+/// 可选的线宽 (物理像素)
+pub const STROKE_WIDTHS: [f32; 3] = [2.0, 4.0, 8.0];
+
+/// 可选的字号 (物理像素), 与 `STROKE_WIDTHS` 一一对应
+pub const FONT_SIZES: [f32; 3] = [16.0, 24.0, 40.0];
+
+/// 可选的马赛克方块边长 / 模糊半径 (物理像素), 与 `STROKE_WIDTHS` 一一对应
+pub const REDACT_STRENGTHS: [u32; 3] = [6, 12, 24];
+
+/// 可选的编号标记半径 (物理像素), 与 `STROKE_WIDTHS` 一一对应
+pub const MARKER_RADII: [f32; 3] = [10.0, 14.0, 22.0];
+
+/// 可选的荧光笔宽度 (物理像素), 与 `STROKE_WIDTHS` 一一对应
+pub const HIGHLIGHT_WIDTHS: [f32; 3] = [12.0, 20.0, 32.0];
+
+/// 荧光笔颜色的不透明度
+pub const HIGHLIGHT_ALPHA: u8 = 0x60;
+
+/// 按钮的边长
+const BUTTON_SIZE: f32 = 28.0;
+
+/// 按钮的间距
+const BUTTON_GAP: f32 = 4.0;
+
+/// 工具栏与选框的距离
+const TOOLBAR_MARGIN: f32 = 6.0;
+
+/// 标注工具
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    /// 拖拽绘制图形
+    Shape(Shape),
+    /// 单击放置 / 编辑文字
+    Text,
+    /// 拖拽打码
+    Redact(RedactKind),
+    /// 单击添加编号标记 (单击已有的标记时删除)
+    Marker,
+    /// 荧光笔
+    Highlight,
+    /// 拖拽添加聚光灯区域
+    Spotlight,
+}
+
+/// 工具栏上的按钮
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ToolbarItem {
+    /// 选择 / 取消标注工具
+    Tool(Tool),
+    /// `PALETTE` 中的颜色
+    Color(usize),
+    /// `STROKE_WIDTHS` 中的线宽 (文字、打码、编号标记及荧光笔为对应的字号、强度、半径及宽度)
+    Width(usize),
+    /// 切换是否填充 (文字为是否绘制背景)
+    Fill,
+    /// 撤销上一个标注
+    Undo,
+}
+
+impl ToolbarItem {
+    /// 工具栏上的全部按钮 (由左到右)
+    pub fn all() -> Vec<ToolbarItem> {
+        [Shape::Rect, Shape::Ellipse, Shape::Line, Shape::Arrow].map(|shape| ToolbarItem::Tool(Tool::Shape(shape)))
+            .into_iter()
+            .chain([ToolbarItem::Tool(Tool::Text)])
+            .chain([RedactKind::Pixelate, RedactKind::Blur, RedactKind::Fill].map(|kind| ToolbarItem::Tool(Tool::Redact(kind))))
+            .chain([Tool::Marker, Tool::Highlight, Tool::Spotlight].map(ToolbarItem::Tool))
+            .chain((0..PALETTE.len()).map(ToolbarItem::Color))
+            .chain((0..STROKE_WIDTHS.len()).map(ToolbarItem::Width))
+            .chain([ToolbarItem::Fill, ToolbarItem::Undo])
+            .collect()
+    }
+}
+
+/// 默认的标注样式
+pub fn default_style() -> AnnotationStyle {
+    AnnotationStyle { color: PALETTE[0], stroke_width: STROKE_WIDTHS[1], fill: false }
+}
+
+/// 线宽在 `STROKE_WIDTHS` 中的序号, 不在其中时为中间值
+fn width_index(stroke_width: f32) -> usize {
+    STROKE_WIDTHS
+        .iter()
+        .position(|width| *width == stroke_width)
+        .unwrap_or(1)
+}
+
+/// 线宽对应的字号
+pub fn font_size(stroke_width: f32) -> f32 {
+    FONT_SIZES[width_index(stroke_width)]
+}
+
+/// 线宽对应的马赛克方块边长 / 模糊半径
+pub fn redact_strength(stroke_width: f32) -> u32 {
+    REDACT_STRENGTHS[width_index(stroke_width)]
+}
+
+/// 线宽对应的编号标记半径
+pub fn marker_radius(stroke_width: f32) -> f32 {
+    MARKER_RADII[width_index(stroke_width)]
+}
+
+/// 线宽对应的荧光笔宽度
+pub fn highlight_width(stroke_width: f32) -> f32 {
+    HIGHLIGHT_WIDTHS[width_index(stroke_width)]
+}
+
+/// 计算工具栏各按钮的位置 (窗口物理坐标)
+///
+/// 与选框右对齐, 默认位于选框下方, 空间不足时位于上方, 都不足时位于选框内的底部; `wh` 为窗口的宽高
+///
+/// 窗口无法完整显示工具栏时不显示
+pub fn toolbar_layout(selection: WindowRect, wh: [f32; 2]) -> Vec<(ToolbarItem, WindowRect)> {
+    let [w, h] = wh;
+    let items = ToolbarItem::all();
+    let width = items.len() as f32 * (BUTTON_SIZE + BUTTON_GAP) + BUTTON_GAP;
+    let height = BUTTON_SIZE + BUTTON_GAP * 2.0;
+    if width > w || height > h {
+        return vec![];
+    }
+
+    let x = (selection.max.x - width).min(w - width).max(0.0);
+    let y = if selection.max.y + TOOLBAR_MARGIN + height <= h {
+        selection.max.y + TOOLBAR_MARGIN
+    } else if selection.min.y - TOOLBAR_MARGIN - height >= 0.0 {
+        selection.min.y - TOOLBAR_MARGIN - height
+    } else {
+        (selection.max.y - height).max(0.0)
+    };
+
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| {
+            let min = WindowPoint::new(x + BUTTON_GAP + i as f32 * (BUTTON_SIZE + BUTTON_GAP), y + BUTTON_GAP);
+            (item, WindowRect::from_points(min, WindowPoint::new(min.x + BUTTON_SIZE, min.y + BUTTON_SIZE)))
+        })
+        .collect()
+}
+
+/// 工具栏的背景区域 (包含各按钮)
+pub fn toolbar_background(layout: &[(ToolbarItem, WindowRect)]) -> Option<WindowRect> {
+    let (first, last) = (layout.first()?.1, layout.last()?.1);
+
+    Some(WindowRect::from_points(
+        WindowPoint::new(first.min.x - BUTTON_GAP, first.min.y - BUTTON_GAP),
+        WindowPoint::new(last.max.x + BUTTON_GAP, last.max.y + BUTTON_GAP),
+    ))
+}
+
+#[cfg(test)]
+mod unit_test {
+    use crate::geometry::{WindowPoint, WindowRect};
+    use crate::imgui_impl::toolbar::{toolbar_background, toolbar_layout, Tool, ToolbarItem};
+    use crate::utils::annotate::Shape;
+    use crate::utils::redact::RedactKind;
+
+    fn rect(x1: f32, y1: f32, x2: f32, y2: f32) -> WindowRect {
+        WindowRect::from_points(WindowPoint::new(x1, y1), WindowPoint::new(x2, y2))
+    }
+
+    #[test]
+    fn place_toolbar_around_selection() {
+        // 21 个按钮: 宽 21 * 32 + 4 = 676, 高 36
+        let layout = toolbar_layout(rect(500.0, 100.0, 1000.0, 400.0), [1920.0, 1080.0]);
+        assert_eq!(layout.len(), 21);
+        assert_eq!(layout[0], (ToolbarItem::Tool(Tool::Shape(Shape::Rect)), rect(328.0, 410.0, 356.0, 438.0)));
+        assert_eq!(layout[4].0, ToolbarItem::Tool(Tool::Text));
+        assert_eq!(layout[7].0, ToolbarItem::Tool(Tool::Redact(RedactKind::Fill)));
+        assert_eq!(layout[10].0, ToolbarItem::Tool(Tool::Spotlight));
+        assert_eq!(toolbar_background(&layout), Some(rect(324.0, 406.0, 1000.0, 442.0)));
+
+        // 下方空间不足: 位于上方
+        let layout = toolbar_layout(rect(500.0, 100.0, 1000.0, 1060.0), [1920.0, 1080.0]);
+        assert_eq!(toolbar_background(&layout).unwrap().min.y, 58.0);
+
+        // 上下都不足: 位于选框内的底部; 选框过窄时不超出窗口左侧
+        let layout = toolbar_layout(rect(0.0, 0.0, 100.0, 1080.0), [1920.0, 1080.0]);
+        assert_eq!(toolbar_background(&layout), Some(rect(0.0, 1044.0, 676.0, 1080.0)));
+
+        // 窗口过小
+        assert_eq!(toolbar_layout(rect(0.0, 0.0, 100.0, 100.0), [400.0, 300.0]), vec![]);
+    }
+}
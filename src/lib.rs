@@ -1,39 +1,212 @@
 #[macro_use]
 extern crate napi_derive;
 
-use crate::declares::SelectedImage;
+use std::time::Duration;
+use napi::bindgen_prelude::{AsyncTask, Either3};
+use crate::async_task::{CaptureRegionTask, CaptureTask, CaptureWindowTask, CaptureWithCropTask, CaptureWithOutcomeTask, PickColorTask};
+use crate::declares::{CaptureInfo, CaptureSet, ConfirmedOutcome, DisplayDescriptor, EndedOutcome, FailedOutcome, GestureOptions, Gestures, OutputFormat, OutputOptions, PickedColor, RedactRegion, SelectedImage, SelectionOutcome, WindowCapture, WindowInfo, WindowSelector};
+use crate::error::CaptureError;
 use crate::screen_capture::ScreenCapture;
+use crate::screenshots_impl::ScreenshotsImpl;
+use crate::utils::redact::Redaction;
 
 mod declares;
+mod error;
 mod utils;
+mod geometry;
 mod screenshots_impl;
+mod mock_impl;
 mod imgui_impl;
+mod window_list;
+mod async_task;
 
 pub mod screen_capture;
 
+/// 解析 js 传入的输出格式
+fn parse_output(output: Option<OutputOptions>) -> Result<Option<OutputFormat>, CaptureError> {
+    output.map(OutputFormat::try_from).transpose()
+}
+
+/// 转换为 js 错误, `code` 为 `CaptureError::code`
+impl From<CaptureError> for napi::Error<&'static str> {
+    fn from(err: CaptureError) -> Self {
+        napi::Error::new(err.code(), err.to_string())
+    }
+}
+
 #[napi]
 pub fn package_name() -> String {
     "screen_capture".to_string()
 }
 
+/// 获取所有屏幕的信息 (不截图)
+#[napi]
+pub fn list_displays() -> napi::Result<Vec<DisplayDescriptor>, &'static str> {
+    Ok(ScreenCapture::list_displays(&ScreenshotsImpl {})?)
+}
+
+/// 截取所有屏幕, 部分屏幕失败时记录在 `failures` 中
+///
+/// 传入 `output` 时按该格式填充 `buffer`, 否则只返回原始 rgba
+#[napi]
+pub fn capture(output: Option<OutputOptions>) -> napi::Result<CaptureSet, &'static str> {
+    Ok(ScreenCapture::capture(&ScreenshotsImpl {})?.encoded(parse_output(output)?)?)
+}
+
+/// 截取指定 id 的屏幕
+#[napi]
+pub fn capture_screen(screen_id: u32, output: Option<OutputOptions>) -> napi::Result<CaptureInfo, &'static str> {
+    Ok(ScreenCapture::capture_screen(&ScreenshotsImpl {}, screen_id)?.encoded(parse_output(output)?)?)
+}
+
+/// 截取点 (物理坐标) 所在的屏幕, 点不在任何屏幕内时以 `INVALID_ARGUMENT` 错误拒绝
+#[napi]
+pub fn capture_screen_at_point(x: i32, y: i32, output: Option<OutputOptions>) -> napi::Result<CaptureInfo, &'static str> {
+    Ok(ScreenCapture::capture_screen_at_point(&ScreenshotsImpl {}, x, y)?.encoded(parse_output(output)?)?)
+}
+
+/// 截取桌面上任意区域 (物理坐标), 跨越多个屏幕时自动拼接
+///
+/// 区域为空、大于桌面或不与任何屏幕相交时以 `INVALID_ARGUMENT` 错误拒绝
+#[napi]
+pub fn capture_region(x: i32, y: i32, width: u32, height: u32, output: Option<OutputOptions>) -> napi::Result<CaptureInfo, &'static str> {
+    Ok(ScreenCapture::capture_region(&ScreenshotsImpl {}, x, y, width, height)?.encoded(parse_output(output)?)?)
+}
+
+/// 获取所有顶层窗口 (由上到下, 目前仅支持 X11)
+#[napi]
+pub fn list_windows() -> napi::Result<Vec<WindowInfo>, &'static str> {
+    Ok(ScreenCapture::list_windows()?)
+}
+
+/// 截取符合条件的最上层窗口, 没有符合条件的窗口时以 `WINDOW_NOT_FOUND` 错误拒绝
+///
+/// 窗口被遮挡或超出屏幕时直接读取窗口内容 (`composited` 为 true, 不含窗口管理器的边框)
 #[napi]
-pub fn ttt() -> napi::Result<Option<SelectedImage>> {
-    Ok(ScreenCapture::capture_with_crop())
+pub fn capture_window(selector: WindowSelector, output: Option<OutputOptions>) -> napi::Result<WindowCapture, &'static str> {
+    Ok(ScreenCapture::capture_window(&ScreenshotsImpl {}, &selector)?.encoded(parse_output(output)?)?)
+}
+
+/// 对截图 (如 `captureRegion` 的结果) 打码, 区域为桌面物理坐标, 原像素被直接覆盖, 无法从结果中还原
+///
+/// 传入 `output` 时按该格式重新填充 `buffer`, 否则清除 `buffer`; 打码方式未知或马赛克 / 模糊的强度小于 4 时以 `INVALID_ARGUMENT` 错误拒绝
+#[napi]
+pub fn redact(capture: CaptureInfo, regions: Vec<RedactRegion>, output: Option<OutputOptions>) -> napi::Result<CaptureInfo, &'static str> {
+    let redactions = regions
+        .into_iter()
+        .map(Redaction::try_from)
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(ScreenCapture::redact(capture, &redactions)?.encoded(parse_output(output)?)?)
+}
+
+/// 截取所有屏幕并交互式裁剪, 用户取消时返回 `null`
+///
+/// 选区按 `output` 编码, 默认为 png; `gestures` 为鼠标 / 按键的行为, 默认全部启用
+#[napi]
+pub fn ttt(output: Option<OutputOptions>, gestures: Option<GestureOptions>) -> napi::Result<Option<SelectedImage>, &'static str> {
+    let output = parse_output(output)?.unwrap_or_default();
+    let gestures = gestures.map(Gestures::from).unwrap_or_default();
+
+    match ScreenCapture::capture_with_crop(&ScreenshotsImpl {}, output, gestures) {
+        Ok(selected) => Ok(Some(selected)),
+        Err(CaptureError::Cancelled) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// 同 `ttt`, 但不抛出错误也不返回 `null`, 而是按 `kind` 区分选择结束的原因
+///
+/// `timeoutMs` 为选择的时限, 超过时结果为 'timed_out'
+#[napi]
+pub fn capture_with_outcome(output: Option<OutputOptions>, gestures: Option<GestureOptions>, timeout_ms: Option<u32>) -> Either3<ConfirmedOutcome, EndedOutcome, FailedOutcome> {
+    let outcome = parse_output(output).map(|output| {
+        let gestures = gestures.map(Gestures::from).unwrap_or_default();
+        let timeout = timeout_ms.map(|ms| Duration::from_millis(ms as u64));
+        ScreenCapture::capture_with_outcome(&ScreenshotsImpl {}, output.unwrap_or_default(), gestures, timeout)
+    });
+
+    SelectionOutcome::from(outcome).into()
+}
+
+/// 截取所有屏幕并交互式取色, 用户取消时返回 `null`
+///
+/// `sampleSize` 大于 1 时返回以拾取点为中心的区域平均色 (默认 1, 上限 64)
+#[napi]
+pub fn pick_color(sample_size: Option<u32>) -> napi::Result<Option<PickedColor>, &'static str> {
+    match ScreenCapture::pick_color(&ScreenshotsImpl {}, sample_size.unwrap_or(1)) {
+        Ok(picked) => Ok(Some(picked)),
+        Err(CaptureError::Cancelled) => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// `capture` 的异步版本, 在工作线程中执行
+#[napi]
+pub fn capture_async(output: Option<OutputOptions>) -> napi::Result<AsyncTask<CaptureTask>, &'static str> {
+    Ok(AsyncTask::new(CaptureTask { output: parse_output(output)?, error: None }))
+}
+
+/// `capture_region` 的异步版本, 在工作线程中执行
+#[napi]
+pub fn capture_region_async(x: i32, y: i32, width: u32, height: u32, output: Option<OutputOptions>) -> napi::Result<AsyncTask<CaptureRegionTask>, &'static str> {
+    Ok(AsyncTask::new(CaptureRegionTask { xywh: (x, y, width, height), output: parse_output(output)?, error: None }))
+}
+
+/// `capture_window` 的异步版本, 在工作线程中执行
+#[napi]
+pub fn capture_window_async(selector: WindowSelector, output: Option<OutputOptions>) -> napi::Result<AsyncTask<CaptureWindowTask>, &'static str> {
+    Ok(AsyncTask::new(CaptureWindowTask { selector, output: parse_output(output)?, error: None }))
+}
+
+/// `ttt` 的异步版本, 选择窗口在常驻的 UI 线程中运行, 不阻塞 js 线程
+///
+/// 已有选择窗口打开时以 `BUSY` 错误拒绝
+#[napi]
+pub fn capture_with_crop_async(output: Option<OutputOptions>, gestures: Option<GestureOptions>) -> napi::Result<AsyncTask<CaptureWithCropTask>, &'static str> {
+    Ok(AsyncTask::new(CaptureWithCropTask {
+        output: parse_output(output)?.unwrap_or_default(),
+        gestures: gestures.map(Gestures::from).unwrap_or_default(),
+        error: None,
+    }))
+}
+
+/// `capture_with_outcome` 的异步版本, 选择窗口在常驻的 UI 线程中运行, 不阻塞 js 线程
+///
+/// 已有选择窗口打开时结果为 'error' (`BUSY`)
+#[napi]
+pub fn capture_with_outcome_async(output: Option<OutputOptions>, gestures: Option<GestureOptions>, timeout_ms: Option<u32>) -> napi::Result<AsyncTask<CaptureWithOutcomeTask>, &'static str> {
+    Ok(AsyncTask::new(CaptureWithOutcomeTask {
+        output: parse_output(output)?.unwrap_or_default(),
+        gestures: gestures.map(Gestures::from).unwrap_or_default(),
+        timeout: timeout_ms.map(|ms| Duration::from_millis(ms as u64)),
+    }))
+}
+
+/// `pick_color` 的异步版本, 选择窗口在常驻的 UI 线程中运行, 不阻塞 js 线程
+///
+/// 已有选择窗口打开时以 `BUSY` 错误拒绝
+#[napi]
+pub fn pick_color_async(sample_size: Option<u32>) -> AsyncTask<PickColorTask> {
+    AsyncTask::new(PickColorTask { sample_size: sample_size.unwrap_or(1), error: None })
 }
 
 #[cfg(test)]
 mod unit_test {
     use crate::screen_capture::ScreenCapture;
+    use crate::screenshots_impl::ScreenshotsImpl;
 
+    /// 需要图形环境, 打开真实的选择窗口
     #[test]
+    #[ignore]
     fn tt() {
-        match ScreenCapture::capture_with_crop() {
-            Some(v) => {
+        match ScreenCapture::capture_with_crop(&ScreenshotsImpl {}, Default::default(), Default::default()) {
+            Ok(_) => {
                 println!("done");
             }
-            None => {
-                println!("fail");
+            Err(err) => {
+                println!("fail: {err}");
             }
         }
     }
-}
\ No newline at end of file
+}
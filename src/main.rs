@@ -3,33 +3,329 @@
 #[macro_use]
 extern crate napi_derive;
 
-use std::fs;
-use crate::declares::SelectedImage;
+use std::{env, fs};
+use std::time::Duration;
+use crate::declares::{CaptureBackend, CaptureFailure, Gestures, OutputFormat, PickedColor, SelectedImage, SelectionOutcome, WindowCapture, WindowInfo, WindowSelector};
+use crate::error::CaptureError;
+use crate::imgui_impl::ReplayScript;
+use crate::mock_impl::MockImpl;
 use crate::screen_capture::ScreenCapture;
+use crate::screenshots_impl::ScreenshotsImpl;
 
 mod declares;
+mod error;
 mod utils;
+mod geometry;
 mod screenshots_impl;
+mod mock_impl;
 mod imgui_impl;
+mod window_list;
 
 pub mod screen_capture;
 
+/// 子命令
+#[derive(Debug, Clone, PartialEq)]
+enum Command {
+    /// 交互式裁剪 (默认)
+    Crop,
+    /// 交互式取色, 参数为采样区域的边长
+    PickColor(u32),
+    /// 列出所有顶层窗口
+    ListWindows,
+    /// 截取符合条件的窗口
+    CaptureWindow(WindowSelector),
+}
+
+/// 命令行参数
+#[derive(Debug, PartialEq)]
+struct CliArgs {
+    /// 子命令
+    command: Command,
+    /// 虚拟屏幕布局清单 (json), 不传则截取真实屏幕
+    manifest_path: Option<String>,
+    /// 输入事件脚本 (json), 传入时不打开窗口而是回放脚本
+    replay_path: Option<String>,
+    /// 输出格式
+    output: OutputFormat,
+    /// 交互式裁剪的时限
+    timeout: Option<Duration>,
+}
+
+/// 解析命令行参数
+///
+/// - `[manifest] [--replay <script>] [--timeout <seconds>] [--format <png|jpeg|webp|bmp|qoi|tiff|rgba|rgb>] [--quality <1~100>] [--drop-alpha] [--stride <bytes>]`
+/// - `pick-color [manifest] [--replay <script>] [--sample <size>]`
+/// - `list-windows`
+/// - `capture-window [manifest] [--id <id>] [--title <text>] [--class <class>] [--pid <pid>] [--format ...]`
+fn parse_args(args: impl Iterator<Item=String>) -> Result<CliArgs, String> {
+    let mut args = args.peekable();
+    let mut cli = CliArgs { command: Command::Crop, manifest_path: None, replay_path: None, output: OutputFormat::default(), timeout: None };
+
+    if args.next_if_eq("pick-color").is_some() {
+        cli.command = Command::PickColor(1);
+    } else if args.next_if_eq("list-windows").is_some() {
+        cli.command = Command::ListWindows;
+    } else if args.next_if_eq("capture-window").is_some() {
+        cli.command = Command::CaptureWindow(WindowSelector::default());
+    }
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--id" | "--title" | "--class" | "--pid" => {
+                let value = args.next().ok_or_else(|| format!("Missing value for '{arg}'"))?;
+                let Command::CaptureWindow(selector) = &mut cli.command else {
+                    return Err(format!("'{arg}' is only available for 'capture-window'"));
+                };
+                match arg.as_str() {
+                    "--id" => selector.window_id = Some(value.parse().map_err(|_| format!("Invalid window id '{value}'"))?),
+                    "--title" => selector.title = Some(value),
+                    "--class" => selector.class = Some(value),
+                    _ => selector.pid = Some(value.parse().map_err(|_| format!("Invalid pid '{value}'"))?),
+                }
+            }
+            "--replay" | "--format" | "--quality" | "--stride" | "--sample" | "--timeout" => {
+                let value = args.next().ok_or_else(|| format!("Missing value for '{arg}'"))?;
+                match arg.as_str() {
+                    "--replay" => cli.replay_path = Some(value),
+                    "--timeout" => cli.timeout = Some(
+                        value.parse().ok().filter(|seconds: &f64| *seconds > 0.0).map(Duration::from_secs_f64).ok_or_else(|| format!("Invalid timeout '{value}'"))?
+                    ),
+                    "--sample" => match cli.command {
+                        Command::PickColor(_) => cli.command = Command::PickColor(
+                            value.parse().ok().filter(|size| *size > 0).ok_or_else(|| format!("Invalid sample size '{value}'"))?
+                        ),
+                        _ => return Err(String::from("'--sample' is only available for 'pick-color'")),
+                    },
+                    "--format" => cli.output.format = value.parse().map_err(|err: CaptureError| err.to_string())?,
+                    "--quality" => cli.output.quality = value.parse().ok()
+                        .filter(|quality| (1..=100).contains(quality))
+                        .ok_or_else(|| format!("Invalid quality '{value}'"))?,
+                    _ => cli.output.stride = Some(value.parse().map_err(|_| format!("Invalid stride '{value}'"))?),
+                }
+            }
+            "--drop-alpha" => cli.output.drop_alpha = true,
+            _ if cli.manifest_path.is_none() && !arg.starts_with("--") => cli.manifest_path = Some(arg),
+            _ => return Err(format!("Unknown argument '{arg}'")),
+        }
+    }
+
+    if matches!(&cli.command, Command::CaptureWindow(selector) if selector.is_empty()) {
+        return Err(String::from("'capture-window' requires at least one of '--id', '--title', '--class' or '--pid'"));
+    }
+
+    Ok(cli)
+}
+
+/// 交互式选择, 或回放脚本
+fn crop_with(backend: &impl CaptureBackend, replay_path: Option<&str>, output: OutputFormat, timeout: Option<Duration>) -> SelectionOutcome {
+    match replay_path {
+        Some(replay_path) => match ReplayScript::from_file(replay_path) {
+            Ok(script) => ScreenCapture::replay_with_outcome(backend, &script, output),
+            Err(err) => SelectionOutcome::Error(CaptureError::InvalidArgument(err)),
+        },
+        None => ScreenCapture::capture_with_outcome(backend, output, Gestures::default(), timeout),
+    }
+}
+
+/// 交互式取色, 或回放脚本
+fn pick_with(backend: &impl CaptureBackend, replay_path: Option<&str>, sample_size: u32) -> Result<PickedColor, CaptureError> {
+    match replay_path {
+        Some(replay_path) => {
+            let script = ReplayScript::from_file(replay_path).map_err(CaptureError::InvalidArgument)?;
+            ScreenCapture::replay_pick_color(backend, &script, sample_size)
+        }
+        None => ScreenCapture::pick_color(backend, sample_size),
+    }
+}
+
+/// 执行子命令并输出结果
+fn run(backend: &impl CaptureBackend, cli: &CliArgs) {
+    match &cli.command {
+        Command::Crop => print_selected(crop_with(backend, cli.replay_path.as_deref(), cli.output, cli.timeout), cli.output),
+        Command::PickColor(sample_size) => print_picked(pick_with(backend, cli.replay_path.as_deref(), *sample_size)),
+        Command::ListWindows => print_windows(ScreenCapture::list_windows()),
+        Command::CaptureWindow(selector) => print_window_capture(ScreenCapture::capture_window(backend, selector), cli.output),
+    }
+}
+
 fn main() {
-    match ScreenCapture::capture_with_crop() {
-        Some(SelectedImage { p1p2, buffer }) => {
+    let cli = match parse_args(env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(err) => {
+            println!("{err}");
+            return;
+        }
+    };
+
+    match &cli.manifest_path {
+        Some(manifest_path) => match MockImpl::from_manifest(manifest_path) {
+            Ok(backend) => run(&backend, &cli),
+            Err(err) => println!("{err}"),
+        },
+        None => run(&ScreenshotsImpl {}, &cli),
+    }
+}
+
+/// 输出截取失败的屏幕
+fn print_failures(failures: &[CaptureFailure]) {
+    for CaptureFailure { screen_id, reason } in failures {
+        println!("screen {screen_id} skipped: {reason}");
+    }
+}
+
+/// 输出取色结果
+fn print_picked(picked: Result<PickedColor, CaptureError>) {
+    match picked {
+        Ok(PickedColor { x, y, sample_size, hex, rgb, hsl, failures }) => {
+            print_failures(&failures);
+            println!("color at ({x}, {y}) (sample {sample_size}x{sample_size}): {hex}, rgb({}, {}, {}), hsl({:.1}, {:.1}%, {:.1}%)", rgb[0], rgb[1], rgb[2], hsl[0], hsl[1], hsl[2]);
+        }
+        Err(CaptureError::Cancelled) => {
+            println!("done without pick!");
+        }
+        Err(err) => {
+            println!("[{}] {err}", err.code());
+        }
+    }
+}
+
+/// 输出窗口列表 (由上到下)
+fn print_windows(windows: Result<Vec<WindowInfo>, CaptureError>) {
+    match windows {
+        Ok(windows) => {
+            for window in &windows {
+                let pid = window.pid.map_or_else(|| String::from("-"), |pid| pid.to_string());
+                let state = if window.visible { "visible" } else { "hidden" };
+                println!("window {} '{}' class '{}' pid {pid} {state}: {:?}", window.window_id, window.title, window.class, window.physical_rect().xywh());
+            }
+        }
+        Err(err) => {
+            println!("[{}] {err}", err.code());
+        }
+    }
+}
+
+/// 输出窗口截图结果, 图像写入当前目录
+fn print_window_capture(captured: Result<WindowCapture, CaptureError>, output: OutputFormat) {
+    let encoded = captured.and_then(|captured| {
+        let buffer = captured.capture.encode(output)?;
+        Ok((captured, buffer))
+    });
+
+    match encoded {
+        Ok((WindowCapture { window, capture, composited }, buffer)) => {
+            let source = if composited { "window contents" } else { "screen" };
+            println!("window {} '{}' from {source}: {:?}", window.window_id, window.title, capture.physical_rect().xywh());
+
+            let image_name = format!("window_{}.{}", window.window_id, output.format.extension());
+            fs::write(image_name, &buffer).unwrap();
+
+            println!("done with capture!");
+        }
+        Err(err) => {
+            println!("[{}] {err}", err.code());
+        }
+    }
+}
+
+/// 输出裁剪结果, 选区写入当前目录
+fn print_selected(outcome: SelectionOutcome, output: OutputFormat) {
+    match outcome {
+        SelectionOutcome::Confirmed(SelectedImage { p1p2, screens, window, screen_id, buffer, stride, failures }) => {
+            print_failures(&failures);
             let w = (p1p2[2] - p1p2[0]) as u32;
             let h = (p1p2[3] - p1p2[1]) as u32;
 
-            let image_name = format!("capture_{x}_{y}_{w}x{h}.png", x = p1p2[0], y = p1p2[1]);
+            for screen in &screens {
+                println!("screen {} (x{}): physical {:?}, logical {:?}", screen.screen_id, screen.scale_factor, screen.physical_rect, screen.logical_rect);
+            }
+            if let Some(window) = &window {
+                println!("window {} '{}': {:?}", window.window_id, window.title, window.physical_rect().xywh());
+            }
+            if let Some(screen_id) = screen_id {
+                println!("whole screen {screen_id}");
+            }
+            if let Some(stride) = stride {
+                println!("raw pixels, {stride} bytes per row");
+            }
+
+            let image_name = format!("capture_{x}_{y}_{w}x{h}.{ext}", x = p1p2[0], y = p1p2[1], ext = output.format.extension());
             fs::write(image_name, &buffer).unwrap();
 
             println!("done with select!");
         }
-        None => {
+        SelectionOutcome::Cancelled => {
             println!("done without select!");
         }
+        SelectionOutcome::Closed => {
+            println!("done without select! (window closed)");
+        }
+        SelectionOutcome::TimedOut => {
+            println!("done without select! (timed out)");
+        }
+        SelectionOutcome::Error(err) => {
+            println!("[{}] {err}", err.code());
+        }
     }
 }
 
 #[cfg(test)]
-mod unit_test {}
\ No newline at end of file
+mod unit_test {
+    use std::time::Duration;
+    use crate::declares::{ImageFormat, OutputFormat, WindowSelector};
+    use crate::mock_impl::MockImpl;
+    use crate::{CliArgs, Command, crop_with, parse_args, pick_with};
+
+    fn args(line: &str) -> impl Iterator<Item=String> + '_ {
+        line.split_whitespace().map(String::from)
+    }
+
+    #[test]
+    fn parse_output_format() {
+        assert_eq!(parse_args(args("")).unwrap(), CliArgs { command: Command::Crop, manifest_path: None, replay_path: None, output: OutputFormat::default(), timeout: None });
+
+        let cli = parse_args(args("layout.json --replay drag.json --format jpeg --quality 75 --drop-alpha")).unwrap();
+        assert_eq!(cli.manifest_path.as_deref(), Some("layout.json"));
+        assert_eq!(cli.replay_path.as_deref(), Some("drag.json"));
+        assert_eq!(cli.output, OutputFormat { quality: 75, drop_alpha: true, ..OutputFormat::from(ImageFormat::Jpeg) });
+
+        assert!(parse_args(args("--format gif")).is_err());
+        assert!(parse_args(args("--quality 0")).is_err());
+        assert!(parse_args(args("--stride")).is_err());
+
+        assert_eq!(parse_args(args("--timeout 1.5")).unwrap().timeout, Some(Duration::from_millis(1500)));
+        assert!(parse_args(args("--timeout 0")).is_err());
+    }
+
+    #[test]
+    fn reject_missing_replay_script() {
+        let backend = MockImpl::new(vec![]);
+        let cropped = crop_with(&backend, Some("missing-replay.json"), OutputFormat::default(), None).into_result();
+        assert_eq!(cropped.unwrap_err().code(), "INVALID_ARGUMENT");
+        assert_eq!(pick_with(&backend, Some("missing-replay.json"), 1).unwrap_err().code(), "INVALID_ARGUMENT");
+    }
+
+    #[test]
+    fn parse_pick_color() {
+        let cli = parse_args(args("pick-color layout.json --sample 5")).unwrap();
+        assert_eq!(cli.command, Command::PickColor(5));
+        assert_eq!(cli.manifest_path.as_deref(), Some("layout.json"));
+
+        assert_eq!(parse_args(args("pick-color")).unwrap().command, Command::PickColor(1));
+        assert!(parse_args(args("pick-color --sample 0")).is_err());
+        assert!(parse_args(args("--sample 3")).is_err());
+    }
+
+    #[test]
+    fn parse_capture_window() {
+        let cli = parse_args(args("capture-window --title Terminal --pid 42 --format bmp")).unwrap();
+        let selector = WindowSelector { title: Some(String::from("Terminal")), pid: Some(42), ..Default::default() };
+        assert_eq!(cli.command, Command::CaptureWindow(selector));
+        assert_eq!(cli.output.format, ImageFormat::Bmp);
+
+        assert_eq!(parse_args(args("list-windows")).unwrap().command, Command::ListWindows);
+        assert!(parse_args(args("capture-window")).is_err());
+        assert!(parse_args(args("capture-window --id abc")).is_err());
+        assert!(parse_args(args("--title Terminal")).is_err());
+    }
+}
@@ -0,0 +1,266 @@
+use std::fs;
+use std::path::Path;
+use serde::Deserialize;
+use crate::declares::{CaptureBackend, CaptureFailure, CaptureInfo, CaptureSet, DisplayDescriptor};
+use crate::error::CaptureError;
+use crate::utils::{crop_rgba, rotate_rgba};
+
+/// 清单中单个屏幕的描述
+#[derive(Debug, Deserialize)]
+struct MockScreen {
+    /// 屏幕 id
+    id: u32,
+    /// 屏幕左上角 x (物理坐标)
+    x: i32,
+    /// 屏幕左上角 y (物理坐标)
+    y: i32,
+    /// 缩放比例, 缺省为 1.0
+    #[serde(default = "default_scale_factor")]
+    scale_factor: f64,
+    /// 顺时针旋转角度, 缺省为 0
+    #[serde(default)]
+    rotation: f64,
+    /// 刷新率, 缺省为 60
+    #[serde(default = "default_refresh_rate")]
+    refresh_rate: f64,
+    /// 是否为主屏幕, 缺省时第一个屏幕为主屏幕
+    #[serde(default)]
+    is_primary: Option<bool>,
+    /// 屏幕图像 (png), 相对路径基于清单所在目录
+    ///
+    /// 图像为面板原始方向, 加载时按 `rotation` 顺时针旋转为桌面方向
+    image: String,
+}
+
+fn default_scale_factor() -> f64 {
+    1.0
+}
+
+fn default_refresh_rate() -> f64 {
+    60.0
+}
+
+/// 屏幕布局清单
+///
+/// ```json
+/// {
+///   "screens": [
+///     { "id": 1, "x": 0, "y": 0, "scale_factor": 1.0, "image": "left.png" },
+///     { "id": 2, "x": 1920, "y": 0, "scale_factor": 1.5, "rotation": 90, "image": "right.png" }
+///   ]
+/// }
+/// ```
+#[derive(Debug, Deserialize)]
+struct MockManifest {
+    screens: Vec<MockScreen>,
+}
+
+/// 虚拟屏幕后端 (用于无显示器环境下的测试)
+///
+/// 屏幕图像可以直接在内存中给出, 也可以从 json 清单 + png 文件中加载
+#[allow(unused)]
+pub struct MockImpl {
+    /// 各屏幕信息, 与 `captures` 一一对应
+    displays: Vec<DisplayDescriptor>,
+    captures: Vec<CaptureInfo>,
+    /// 截取所有屏幕时报告为失败的屏幕
+    failures: Vec<CaptureFailure>,
+}
+
+#[allow(unused)]
+impl MockImpl {
+    /// 直接使用内存中的截图 (未旋转, 刷新率 60, 第一个屏幕为主屏幕)
+    pub fn new(captures: Vec<CaptureInfo>) -> MockImpl {
+        let displays = captures
+            .iter()
+            .enumerate()
+            .map(|(idx, capture)| MockImpl::describe(capture, 0.0, 60.0, idx == 0))
+            .collect();
+
+        MockImpl { displays, captures, failures: vec![] }
+    }
+
+    /// 截取所有屏幕时额外报告 `failures` (模拟部分屏幕截取失败)
+    pub fn with_failures(self, failures: Vec<CaptureFailure>) -> MockImpl {
+        MockImpl { failures, ..self }
+    }
+
+    /// 根据截图推算屏幕信息
+    fn describe(capture: &CaptureInfo, rotation: f64, refresh_rate: f64, is_primary: bool) -> DisplayDescriptor {
+        DisplayDescriptor {
+            screen_id: capture.screen_id,
+            scale_factor: capture.scale_factor,
+            physical_x: capture.physical_x,
+            physical_y: capture.physical_y,
+            physical_width: capture.physical_width,
+            physical_height: capture.physical_height,
+            logical_width: (capture.physical_width as f64 / capture.scale_factor).round() as u32,
+            logical_height: (capture.physical_height as f64 / capture.scale_factor).round() as u32,
+            rotation,
+            refresh_rate,
+            is_primary,
+        }
+    }
+
+    /// 从 json 清单加载屏幕布局
+    pub fn from_manifest(manifest_path: impl AsRef<Path>) -> Result<MockImpl, String> {
+        let manifest_path = manifest_path.as_ref();
+        let manifest_dir = manifest_path.parent().unwrap_or(Path::new("."));
+
+        let content = fs::read_to_string(manifest_path)
+            .map_err(|err| format!("Failed to read manifest '{}': {err}", manifest_path.display()))?;
+        let manifest: MockManifest = serde_json::from_str(&content)
+            .map_err(|err| format!("Failed to parse manifest '{}': {err}", manifest_path.display()))?;
+
+        let mut displays = vec![];
+        let mut captures = vec![];
+        for (idx, screen) in manifest.screens.into_iter().enumerate() {
+            let image_path = manifest_dir.join(&screen.image);
+            let buffer = fs::read(&image_path)
+                .map_err(|err| format!("Failed to read image '{}': {err}", image_path.display()))?;
+            let image = image::load_from_memory(&buffer)
+                .map_err(|err| format!("Failed to decode image '{}': {err}", image_path.display()))?
+                .to_rgba8();
+
+            // 旋转为桌面方向
+            let (rgba, physical_width, physical_height) = rotate_rgba(image.as_raw(), image.width(), image.height(), screen.rotation);
+
+            let capture = CaptureInfo {
+                screen_id: screen.id,
+                scale_factor: screen.scale_factor,
+                physical_x: screen.x,
+                physical_y: screen.y,
+                physical_width,
+                physical_height,
+                buffer: None,
+                stride: None,
+                rgba,
+            };
+
+            displays.push(MockImpl::describe(&capture, screen.rotation, screen.refresh_rate, screen.is_primary.unwrap_or(idx == 0)));
+            captures.push(capture);
+        }
+
+        Ok(MockImpl { displays, captures, failures: vec![] })
+    }
+
+    /// 根据 id 查找屏幕图像
+    fn find_capture(&self, screen_id: u32) -> Result<&CaptureInfo, CaptureError> {
+        self.captures
+            .iter()
+            .find(|capture| capture.screen_id == screen_id)
+            .ok_or_else(|| CaptureError::Screen { screen_id, reason: String::from("screen not found") })
+    }
+}
+
+/// 桌面上 xywh 区域 (物理坐标) 的单色屏幕截图 (缩放比例为 1.0), 用于测试
+#[cfg(test)]
+pub fn solid_screen(screen_id: u32, xywh: (i32, i32, u32, u32), color: [u8; 4]) -> CaptureInfo {
+    let (physical_x, physical_y, physical_width, physical_height) = xywh;
+
+    CaptureInfo {
+        screen_id,
+        scale_factor: 1.0,
+        physical_x,
+        physical_y,
+        physical_width,
+        physical_height,
+        buffer: None,
+        stride: None,
+        rgba: color.repeat((physical_width * physical_height) as usize),
+    }
+}
+
+impl CaptureBackend for MockImpl {
+    fn capture_all(&self) -> Result<CaptureSet, CaptureError> {
+        if self.captures.is_empty() {
+            return Err(CaptureError::NoDisplays(String::from("mock layout has no screen")));
+        }
+
+        Ok(CaptureSet { captures: self.captures.clone(), failures: self.failures.clone() })
+    }
+
+    fn displays(&self) -> Result<Vec<DisplayDescriptor>, CaptureError> {
+        if self.captures.is_empty() {
+            return Err(CaptureError::NoDisplays(String::from("mock layout has no screen")));
+        }
+
+        Ok(self.displays.clone())
+    }
+
+    fn capture_screen(&self, screen: &DisplayDescriptor) -> Result<CaptureInfo, CaptureError> {
+        self.find_capture(screen.screen_id).cloned()
+    }
+
+    fn capture_area(&self, screen: &DisplayDescriptor, xywh: (i32, i32, u32, u32)) -> Result<CaptureInfo, CaptureError> {
+        let capture = self.find_capture(screen.screen_id)?;
+        let (x, y, w, h) = xywh;
+        let rgba = crop_rgba(&capture.rgba, capture.physical_width, capture.physical_height, xywh);
+
+        Ok(CaptureInfo {
+            screen_id: capture.screen_id,
+            scale_factor: capture.scale_factor,
+            physical_x: capture.physical_x + x,
+            physical_y: capture.physical_y + y,
+            physical_width: w,
+            physical_height: h,
+            buffer: None,
+            stride: None,
+            rgba,
+        })
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use std::fs;
+    use image::{ImageBuffer, Rgba};
+    use crate::declares::CaptureBackend;
+    use crate::mock_impl::MockImpl;
+
+    #[test]
+    fn load_manifest() {
+        let dir = std::env::temp_dir().join("screen_capture_mock_manifest");
+        fs::create_dir_all(&dir).unwrap();
+
+        ImageBuffer::from_pixel(4, 3, Rgba([255u8, 0, 0, 255])).save(dir.join("left.png")).unwrap();
+        // 面板原始方向 3x2, 左上角为白色
+        let mut right = ImageBuffer::from_pixel(3, 2, Rgba([0u8, 0, 255, 255]));
+        right.put_pixel(0, 0, Rgba([255, 255, 255, 255]));
+        right.save(dir.join("right.png")).unwrap();
+        fs::write(dir.join("manifest.json"), r#"{
+            "screens": [
+                { "id": 1, "x": 0, "y": 0, "image": "left.png" },
+                { "id": 2, "x": 4, "y": -1, "scale_factor": 1.5, "rotation": 90, "image": "right.png" }
+            ]
+        }"#).unwrap();
+
+        let backend = MockImpl::from_manifest(dir.join("manifest.json")).unwrap();
+        let captures = backend.capture_all().unwrap().captures;
+        let displays = backend.displays().unwrap();
+
+        assert_eq!(captures.len(), 2);
+        assert_eq!((captures[0].screen_id, captures[0].scale_factor), (1, 1.0));
+        assert_eq!((captures[0].physical_width, captures[0].physical_height), (4, 3));
+        assert_eq!(&captures[0].rgba[0..4], &[255, 0, 0, 255]);
+        assert_eq!((captures[1].physical_x, captures[1].physical_y, captures[1].scale_factor), (4, -1, 1.5));
+
+        // 旋转 90 度后为 2x3, 白色像素位于右上角
+        assert_eq!((captures[1].physical_width, captures[1].physical_height), (2, 3));
+        assert_eq!(&captures[1].rgba[4..8], &[255, 255, 255, 255]);
+
+        assert!(displays[0].is_primary && !displays[1].is_primary);
+        assert!(displays[1].is_portrait());
+        assert_eq!((displays[1].logical_width, displays[1].logical_height), (1, 2));
+    }
+
+    #[test]
+    fn missing_manifest() {
+        assert!(MockImpl::from_manifest("./not_exist/manifest.json").is_err());
+    }
+
+    #[test]
+    fn empty_layout() {
+        assert_eq!(MockImpl::new(vec![]).capture_all().unwrap_err().code(), "NO_DISPLAYS");
+    }
+}
@@ -1,20 +1,528 @@
-use crate::declares::{CaptureInfo, SelectedImage};
-use crate::imgui_impl::ImguiImpl;
-use crate::screenshots_impl::ScreenshotsImpl;
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use std::sync::atomic::{AtomicBool, Ordering};
+use crate::declares::{CaptureBackend, CaptureInfo, CaptureSet, DisplayDescriptor, Gestures, OutputFormat, PickedColor, SelectedImage, SelectionOutcome, WindowCapture, WindowInfo, WindowSelector};
+use crate::error::CaptureError;
+use crate::geometry::DesktopRect;
+use crate::imgui_impl::{ImguiImpl, ReplayScript};
+use crate::utils::{crop_captures, intersect, rgba_len};
+use crate::utils::annotate::spotlight_mask;
+use crate::utils::redact::{apply_redactions, Redaction};
+use crate::window_list;
+
+/// 截图队列及交互式选择窗口的占用状态
+struct CaptureGuard {
+    /// 截图操作串行执行 (并发调用时排队)
+    queue: Mutex<()>,
+    /// 同一时间只允许打开一个交互式选择窗口
+    session: AtomicBool,
+}
+
+/// 模块方法共用的状态
+static GUARD: CaptureGuard = CaptureGuard::new();
+
+impl CaptureGuard {
+    const fn new() -> CaptureGuard {
+        CaptureGuard { queue: Mutex::new(()), session: AtomicBool::new(false) }
+    }
+
+    /// 进入截图队列
+    fn enqueue(&self) -> MutexGuard<'_, ()> {
+        self.queue.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// 进入截图队列, 交互式选择窗口打开时直接拒绝, 以免截到选择窗口
+    ///
+    /// 进入队列后再检查: 之后打开的选择窗口需要等待本次截图结束
+    fn enqueue_idle(&self) -> Result<MutexGuard<'_, ()>, CaptureError> {
+        let queue = self.enqueue();
+        match self.session.load(Ordering::SeqCst) {
+            true => Err(CaptureError::Busy),
+            false => Ok(queue),
+        }
+    }
+
+    /// 占用交互式选择窗口, 已有窗口打开时直接拒绝
+    fn begin_session(&self) -> Result<CropSession<'_>, CaptureError> {
+        if self.session.swap(true, Ordering::SeqCst) {
+            Err(CaptureError::Busy)
+        } else {
+            Ok(CropSession { guard: self })
+        }
+    }
+
+    /// 为已占用的选择窗口截取所有屏幕
+    fn capture_for_session(&self, _session: &CropSession, backend: &impl CaptureBackend) -> Result<CaptureSet, CaptureError> {
+        let _queue = self.enqueue();
+        backend.capture_all()
+    }
+}
+
+/// 交互式选择窗口的占用标记, 离开作用域时释放
+struct CropSession<'a> {
+    guard: &'a CaptureGuard,
+}
+
+impl Drop for CropSession<'_> {
+    fn drop(&mut self) {
+        self.guard.session.store(false, Ordering::SeqCst);
+    }
+}
+
+/// 窗口是否被上方的可见窗口遮挡 (`above` 为其上方的窗口), 停靠栏 / 桌面等组件不计入
+fn is_obscured(above: &[WindowInfo], rect: DesktopRect) -> bool {
+    above
+        .iter()
+        .filter(|window| window.visible && !window.is_desktop_component())
+        .any(|window| window.physical_rect().intersect(&rect).is_some())
+}
+
+/// 区域是否完全位于屏幕内 (按屏幕的并集计算, 镜像 / 重叠的屏幕不重复计入)
+fn is_on_screens(screens: &[DisplayDescriptor], rect: DesktopRect) -> bool {
+    let screens: Vec<DesktopRect> = screens
+        .iter()
+        .map(|screen| DesktopRect::new(screen.physical_x, screen.physical_y, screen.physical_width, screen.physical_height))
+        .collect();
+    spotlight_mask(rect, &screens).is_empty()
+}
+
+/// 区域的尺寸是否不大于桌面 (所有屏幕的外接矩形)
+fn fits_desktop(screens: &[DisplayDescriptor], width: u32, height: u32) -> bool {
+    let (mut xl, mut yl, mut xh, mut yh) = (i64::MAX, i64::MAX, i64::MIN, i64::MIN);
+
+    for screen in screens {
+        let (x, y, w, h) = screen.xywh();
+        xl = xl.min(x as i64);
+        yl = yl.min(y as i64);
+        xh = xh.max(x as i64 + w as i64);
+        yh = yh.max(y as i64 + h as i64);
+    }
+
+    width as i64 <= xh - xl && height as i64 <= yh - yl
+}
 
 /// 作为模块暴露的方法
+///
+/// 可在任意线程中调用: 截图操作排队执行, 交互式选择窗口同时只能打开一个, 且窗口打开期间截取屏幕以 `Busy` 拒绝
 pub struct ScreenCapture {}
 
 #[allow(unused)]
 impl ScreenCapture {
+    /// 获取所有屏幕的信息 (不截图)
+    pub fn list_displays(backend: &impl CaptureBackend) -> Result<Vec<DisplayDescriptor>, CaptureError> {
+        let _queue = GUARD.enqueue();
+        backend.displays()
+    }
+
     /// 截取所有屏幕
-    pub fn capture() -> Vec<CaptureInfo> {
-        ScreenshotsImpl::capture_all()
+    pub fn capture(backend: &impl CaptureBackend) -> Result<CaptureSet, CaptureError> {
+        let _queue = GUARD.enqueue_idle()?;
+        backend.capture_all()
+    }
+
+    /// 截取所有屏幕并裁剪 (截取失败的屏幕不参与裁剪), 选区按 `output` 编码, `gestures` 为鼠标 / 按键的行为
+    ///
+    /// 未确认的结果 (取消 / 关闭 / 超时) 都视为 `Cancelled`, 需要区分时使用 `capture_with_outcome`
+    pub fn capture_with_crop(backend: &impl CaptureBackend, output: OutputFormat, gestures: Gestures) -> Result<SelectedImage, CaptureError> {
+        ScreenCapture::capture_with_outcome(backend, output, gestures, None).into_result()
+    }
+
+    /// 同 `capture_with_crop`, 区分选择结束的原因, `timeout` 为选择的时限
+    pub fn capture_with_outcome(backend: &impl CaptureBackend, output: OutputFormat, gestures: Gestures, timeout: Option<Duration>) -> SelectionOutcome {
+        ScreenCapture::crop_outcome(&GUARD, backend, output, gestures, timeout)
     }
 
-    /// 截取所有屏幕并裁剪
-    pub fn capture_with_crop() -> Option<SelectedImage> {
-        let captures = ScreenshotsImpl::capture_all();
-        ImguiImpl::bounding(captures)
+    /// 占用 `guard` 中的选择窗口后执行 `capture_with_outcome`
+    fn crop_outcome(guard: &CaptureGuard, backend: &impl CaptureBackend, output: OutputFormat, gestures: Gestures, timeout: Option<Duration>) -> SelectionOutcome {
+        let session = guard.begin_session().and_then(|session| {
+            let shoots = guard.capture_for_session(&session, backend)?;
+            Ok((session, shoots))
+        });
+
+        match session {
+            Ok((_session, CaptureSet { captures, failures })) => ImguiImpl::bounding(captures, output, gestures, timeout).with_failures(failures),
+            Err(err) => SelectionOutcome::Error(err),
+        }
+    }
+
+    /// 截取所有屏幕并回放输入事件脚本进行裁剪 (不打开窗口, 用于自动化测试)
+    pub fn replay_with_crop(backend: &impl CaptureBackend, script: &ReplayScript, output: OutputFormat) -> Result<SelectedImage, CaptureError> {
+        ScreenCapture::replay_with_outcome(backend, script, output).into_result()
+    }
+
+    /// 同 `replay_with_crop`, 区分选择结束的原因
+    pub fn replay_with_outcome(backend: &impl CaptureBackend, script: &ReplayScript, output: OutputFormat) -> SelectionOutcome {
+        match ScreenCapture::capture(backend) {
+            Ok(CaptureSet { captures, failures }) => ImguiImpl::replay_outcome(captures, script, output).with_failures(failures),
+            Err(err) => SelectionOutcome::Error(err),
+        }
+    }
+
+    /// 截取所有屏幕并交互式取色 (截取失败的屏幕不参与取色)
+    ///
+    /// 颜色取自原始截图中以拾取点为中心的 `sample_size` x `sample_size` 区域的平均色
+    pub fn pick_color(backend: &impl CaptureBackend, sample_size: u32) -> Result<PickedColor, CaptureError> {
+        let session = GUARD.begin_session()?;
+        let CaptureSet { captures, failures } = GUARD.capture_for_session(&session, backend)?;
+        ImguiImpl::pick_color(captures, sample_size).map(|picked| PickedColor { failures, ..picked })
+    }
+
+    /// 截取所有屏幕并回放输入事件脚本进行取色 (不打开窗口, 用于自动化测试)
+    pub fn replay_pick_color(backend: &impl CaptureBackend, script: &ReplayScript, sample_size: u32) -> Result<PickedColor, CaptureError> {
+        let CaptureSet { captures, failures } = ScreenCapture::capture(backend)?;
+        ImguiImpl::replay_pick_color(captures, script, sample_size).map(|picked| PickedColor { failures, ..picked })
+    }
+
+    /// 获取所有顶层窗口 (由上到下)
+    pub fn list_windows() -> Result<Vec<WindowInfo>, CaptureError> {
+        let _queue = GUARD.enqueue();
+        window_list::list_windows()
+    }
+
+    /// 截取符合条件的最上层窗口 (不需要用户操作)
+    ///
+    /// 窗口完全可见时从屏幕截图中裁剪 (含窗口管理器的边框), 被遮挡或超出屏幕时直接读取窗口内容
+    pub fn capture_window(backend: &impl CaptureBackend, selector: &WindowSelector) -> Result<WindowCapture, CaptureError> {
+        let windows = ScreenCapture::list_windows()?;
+        ScreenCapture::capture_listed_window(backend, &windows, selector, window_list::read_window)
+    }
+
+    /// 在 `windows` (由上到下) 中查找并截取窗口, `read_window` 用于读取被遮挡的窗口
+    fn capture_listed_window(
+        backend: &impl CaptureBackend,
+        windows: &[WindowInfo],
+        selector: &WindowSelector,
+        read_window: impl FnOnce(u32) -> Result<(DesktopRect, Vec<u8>), CaptureError>,
+    ) -> Result<WindowCapture, CaptureError> {
+        if selector.is_empty() {
+            return Err(CaptureError::WindowNotFound(String::from("no condition given")));
+        }
+        let index = windows
+            .iter()
+            .position(|window| selector.matches(window))
+            .ok_or_else(|| CaptureError::WindowNotFound(selector.to_string()))?;
+        let window = windows[index].clone();
+        let rect = window.physical_rect();
+
+        let screens = ScreenCapture::list_displays(backend)?;
+        if window.visible && !is_obscured(&windows[..index], rect) && is_on_screens(&screens, rect) {
+            let (x, y, width, height) = rect.xywh();
+            let capture = ScreenCapture::capture_region(backend, x, y, width, height)?;
+            return Ok(WindowCapture { window, capture, composited: false });
+        }
+
+        let (area, rgba) = {
+            let _queue = GUARD.enqueue();
+            read_window(window.window_id)?
+        };
+
+        // 以相交面积最大的屏幕作为所处屏幕
+        let main_screen = screens
+            .iter()
+            .filter_map(|screen| intersect(screen.xywh(), (area.x, area.y, area.width as i32, area.height as i32)).map(|part| (screen, part)))
+            .max_by_key(|(_, (_, _, pw, ph))| pw * ph)
+            .map(|(screen, _)| screen);
+
+        let capture = CaptureInfo {
+            screen_id: main_screen.map_or(0, |screen| screen.screen_id),
+            scale_factor: main_screen.map_or(1.0, |screen| screen.scale_factor),
+            physical_x: area.x,
+            physical_y: area.y,
+            physical_width: area.width,
+            physical_height: area.height,
+            buffer: None,
+            stride: None,
+            rgba,
+        };
+
+        Ok(WindowCapture { window, capture, composited: true })
+    }
+
+    /// 对截图打码 (不需要用户操作), 打码区域为桌面物理坐标, 与截图不相交的区域忽略
+    ///
+    /// 直接覆盖 `rgba` 中的像素, 并清除按原像素编码的 `buffer`
+    pub fn redact(mut capture: CaptureInfo, redactions: &[Redaction]) -> Result<CaptureInfo, CaptureError> {
+        let rect = capture.physical_rect();
+        if rgba_len(rect.width, rect.height) != Some(capture.rgba.len()) {
+            return Err(CaptureError::InvalidArgument(format!("rgba has {} bytes, expected {}x{}x4", capture.rgba.len(), rect.width, rect.height)));
+        }
+
+        apply_redactions(&mut capture.rgba, rect, redactions);
+        capture.buffer = None;
+        capture.stride = None;
+        Ok(capture)
+    }
+
+    /// 截取指定 id 的屏幕
+    pub fn capture_screen(backend: &impl CaptureBackend, screen_id: u32) -> Result<CaptureInfo, CaptureError> {
+        let _queue = GUARD.enqueue_idle()?;
+        let screen = backend.displays()?
+            .into_iter()
+            .find(|screen| screen.screen_id == screen_id)
+            .ok_or_else(|| CaptureError::Screen { screen_id, reason: String::from("screen not found") })?;
+
+        backend.capture_screen(&screen)
+    }
+
+    /// 截取点 (物理坐标) 所在的屏幕
+    pub fn capture_screen_at_point(backend: &impl CaptureBackend, x: i32, y: i32) -> Result<CaptureInfo, CaptureError> {
+        let _queue = GUARD.enqueue_idle()?;
+        let screen = backend.displays()?
+            .into_iter()
+            .find(|screen| screen.contains(x, y))
+            .ok_or_else(|| CaptureError::InvalidArgument(format!("no screen contains point ({x}, {y})")))?;
+
+        backend.capture_screen(&screen)
+    }
+
+    /// 截取桌面上任意区域 (物理坐标)
+    ///
+    /// 只截取区域与各屏幕相交的部分, 跨越多个屏幕时拼接为一张图像, 不被任何屏幕覆盖的部分为透明
+    pub fn capture_region(backend: &impl CaptureBackend, x: i32, y: i32, width: u32, height: u32) -> Result<CaptureInfo, CaptureError> {
+        if width == 0 || height == 0 {
+            return Err(CaptureError::InvalidArgument(format!("region ({x}, {y}, {width}, {height}) is empty")));
+        }
+        let _queue = GUARD.enqueue_idle()?;
+        let screens = backend.displays()?;
+
+        // 区域大于桌面或右下角溢出时直接拒绝, 之后的坐标计算不会溢出
+        let overflows = x.checked_add(width as i32).is_none() || y.checked_add(height as i32).is_none();
+        if !fits_desktop(&screens, width, height) || overflows {
+            return Err(CaptureError::InvalidArgument(format!("region ({x}, {y}, {width}, {height}) exceeds the desktop")));
+        }
+        let region = (x, y, width as i32, height as i32);
+
+        // 与区域相交的屏幕及相交部分
+        let parts: Vec<(DisplayDescriptor, (i32, i32, i32, i32))> = screens
+            .into_iter()
+            .filter_map(|screen| intersect(screen.xywh(), region).map(|part| (screen, part)))
+            .collect();
+
+        if parts.is_empty() {
+            return Err(CaptureError::InvalidArgument(format!("no screen intersects region ({x}, {y}, {width}, {height})")));
+        }
+
+        let mut captures = vec![];
+        for (screen, (px, py, pw, ph)) in &parts {
+            let relative = (px - screen.physical_x, py - screen.physical_y, *pw as u32, *ph as u32);
+            captures.push(backend.capture_area(screen, relative)?);
+        }
+
+        // 区域完全位于单个屏幕内: 直接返回
+        if captures.len() == 1 && (width, height) == (captures[0].physical_width, captures[0].physical_height) {
+            return Ok(captures.remove(0));
+        }
+
+        // 拼接各部分
+        let rgba = crop_captures(&captures, (x, y, width, height));
+
+        // 以相交面积最大的屏幕作为所处屏幕
+        let (main_screen, _) = parts
+            .iter()
+            .max_by_key(|(_, (_, _, pw, ph))| pw * ph)
+            .unwrap();
+
+        Ok(CaptureInfo {
+            screen_id: main_screen.screen_id,
+            scale_factor: main_screen.scale_factor,
+            physical_x: x,
+            physical_y: y,
+            physical_width: width,
+            physical_height: height,
+            buffer: None,
+            stride: None,
+            rgba,
+        })
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use crate::declares::{CaptureFailure, CaptureInfo, ImageFormat, OutputFormat, WindowInfo, WindowSelector};
+    use crate::error::CaptureError;
+    use crate::geometry::DesktopRect;
+    use crate::imgui_impl::ReplayScript;
+    use crate::mock_impl::{solid_screen, MockImpl};
+    use crate::screen_capture::{is_on_screens, CaptureGuard, ScreenCapture};
+    use crate::utils::redact::{RedactKind, Redaction};
+
+    /// 左: 红色 4x4 屏幕, 右: 蓝色 4x4 屏幕 (向下偏移 2)
+    fn two_screens() -> MockImpl {
+        MockImpl::new(vec![
+            solid_screen(1, (0, 0, 4, 4), [255, 0, 0, 255]),
+            solid_screen(2, (4, 2, 4, 4), [0, 0, 255, 255]),
+        ])
+    }
+
+    fn pixel(capture: &CaptureInfo, x: u32, y: u32) -> &[u8] {
+        let i = ((y * capture.physical_width + x) * 4) as usize;
+        &capture.rgba[i..i + 4]
+    }
+
+    #[test]
+    fn capture_screen_by_id_and_point() {
+        let backend = two_screens();
+
+        assert_eq!(ScreenCapture::capture_screen(&backend, 2).unwrap().physical_x, 4);
+        assert_eq!(ScreenCapture::capture_screen_at_point(&backend, 5, 5).unwrap().screen_id, 2);
+        assert_eq!(ScreenCapture::capture_screen(&backend, 3).unwrap_err().code(), "SCREEN_CAPTURE_FAILED");
+        assert_eq!(ScreenCapture::capture_screen_at_point(&backend, 5, 0).unwrap_err().code(), "INVALID_ARGUMENT");
+    }
+
+    #[test]
+    fn capture_region_inside_single_screen() {
+        let capture = ScreenCapture::capture_region(&two_screens(), 1, 1, 2, 3).unwrap();
+
+        assert_eq!((capture.screen_id, capture.physical_x, capture.physical_y), (1, 1, 1));
+        assert_eq!((capture.physical_width, capture.physical_height), (2, 3));
+        assert_eq!(capture.rgba.len(), 2 * 3 * 4);
+        // 默认不编码, 按需编码
+        assert!(capture.buffer.is_none());
+        assert!(capture.encode(OutputFormat::default()).unwrap().starts_with(b"\x89PNG"));
+        // 原始阵列同时返回每行的字节数
+        let raw = capture.clone().encoded(Some(OutputFormat { stride: Some(8), ..OutputFormat::from(ImageFormat::RawRgb) })).unwrap();
+        assert_eq!((raw.stride, raw.buffer.unwrap().len()), (Some(8), 8 * 3));
+        let bmp = capture.encoded(Some(OutputFormat::from(ImageFormat::Bmp))).unwrap();
+        assert!(bmp.stride.is_none() && bmp.buffer.unwrap().starts_with(b"BM"));
+    }
+
+    #[test]
+    fn capture_region_across_screens() {
+        let capture = ScreenCapture::capture_region(&two_screens(), 2, 1, 4, 3).unwrap();
+
+        assert_eq!((capture.physical_x, capture.physical_y, capture.physical_width, capture.physical_height), (2, 1, 4, 3));
+        // 左屏部分
+        assert_eq!(pixel(&capture, 0, 0), &[255, 0, 0, 255]);
+        assert_eq!(pixel(&capture, 1, 2), &[255, 0, 0, 255]);
+        // 右屏部分
+        assert_eq!(pixel(&capture, 2, 1), &[0, 0, 255, 255]);
+        // 未被屏幕覆盖的部分
+        assert_eq!(pixel(&capture, 3, 0), &[0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn reject_concurrent_crop() {
+        // 使用独立的状态, 不影响并行的其他测试
+        let guard = CaptureGuard::new();
+        let session = guard.begin_session().unwrap();
+
+        // 选择窗口打开期间不能再打开选择窗口, 也不能截取屏幕
+        assert_eq!(guard.begin_session().err(), Some(CaptureError::Busy));
+        assert_eq!(guard.enqueue_idle().err(), Some(CaptureError::Busy));
+        let outcome = ScreenCapture::crop_outcome(&guard, &two_screens(), OutputFormat::default(), Default::default(), None);
+        assert_eq!(outcome.into_result().unwrap_err().code(), "BUSY");
+
+        drop(session);
+        assert!(guard.enqueue_idle().is_ok() && guard.begin_session().is_ok());
+    }
+
+    #[test]
+    fn report_failures_with_selection() {
+        let failure = CaptureFailure { screen_id: 3, reason: String::from("permission denied") };
+        let backend = two_screens().with_failures(vec![failure.clone()]);
+        let script = ReplayScript::parse(r#"{
+            "events": [
+                { "type": "cursor_moved", "x": 0, "y": 1 },
+                { "type": "button", "button": "left", "pressed": true },
+                { "type": "cursor_moved", "x": 3, "y": 4 },
+                { "type": "button", "button": "left", "pressed": false },
+                { "type": "key", "key": "enter", "pressed": true }
+            ]
+        }"#).unwrap();
+
+        // 截取失败的屏幕不参与裁剪 / 取色, 但随结果返回
+        let selected = ScreenCapture::replay_with_crop(&backend, &script, OutputFormat::default()).unwrap();
+        assert_eq!((selected.p1p2.clone(), selected.failures), (vec![0.0, 1.0, 3.0, 4.0], vec![failure.clone()]));
+        let picked = ScreenCapture::replay_pick_color(&backend, &script, 1).unwrap();
+        assert_eq!((picked.hex.as_str(), picked.failures), ("#FF0000", vec![failure]));
+    }
+
+    fn window(window_id: u32, title: &str, xywh: (i32, i32, u32, u32)) -> WindowInfo {
+        let (physical_x, physical_y, physical_width, physical_height) = xywh;
+        WindowInfo { window_id, title: String::from(title), class: String::from("Demo"), window_type: String::from("normal"), pid: Some(window_id * 10), visible: true, physical_x, physical_y, physical_width, physical_height }
+    }
+
+    #[test]
+    fn overlapping_screens_cover_once() {
+        // 镜像的两个屏幕: 相交面积之和等于区域面积, 但区域有一半不在屏幕内
+        let mirrored = MockImpl::new(vec![solid_screen(1, (0, 0, 4, 4), [255, 0, 0, 255]), solid_screen(2, (0, 0, 4, 4), [0, 0, 255, 255])]);
+        let screens = ScreenCapture::list_displays(&mirrored).unwrap();
+        assert!(!is_on_screens(&screens, DesktopRect::new(2, 0, 4, 4)));
+        assert!(is_on_screens(&screens, DesktopRect::new(0, 0, 4, 4)));
+
+        let screens = ScreenCapture::list_displays(&two_screens()).unwrap();
+        assert!(is_on_screens(&screens, DesktopRect::new(2, 2, 4, 2)));
+        assert!(!is_on_screens(&screens, DesktopRect::new(2, 1, 4, 2)));
+    }
+
+    #[test]
+    fn capture_window_by_selector() {
+        let backend = two_screens();
+        // 由上到下: "editor" 在左屏内且未被遮挡, "terminal" 被 "editor" 遮挡
+        let windows = [window(1, "editor", (0, 0, 2, 2)), window(2, "Terminal", (1, 1, 5, 2))];
+        let read_window = |_| Ok((DesktopRect::new(2, 2, 3, 1), [0, 255, 0, 255].repeat(3)));
+
+        // 从屏幕截图中裁剪
+        let captured = ScreenCapture::capture_listed_window(&backend, &windows, &WindowSelector { pid: Some(10), ..Default::default() }, read_window).unwrap();
+        assert_eq!((captured.window.window_id, captured.composited), (1, false));
+        assert_eq!(captured.capture.physical_rect().xywh(), (0, 0, 2, 2));
+        assert_eq!(pixel(&captured.capture, 1, 1), &[255, 0, 0, 255]);
+
+        // 被遮挡: 直接读取窗口内容
+        let selector = WindowSelector { title: Some(String::from("term")), class: Some(String::from("demo")), ..Default::default() };
+        let captured = ScreenCapture::capture_listed_window(&backend, &windows, &selector, read_window).unwrap();
+        assert_eq!((captured.window.window_id, captured.composited), (2, true));
+        assert_eq!((captured.capture.screen_id, captured.capture.physical_rect().xywh()), (1, (2, 2, 3, 1)));
+        assert_eq!(pixel(&captured.capture, 2, 0), &[0, 255, 0, 255]);
+
+        // 上方的停靠栏不视为遮挡
+        let dock = WindowInfo { window_type: String::from("dock"), ..window(3, "panel", (0, 0, 6, 1)) };
+        let windows = [dock, window(1, "editor", (0, 0, 2, 2))];
+        let captured = ScreenCapture::capture_listed_window(&backend, &windows, &WindowSelector { window_id: Some(1), ..Default::default() }, read_window).unwrap();
+        assert_eq!((captured.window.window_id, captured.composited), (1, false));
+
+        // 没有符合条件的窗口
+        let selector = WindowSelector { window_id: Some(1), title: Some(String::from("term")), ..Default::default() };
+        assert_eq!(ScreenCapture::capture_listed_window(&backend, &windows, &selector, read_window).unwrap_err().code(), "WINDOW_NOT_FOUND");
+        assert_eq!(ScreenCapture::capture_listed_window(&backend, &windows, &WindowSelector::default(), read_window).unwrap_err().code(), "WINDOW_NOT_FOUND");
+    }
+
+    #[test]
+    fn redact_capture() {
+        let mut capture = ScreenCapture::capture_region(&two_screens(), 2, 1, 4, 3).unwrap().encoded(Some(OutputFormat::default())).unwrap();
+        let redactions = [
+            Redaction { kind: RedactKind::Fill, rect: DesktopRect::new(3, 0, 2, 2), strength: 1, color: [0, 255, 0] },
+            Redaction { kind: RedactKind::Pixelate, rect: DesktopRect::new(2, 3, 4, 1), strength: 4, color: [0, 0, 0] },
+        ];
+        capture = ScreenCapture::redact(capture, &redactions).unwrap();
+
+        // 填充与截图相交的部分, 马赛克混合左右两屏及未覆盖的像素
+        assert!(capture.buffer.is_none());
+        assert_eq!((pixel(&capture, 0, 0), pixel(&capture, 1, 0), pixel(&capture, 2, 0)), (&[255, 0, 0, 255][..], &[0, 255, 0, 255][..], &[0, 255, 0, 255][..]));
+        assert_eq!(pixel(&capture, 1, 1), &[255, 0, 0, 255]);
+        assert_eq!(pixel(&capture, 0, 2), &[128, 0, 128, 255]);
+        assert_eq!(pixel(&capture, 3, 2), &[128, 0, 128, 255]);
+
+        capture.rgba.pop();
+        assert_eq!(ScreenCapture::redact(capture, &redactions).unwrap_err().code(), "INVALID_ARGUMENT");
+    }
+
+    #[test]
+    fn capture_region_outside_screens() {
+        // 与 headless 环境 (NO_DISPLAYS) 区分
+        assert_eq!(ScreenCapture::capture_region(&two_screens(), 100, 100, 4, 4).unwrap_err().code(), "INVALID_ARGUMENT");
+        assert_eq!(ScreenCapture::capture_region(&two_screens(), 0, 0, 0, 4).unwrap_err().code(), "INVALID_ARGUMENT");
+        assert_eq!(ScreenCapture::capture_region(&MockImpl::new(vec![]), 0, 0, 4, 4).unwrap_err().code(), "NO_DISPLAYS");
+    }
+
+    #[test]
+    fn reject_oversized_region() {
+        // 桌面为 8x6
+        let backend = two_screens();
+
+        assert_eq!(ScreenCapture::capture_region(&backend, 0, 0, 40000, 40000).unwrap_err().code(), "INVALID_ARGUMENT");
+        assert_eq!(ScreenCapture::capture_region(&backend, 0, 0, 9, 6).unwrap_err().code(), "INVALID_ARGUMENT");
+        assert_eq!(ScreenCapture::capture_region(&backend, i32::MAX - 2, 0, 4, 4).unwrap_err().code(), "INVALID_ARGUMENT");
+        assert_eq!(ScreenCapture::capture_region(&backend, -2, 0, 8, 6).unwrap().rgba.len(), 8 * 6 * 4);
     }
-}
\ No newline at end of file
+}
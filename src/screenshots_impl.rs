@@ -1,46 +1,150 @@
 use screenshots::{DisplayInfo, Screen};
-use crate::declares::CaptureInfo;
+use crate::declares::{CaptureBackend, CaptureFailure, CaptureInfo, CaptureSet, DisplayDescriptor};
+use crate::error::CaptureError;
+use crate::utils::{crop_rgba, rotate_rgba};
 
 /// `screenshots` 库拿到的坐标基本为物理坐标
 pub struct ScreenshotsImpl {}
 
 impl ScreenshotsImpl {
-    /// 截取所有屏幕 (失败的屏幕静默忽略)
-    pub fn capture_all() -> Vec<CaptureInfo> {
+    /// 获取所有屏幕
+    fn all_screens() -> Result<Vec<Screen>, CaptureError> {
+        let screens = Screen::all()
+            .map_err(|err| CaptureError::NoDisplays(err.to_string()))?;
+
+        if screens.is_empty() {
+            return Err(CaptureError::NoDisplays(String::from("no screen was found")));
+        }
+
+        Ok(screens)
+    }
+
+    /// 根据 id 查找屏幕
+    fn find_screen(screen_id: u32) -> Result<Screen, CaptureError> {
+        ScreenshotsImpl::all_screens()?
+            .into_iter()
+            .find(|screen| screen.display_info.id == screen_id)
+            .ok_or_else(|| CaptureError::Screen { screen_id, reason: String::from("screen not found") })
+    }
+
+    /// `DisplayInfo` 的宽高为逻辑尺寸, 需换算为物理尺寸
+    fn to_descriptor(display_info: &DisplayInfo) -> DisplayDescriptor {
+        let DisplayInfo { id, x, y, width, height, rotation, scale_factor, frequency, is_primary, .. } = *display_info;
+
+        DisplayDescriptor {
+            screen_id: id,
+            scale_factor: scale_factor as f64,
+            physical_x: x,
+            physical_y: y,
+            physical_width: (width as f32 * scale_factor) as u32,
+            physical_height: (height as f32 * scale_factor) as u32,
+            logical_width: width,
+            logical_height: height,
+            rotation: rotation as f64,
+            refresh_rate: frequency as f64,
+            is_primary,
+        }
+    }
+
+    /// 组装截图信息
+    fn to_capture_info(screen: &DisplayDescriptor, xywh: (i32, i32, u32, u32), rgba: Vec<u8>) -> CaptureInfo {
+        let (physical_x, physical_y, physical_width, physical_height) = xywh;
+
+        CaptureInfo {
+            screen_id: screen.screen_id,
+            scale_factor: screen.scale_factor,
+            physical_x,
+            physical_y,
+            physical_width,
+            physical_height,
+            buffer: None,
+            stride: None,
+            rgba,
+        }
+    }
+
+    /// 截取完整屏幕
+    ///
+    /// 旋转 90 / 270 度的屏幕若返回了面板原始方向的图像 (宽高与屏幕相反), 则旋转为桌面方向
+    fn capture_image(screen: &Screen, display: &DisplayDescriptor) -> Result<CaptureInfo, String> {
+        let image = screen.capture().map_err(|err| err.to_string())?;
+        let (width, height) = (image.width(), image.height());
+
+        let (rgba, width, height) = if display.is_portrait() && width != height && (width, height) == (display.physical_height, display.physical_width) {
+            rotate_rgba(image.rgba(), width, height, display.rotation)
+        } else {
+            (image.rgba().clone(), width, height)
+        };
+
+        Ok(ScreenshotsImpl::to_capture_info(display, (display.physical_x, display.physical_y, width, height), rgba))
+    }
+}
+
+impl CaptureBackend for ScreenshotsImpl {
+    /// 截取所有屏幕 (失败的屏幕记录在 `failures` 中)
+    fn capture_all(&self) -> Result<CaptureSet, CaptureError> {
         // 获取所有屏幕
-        match Screen::all() {
-            Ok(screens) => {
-                // 储存所有屏幕截图
-                let mut shoots: Vec<CaptureInfo> = vec![];
-
-                for screen in screens {
-                    match screen.capture() {
-                        Ok(image) => {
-                            let DisplayInfo { id, x, y, scale_factor, .. } = screen.display_info;
-                            shoots.push(CaptureInfo {
-                                screen_id: id,
-                                scale_factor: scale_factor as f64,
-                                physical_x: x,
-                                physical_y: y,
-                                physical_width: image.width(),
-                                physical_height: image.height(),
-                                buffer: image.to_png().unwrap_or(vec![]),
-                                rgba: image.rgba().clone(),
-                            });
-                        }
-                        Err(_) => {
-                            println!("Failed to get screen image");
-                        }
-                    }
-                }
-
-                shoots
-            }
-            Err(_) => {
-                println!("Failed to get screen information");
-                vec![]
+        let screens = ScreenshotsImpl::all_screens()?;
+
+        // 储存所有屏幕截图
+        let mut shoots = CaptureSet { captures: vec![], failures: vec![] };
+
+        for screen in screens {
+            let display = ScreenshotsImpl::to_descriptor(&screen.display_info);
+
+            match ScreenshotsImpl::capture_image(&screen, &display) {
+                Ok(capture) => shoots.captures.push(capture),
+                Err(reason) => shoots.failures.push(CaptureFailure {
+                    screen_id: display.screen_id,
+                    reason,
+                }),
             }
         }
+
+        // 所有屏幕都失败了: 返回第一个失败的原因
+        if shoots.captures.is_empty() {
+            let CaptureFailure { screen_id, reason } = shoots.failures.remove(0);
+            return Err(CaptureError::Screen { screen_id, reason });
+        }
+
+        Ok(shoots)
+    }
+
+    fn displays(&self) -> Result<Vec<DisplayDescriptor>, CaptureError> {
+        Ok(ScreenshotsImpl::all_screens()?
+            .iter()
+            .map(|screen| ScreenshotsImpl::to_descriptor(&screen.display_info))
+            .collect())
     }
-}
 
+    fn capture_screen(&self, screen: &DisplayDescriptor) -> Result<CaptureInfo, CaptureError> {
+        let screen_id = screen.screen_id;
+
+        ScreenshotsImpl::capture_image(&ScreenshotsImpl::find_screen(screen_id)?, screen)
+            .map_err(|reason| CaptureError::Screen { screen_id, reason })
+    }
+
+    /// `Screen::capture_area` 使用逻辑坐标, 因此先截取覆盖目标区域的逻辑区域, 再裁剪为精确的物理区域
+    fn capture_area(&self, screen: &DisplayDescriptor, xywh: (i32, i32, u32, u32)) -> Result<CaptureInfo, CaptureError> {
+        let screen_id = screen.screen_id;
+        let (x, y, w, h) = xywh;
+        let scale_factor = screen.scale_factor;
+
+        // 覆盖目标区域的逻辑区域
+        let logical_x = (x as f64 / scale_factor).floor() as i32;
+        let logical_y = (y as f64 / scale_factor).floor() as i32;
+        let logical_w = ((x + w as i32) as f64 / scale_factor).ceil() as i32 - logical_x;
+        let logical_h = ((y + h as i32) as f64 / scale_factor).ceil() as i32 - logical_y;
+
+        let image = ScreenshotsImpl::find_screen(screen_id)?
+            .capture_area(logical_x, logical_y, logical_w as u32, logical_h as u32)
+            .map_err(|err| CaptureError::Screen { screen_id, reason: err.to_string() })?;
+
+        // 逻辑区域左上角对应的物理坐标 (与 `screenshots` 内部的换算方式一致)
+        let offset_x = x - (logical_x as f64 * scale_factor) as i32;
+        let offset_y = y - (logical_y as f64 * scale_factor) as i32;
+        let rgba = crop_rgba(image.rgba(), image.width(), image.height(), (offset_x, offset_y, w, h));
+
+        Ok(ScreenshotsImpl::to_capture_info(screen, (screen.physical_x + x, screen.physical_y + y, w, h), rgba))
+    }
+}
@@ -0,0 +1,552 @@
+use ab_glyph::FontArc;
+use crate::geometry::DesktopRect;
+use crate::utils::{blend_pixel, rgba_len};
+use crate::utils::redact::{apply_redactions, Redaction};
+use crate::utils::text::{contrast_background, draw_text_label, TextLabel};
+
+/// 标注的图形
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    /// 矩形
+    Rect,
+    /// 椭圆
+    Ellipse,
+    /// 直线
+    Line,
+    /// 箭头 (箭头位于终点)
+    Arrow,
+}
+
+/// 标注的样式
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AnnotationStyle {
+    /// 颜色 rgba
+    pub color: [u8; 4],
+    /// 线宽 (物理像素)
+    pub stroke_width: f32,
+    /// 是否填充 (仅矩形 / 椭圆)
+    pub fill: bool,
+}
+
+/// 一个标注 (桌面物理坐标)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Annotation {
+    pub shape: Shape,
+    /// 起点, 矩形 / 椭圆为外接矩形的一角
+    pub from: [f32; 2],
+    /// 终点, 矩形 / 椭圆为外接矩形的另一角
+    pub to: [f32; 2],
+    pub style: AnnotationStyle,
+}
+
+/// 编号标记: 实心圆 + 编号 (桌面物理坐标), 编号为其在所有标记中的顺序
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepMarker {
+    pub center: [f32; 2],
+    pub radius: f32,
+    /// 圆的颜色 rgba, 编号为与之形成对比的黑色 / 白色
+    pub color: [u8; 4],
+}
+
+/// 荧光笔的笔迹 (桌面物理坐标)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Highlight {
+    pub points: Vec<[f32; 2]>,
+    /// 笔触的宽度 (物理像素)
+    pub width: f32,
+    /// 半透明的颜色 rgba, 笔迹自身重叠处不加深
+    pub color: [u8; 4],
+}
+
+/// 选区上的标注, 按添加的顺序绘制 (打码作用于截图像素, 先于其他标注; 聚光灯之外变暗, 其次于打码)
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mark {
+    Shape(Annotation),
+    Text(TextLabel),
+    Redact(Redaction),
+    Marker(StepMarker),
+    Highlight(Highlight),
+    /// 聚光灯: 选区内除各聚光灯区域外都变暗
+    Spotlight(DesktopRect),
+}
+
+/// 聚光灯之外变暗的颜色 (同选择窗口中选区外的蒙层)
+pub const DIM_COLOR: [u8; 4] = [0x00, 0x00, 0x00, 0x66];
+
+/// 标注中的各打码区域 (按添加的顺序)
+pub fn redactions(marks: &[Mark]) -> Vec<Redaction> {
+    marks
+        .iter()
+        .filter_map(|mark| match mark {
+            Mark::Redact(redaction) => Some(*redaction),
+            _ => None,
+        })
+        .collect()
+}
+
+/// 箭头头部的最小长度
+const MIN_ARROW_HEAD: f32 = 10.0;
+
+/// 七段数码管各段的端点 (相对数字框, 宽 1 高 2): a, b, c, d, e, f, g
+const SEGMENTS: [([f32; 2], [f32; 2]); 7] = [
+    ([0.0, 0.0], [1.0, 0.0]),
+    ([1.0, 0.0], [1.0, 1.0]),
+    ([1.0, 1.0], [1.0, 2.0]),
+    ([0.0, 2.0], [1.0, 2.0]),
+    ([0.0, 1.0], [0.0, 2.0]),
+    ([0.0, 0.0], [0.0, 1.0]),
+    ([0.0, 1.0], [1.0, 1.0]),
+];
+
+/// 数字 0 ~ 9 点亮的段 (由低位到高位为 a ~ g)
+const DIGIT_SEGMENTS: [u8; 10] = [0x3f, 0x06, 0x5b, 0x4f, 0x66, 0x6d, 0x7d, 0x07, 0x7f, 0x6f];
+
+fn sub(a: [f32; 2], b: [f32; 2]) -> [f32; 2] {
+    [a[0] - b[0], a[1] - b[1]]
+}
+
+fn dot(a: [f32; 2], b: [f32; 2]) -> f32 {
+    a[0] * b[0] + a[1] * b[1]
+}
+
+fn length(a: [f32; 2]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+/// 点到线段的距离
+fn segment_distance(p: [f32; 2], a: [f32; 2], b: [f32; 2]) -> f32 {
+    let (ap, ab) = (sub(p, a), sub(b, a));
+    let t = match dot(ab, ab) {
+        len2 if len2 > 0.0 => (dot(ap, ab) / len2).clamp(0.0, 1.0),
+        _ => 0.0,
+    };
+
+    length([ap[0] - ab[0] * t, ap[1] - ab[1] * t])
+}
+
+/// 点到矩形边界的有向距离 (内部为负)
+fn rect_distance(p: [f32; 2], min: [f32; 2], max: [f32; 2]) -> f32 {
+    let center = [(min[0] + max[0]) / 2.0, (min[1] + max[1]) / 2.0];
+    let half = [(max[0] - min[0]) / 2.0, (max[1] - min[1]) / 2.0];
+    let q = [(p[0] - center[0]).abs() - half[0], (p[1] - center[1]).abs() - half[1]];
+
+    length([q[0].max(0.0), q[1].max(0.0)]) + q[0].max(q[1]).min(0.0)
+}
+
+/// 点到椭圆边界的有向距离 (内部为负, 按梯度近似)
+fn ellipse_distance(p: [f32; 2], min: [f32; 2], max: [f32; 2]) -> f32 {
+    let (a, b) = (((max[0] - min[0]) / 2.0).max(0.5), ((max[1] - min[1]) / 2.0).max(0.5));
+    let (x, y) = (p[0] - (min[0] + max[0]) / 2.0, p[1] - (min[1] + max[1]) / 2.0);
+    let f = (x / a).powi(2) + (y / b).powi(2) - 1.0;
+    let gradient = 2.0 * length([x / (a * a), y / (b * b)]);
+
+    if gradient > f32::EPSILON { f / gradient } else { -a.min(b) }
+}
+
+/// 点到三角形边界的有向距离 (内部为负, 顶点附近为近似值)
+fn triangle_distance(p: [f32; 2], triangle: [[f32; 2]; 3]) -> f32 {
+    let [a, b, c] = triangle;
+    // 顺时针时翻转法线, 使其朝外
+    let orientation = if (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]) >= 0.0 { 1.0 } else { -1.0 };
+
+    [(a, b), (b, c), (c, a)]
+        .into_iter()
+        .map(|(start, end)| {
+            let edge = sub(end, start);
+            let normal = [edge[1] * orientation, -edge[0] * orientation];
+            dot(sub(p, start), normal) / length(normal).max(f32::EPSILON)
+        })
+        .fold(f32::MIN, f32::max)
+}
+
+/// 有向距离 -> 像素覆盖率 (边缘 1 像素内抗锯齿)
+fn edge_coverage(distance: f32) -> f32 {
+    (0.5 - distance).clamp(0.0, 1.0)
+}
+
+impl Annotation {
+    /// 外接矩形的左上点和右下点
+    fn corners(&self) -> ([f32; 2], [f32; 2]) {
+        let [x1, y1] = self.from;
+        let [x2, y2] = self.to;
+        ([x1.min(x2), y1.min(y2)], [x1.max(x2), y1.max(y2)])
+    }
+
+    /// 箭头的头部: (线段的终点, 三角形的三个顶点), 终点为三角形的尖端
+    pub fn arrow_head(&self) -> ([f32; 2], [[f32; 2]; 3]) {
+        let direction = sub(self.to, self.from);
+        let len = length(direction);
+        let head = (self.style.stroke_width * 3.0).max(MIN_ARROW_HEAD).min(len);
+        if head <= 0.0 {
+            return (self.to, [self.to; 3]);
+        }
+
+        let unit = [direction[0] / len, direction[1] / len];
+        let base = [self.to[0] - unit[0] * head, self.to[1] - unit[1] * head];
+        let (nx, ny) = (-unit[1] * head / 2.0, unit[0] * head / 2.0);
+
+        (base, [[base[0] + nx, base[1] + ny], self.to, [base[0] - nx, base[1] - ny]])
+    }
+
+    /// 可能覆盖的像素范围 (桌面物理坐标)
+    fn bounds(&self) -> DesktopRect {
+        let (min, max) = self.corners();
+        let pad = self.style.stroke_width / 2.0 + match self.shape {
+            Shape::Arrow => (self.style.stroke_width * 3.0).max(MIN_ARROW_HEAD),
+            _ => 0.0,
+        } + 1.0;
+        let (x1, y1) = ((min[0] - pad).floor() as i32, (min[1] - pad).floor() as i32);
+        let (x2, y2) = ((max[0] + pad).ceil() as i32, (max[1] + pad).ceil() as i32);
+
+        DesktopRect::new(x1, y1, (x2 - x1) as u32, (y2 - y1) as u32)
+    }
+
+    /// 点 `p` 所在像素被覆盖的比例
+    fn coverage(&self, p: [f32; 2]) -> f32 {
+        let half = self.style.stroke_width / 2.0;
+        let (min, max) = self.corners();
+        let outline = |distance: f32| {
+            let stroke = edge_coverage(distance.abs() - half);
+            if self.style.fill { stroke.max(edge_coverage(distance)) } else { stroke }
+        };
+
+        match self.shape {
+            Shape::Rect => outline(rect_distance(p, min, max)),
+            Shape::Ellipse => outline(ellipse_distance(p, min, max)),
+            Shape::Line => edge_coverage(segment_distance(p, self.from, self.to) - half),
+            Shape::Arrow => {
+                let (base, triangle) = self.arrow_head();
+                edge_coverage(segment_distance(p, self.from, base) - half).max(edge_coverage(triangle_distance(p, triangle)))
+            }
+        }
+    }
+}
+
+impl StepMarker {
+    /// 编号为 `number` 时的图形: 实心圆 + 七段数码管样式的数字 (不依赖字体)
+    pub fn annotations(&self, number: usize) -> Vec<Annotation> {
+        let [x, y] = self.center;
+        let r = self.radius;
+        let circle = Annotation {
+            shape: Shape::Ellipse,
+            from: [x - r, y - r],
+            to: [x + r, y + r],
+            style: AnnotationStyle { color: self.color, stroke_width: 1.0, fill: true },
+        };
+
+        let [cr, cg, cb, _] = contrast_background(self.color);
+        let style = AnnotationStyle { color: [cr, cg, cb, 0xff], stroke_width: (r * 0.16).max(1.0), fill: false };
+        let digits: Vec<usize> = number.to_string().bytes().map(|digit| (digit - b'0') as usize).collect();
+        // 数字框宽 0.4r 高 0.8r, 多位数时间隔 0.3r
+        let (w, gap) = (r * 0.4, r * 0.3);
+        let total = w * digits.len() as f32 + gap * (digits.len() - 1) as f32;
+        let (left, top) = (x - total / 2.0, y - w);
+
+        let segments = digits.into_iter().enumerate().flat_map(|(i, digit)| {
+            let x0 = left + i as f32 * (w + gap);
+            SEGMENTS
+                .into_iter()
+                .enumerate()
+                .filter(move |(segment, _)| DIGIT_SEGMENTS[digit] & (1 << segment) != 0)
+                .map(move |(_, (from, to))| Annotation {
+                    shape: Shape::Line,
+                    from: [x0 + from[0] * w, top + from[1] * w],
+                    to: [x0 + to[0] * w, top + to[1] * w],
+                    style,
+                })
+        });
+
+        [circle].into_iter().chain(segments).collect()
+    }
+}
+
+/// 各点向外扩展 `pad` 后覆盖的像素范围 (桌面物理坐标), 没有点时为 `None`
+fn points_bounds(points: &[[f32; 2]], pad: f32) -> Option<DesktopRect> {
+    if points.is_empty() {
+        return None;
+    }
+    let (x1, y1) = points.iter().fold((f32::MAX, f32::MAX), |(x, y), p| (x.min(p[0]), y.min(p[1])));
+    let (x2, y2) = points.iter().fold((f32::MIN, f32::MIN), |(x, y), p| (x.max(p[0]), y.max(p[1])));
+    let (x1, y1) = ((x1 - pad).floor() as i32, (y1 - pad).floor() as i32);
+    let (x2, y2) = ((x2 + pad).ceil() as i32, (y2 + pad).ceil() as i32);
+
+    Some(DesktopRect::new(x1, y1, (x2 - x1) as u32, (y2 - y1) as u32))
+}
+
+impl Highlight {
+    /// 笔迹覆盖的像素范围 (桌面物理坐标), 没有点时为 `None`
+    pub fn bounds(&self) -> Option<DesktopRect> {
+        points_bounds(&self.points, self.width / 2.0 + 1.0)
+    }
+
+    /// 透明背景上的笔迹图像: (覆盖的范围, rgba 阵列), 没有点时为 `None`
+    pub fn render(&self) -> Option<(DesktopRect, Vec<u8>)> {
+        let bounds = self.bounds()?;
+        let mut rgba = vec![0; rgba_len(bounds.width, bounds.height)?];
+        draw_highlight(&mut rgba, bounds, self);
+        Some((bounds, rgba))
+    }
+}
+
+/// 将荧光笔的笔迹绘制到 rgba 阵列 (行优先, 对应桌面上的 `rect` 区域) 中
+///
+/// 先求各线段覆盖率的最大值, 再一次性混合, 因此笔迹自身重叠处不会加深
+fn draw_highlight(rgba: &mut [u8], rect: DesktopRect, highlight: &Highlight) {
+    let half = highlight.width / 2.0;
+    let Some(bounds) = highlight.bounds().and_then(|bounds| bounds.intersect(&rect)) else { return };
+    let mut coverage = vec![0f32; (bounds.width * bounds.height) as usize];
+
+    // 单个点时为圆点
+    let first = highlight.points[0];
+    let segments = highlight.points.windows(2).map(|pair| (pair[0], pair[1])).chain((highlight.points.len() == 1).then_some((first, first)));
+    for (a, b) in segments {
+        let Some(part) = points_bounds(&[a, b], half + 1.0).and_then(|part| part.intersect(&bounds)) else { continue };
+
+        for y in part.y..part.y + part.height as i32 {
+            for x in part.x..part.x + part.width as i32 {
+                let value = edge_coverage(segment_distance([x as f32 + 0.5, y as f32 + 0.5], a, b) - half);
+                let i = ((y - bounds.y) as u32 * bounds.width + (x - bounds.x) as u32) as usize;
+                coverage[i] = coverage[i].max(value);
+            }
+        }
+    }
+
+    for (n, value) in coverage.into_iter().enumerate() {
+        if value > 0.0 {
+            let (x, y) = (bounds.x + (n as u32 % bounds.width) as i32, bounds.y + (n as u32 / bounds.width) as i32);
+            let i = (((y - rect.y) as u32 * rect.width + (x - rect.x) as u32) * 4) as usize;
+            blend_pixel(&mut rgba[i..i + 4], highlight.color, value);
+        }
+    }
+}
+
+/// `bounds` 中除去各 `holes` 之外的部分, 拆分为互不重叠的矩形 (各 `holes` 可重叠)
+///
+/// 按 `holes` 的上下边界分为若干水平条带, 每个条带中取未被覆盖的区间
+pub fn spotlight_mask(bounds: DesktopRect, holes: &[DesktopRect]) -> Vec<DesktopRect> {
+    let holes: Vec<DesktopRect> = holes.iter().filter_map(|hole| hole.intersect(&bounds)).collect();
+    let (bx2, by2) = (bounds.x + bounds.width as i32, bounds.y + bounds.height as i32);
+
+    let mut edges: Vec<i32> = holes
+        .iter()
+        .flat_map(|hole| [hole.y, hole.y + hole.height as i32])
+        .chain([bounds.y, by2])
+        .collect();
+    edges.sort_unstable();
+    edges.dedup();
+
+    let mut rects = vec![];
+    for band in edges.windows(2) {
+        let (y1, y2) = (band[0], band[1]);
+        let mut covered: Vec<(i32, i32)> = holes
+            .iter()
+            .filter(|hole| hole.y <= y1 && hole.y + hole.height as i32 >= y2)
+            .map(|hole| (hole.x, hole.x + hole.width as i32))
+            .collect();
+        covered.sort_unstable();
+
+        let mut x = bounds.x;
+        for (x1, x2) in covered.into_iter().chain([(bx2, bx2)]) {
+            if x1 > x {
+                rects.push(DesktopRect::new(x, y1, (x1 - x) as u32, (y2 - y1) as u32));
+            }
+            x = x.max(x2);
+        }
+    }
+
+    rects
+}
+
+/// 将标注绘制到 rgba 阵列 (行优先, 对应桌面上的 `rect` 区域) 中, 按覆盖率混合到原有像素上
+pub fn draw_annotations(rgba: &mut [u8], rect: DesktopRect, annotations: &[Annotation]) {
+    for annotation in annotations {
+        let Some(bounds) = annotation.bounds().intersect(&rect) else { continue };
+        for y in bounds.y..bounds.y + bounds.height as i32 {
+            for x in bounds.x..bounds.x + bounds.width as i32 {
+                let coverage = annotation.coverage([x as f32 + 0.5, y as f32 + 0.5]);
+                if coverage > 0.0 {
+                    let i = (((y - rect.y) as u32 * rect.width + (x - rect.x) as u32) * 4) as usize;
+                    blend_pixel(&mut rgba[i..i + 4], annotation.style.color, coverage);
+                }
+            }
+        }
+    }
+}
+
+/// 选区 `rect` 中变暗的部分 (没有聚光灯时为空)
+pub fn dimmed_rects(rect: DesktopRect, marks: &[Mark]) -> Vec<DesktopRect> {
+    let spotlights: Vec<DesktopRect> = marks
+        .iter()
+        .filter_map(|mark| match mark {
+            Mark::Spotlight(spotlight) => Some(*spotlight),
+            _ => None,
+        })
+        .collect();
+
+    match spotlights.is_empty() {
+        true => vec![],
+        false => spotlight_mask(rect, &spotlights),
+    }
+}
+
+/// 将标注绘制到 rgba 阵列 (行优先, 对应桌面上的 `rect` 区域) 中: 先打码, 再使聚光灯之外变暗, 最后按顺序绘制其他标注
+///
+/// 编号标记按顺序编号, 没有字体时不绘制文字; 选择窗口中的预览与此保持相同的顺序
+pub fn draw_marks(rgba: &mut [u8], rect: DesktopRect, marks: &[Mark], font: Option<&FontArc>) {
+    apply_redactions(rgba, rect, &redactions(marks));
+
+    for dimmed in dimmed_rects(rect, marks) {
+        for y in dimmed.y..dimmed.y + dimmed.height as i32 {
+            for x in dimmed.x..dimmed.x + dimmed.width as i32 {
+                let i = (((y - rect.y) as u32 * rect.width + (x - rect.x) as u32) * 4) as usize;
+                blend_pixel(&mut rgba[i..i + 4], DIM_COLOR, 1.0);
+            }
+        }
+    }
+
+    let mut number = 0;
+    for mark in marks {
+        match (mark, font) {
+            (Mark::Shape(annotation), _) => draw_annotations(rgba, rect, std::slice::from_ref(annotation)),
+            (Mark::Text(label), Some(font)) => draw_text_label(rgba, rect, label, font),
+            (Mark::Marker(marker), _) => {
+                number += 1;
+                draw_annotations(rgba, rect, &marker.annotations(number));
+            }
+            (Mark::Highlight(highlight), _) => draw_highlight(rgba, rect, highlight),
+            (Mark::Text(_), None) | (Mark::Redact(_) | Mark::Spotlight(_), _) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use crate::geometry::DesktopRect;
+    use crate::utils::annotate::{draw_annotations, draw_marks, spotlight_mask, Annotation, AnnotationStyle, Highlight, Mark, Shape, StepMarker};
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const WHITE: [u8; 4] = [255, 255, 255, 255];
+
+    fn annotation(shape: Shape, from: [f32; 2], to: [f32; 2], stroke_width: f32, fill: bool) -> Annotation {
+        Annotation { shape, from, to, style: AnnotationStyle { color: RED, stroke_width, fill } }
+    }
+
+    /// 在白色的 `rect` 区域上绘制, 返回读取像素的函数
+    fn render(rect: DesktopRect, annotations: &[Annotation]) -> impl Fn(i32, i32) -> [u8; 4] {
+        let mut rgba = WHITE.repeat((rect.width * rect.height) as usize);
+        draw_annotations(&mut rgba, rect, annotations);
+
+        move |x, y| {
+            let i = (((y - rect.y) as u32 * rect.width + (x - rect.x) as u32) * 4) as usize;
+            rgba[i..i + 4].try_into().unwrap()
+        }
+    }
+
+    #[test]
+    fn rasterize_rect_and_ellipse() {
+        // 选区位于桌面 (100, 50)
+        let rect = DesktopRect::new(100, 50, 40, 40);
+        let pixel = render(rect, &[annotation(Shape::Rect, [105.0, 55.0], [125.0, 75.0], 2.0, false)]);
+
+        // 边框覆盖 (105, 55) 两侧各 1 像素, 内部及外部不变
+        assert_eq!((pixel(104, 60), pixel(105, 60), pixel(115, 55)), (RED, RED, RED));
+        assert_eq!((pixel(115, 65), pixel(102, 60), pixel(127, 60)), (WHITE, WHITE, WHITE));
+
+        let pixel = render(rect, &[annotation(Shape::Rect, [125.0, 75.0], [105.0, 55.0], 2.0, true)]);
+        assert_eq!((pixel(115, 65), pixel(127, 60)), (RED, WHITE));
+
+        let pixel = render(rect, &[annotation(Shape::Ellipse, [100.0, 50.0], [140.0, 70.0], 4.0, false)]);
+        assert_eq!((pixel(120, 50), pixel(100, 60), pixel(120, 60)), (RED, RED, WHITE));
+        // 外接矩形的角不在椭圆上
+        assert_eq!(pixel(101, 51), WHITE);
+    }
+
+    #[test]
+    fn rasterize_line_and_arrow() {
+        let rect = DesktopRect::new(0, 0, 40, 20);
+        let pixel = render(rect, &[annotation(Shape::Line, [0.0, 10.0], [40.0, 10.0], 4.0, false)]);
+
+        assert_eq!((pixel(0, 8), pixel(39, 11), pixel(20, 7), pixel(20, 12)), (RED, RED, WHITE, WHITE));
+
+        // 超出区域的部分被裁剪
+        let arrow = annotation(Shape::Arrow, [5.0, 10.0], [35.0, 10.0], 2.0, false);
+        let pixel = render(rect, &[arrow, annotation(Shape::Line, [-10.0, -10.0], [50.0, 30.0], 1.0, false)]);
+
+        // 头部为 10 像素长, 底边宽 10 像素的三角形
+        assert_eq!(arrow.arrow_head(), ([25.0, 10.0], [[25.0, 15.0], [35.0, 10.0], [25.0, 5.0]]));
+        assert_eq!((pixel(10, 10), pixel(26, 13), pixel(31, 10), pixel(10, 13)), (RED, RED, RED, WHITE));
+        assert_eq!(pixel(36, 10), WHITE);
+    }
+
+    #[test]
+    fn blend_translucent_color() {
+        let mut rgba = [0u8, 0, 255, 255].repeat(4);
+        let mut half_red = annotation(Shape::Rect, [0.0, 0.0], [2.0, 2.0], 1.0, true);
+        half_red.style.color = [255, 0, 0, 128];
+        draw_annotations(&mut rgba, DesktopRect::new(0, 0, 2, 2), &[half_red]);
+
+        assert_eq!(&rgba[..4], &[128, 0, 127, 255]);
+    }
+
+    #[test]
+    fn number_markers_in_order() {
+        let marker = StepMarker { center: [20.0, 20.0], radius: 10.0, color: RED };
+        // 圆 + 数字的各段: "1" 为 2 段, "10" 为 2 + 6 段
+        assert_eq!(marker.annotations(1).len(), 3);
+        assert_eq!(marker.annotations(10).len(), 9);
+
+        // 第二个标记的编号为 2: 数字框 (36, 16) ~ (40, 24) 的顶部有 a 段, "1" 没有; 深色的圆上编号为白色
+        let navy = [0, 0, 128, 255];
+        let rect = DesktopRect::new(0, 0, 60, 40);
+        let first = StepMarker { color: navy, ..marker };
+        let second = StepMarker { center: [38.0, 20.0], ..first };
+        let mut rgba = WHITE.repeat((rect.width * rect.height) as usize);
+        draw_marks(&mut rgba, rect, &[Mark::Marker(first), Mark::Marker(second)], None);
+
+        let pixel = |x: u32, y: u32| &rgba[((y * rect.width + x) * 4) as usize..((y * rect.width + x) * 4 + 4) as usize];
+        assert!(pixel(38, 16)[0] > 128, "{:?}", pixel(38, 16));
+        assert_eq!((pixel(20, 16), pixel(38, 12)), (&navy[..], &navy[..]));
+    }
+
+    #[test]
+    fn highlight_does_not_darken_itself() {
+        // 折返的笔迹: 重叠处与只经过一次的地方颜色相同
+        let rect = DesktopRect::new(0, 0, 40, 20);
+        let highlight = Highlight { points: vec![[5.0, 10.0], [35.0, 10.0], [20.0, 10.5]], width: 6.0, color: [255, 0, 0, 0x80] };
+        let mut rgba = WHITE.repeat((rect.width * rect.height) as usize);
+        draw_marks(&mut rgba, rect, &[Mark::Highlight(highlight.clone())], None);
+
+        let pixel = |x: u32, y: u32| &rgba[((y * rect.width + x) * 4) as usize..((y * rect.width + x) * 4 + 4) as usize];
+        assert_eq!(pixel(10, 10), &[255, 127, 127, 255]);
+        assert_eq!(pixel(25, 10), pixel(10, 10));
+        assert_eq!(pixel(10, 15), &WHITE[..]);
+
+        // 预览用的图像: 透明背景上同样不加深
+        let (bounds, rgba) = highlight.render().unwrap();
+        let pixel = |x: i32, y: i32| &rgba[(((y - bounds.y) as u32 * bounds.width + (x - bounds.x) as u32) * 4) as usize..][..4];
+        assert_eq!(bounds.xywh(), (1, 6, 38, 9));
+        assert_eq!((pixel(10, 10), pixel(25, 10)), (&[255, 0, 0, 0x80][..], &[255, 0, 0, 0x80][..]));
+    }
+
+    #[test]
+    fn dim_outside_spotlights() {
+        let bounds = DesktopRect::new(0, 0, 10, 10);
+        let holes = [DesktopRect::new(2, 2, 4, 4), DesktopRect::new(4, 4, 4, 4), DesktopRect::new(8, 8, 5, 5)];
+        let rects = spotlight_mask(bounds, &holes);
+
+        // 变暗的部分互不重叠, 且不覆盖任何聚光灯区域
+        let covered = |x: i32, y: i32| rects.iter().filter(|rect| rect.contains(crate::geometry::DesktopPoint::new(x, y))).count();
+        let in_hole = |x: i32, y: i32| holes.iter().any(|hole| hole.contains(crate::geometry::DesktopPoint::new(x, y)));
+        for y in 0..10 {
+            for x in 0..10 {
+                assert_eq!(covered(x, y), if in_hole(x, y) { 0 } else { 1 }, "({x}, {y})");
+            }
+        }
+
+        // 在聚光灯之外叠加蒙层
+        let mut rgba = WHITE.repeat(100);
+        draw_marks(&mut rgba, bounds, &[Mark::Spotlight(holes[0])], None);
+        assert_eq!((&rgba[..4], &rgba[(3 * 10 + 3) * 4..(3 * 10 + 3) * 4 + 4]), (&[153, 153, 153, 255][..], &WHITE[..]));
+        assert_eq!(spotlight_mask(bounds, &[]), vec![bounds]);
+    }
+}
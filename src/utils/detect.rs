@@ -0,0 +1,215 @@
+use std::collections::BTreeMap;
+use crate::declares::CaptureInfo;
+use crate::geometry::DesktopRect;
+
+/// 相邻像素的亮度差不小于该值时视为边缘
+const EDGE_THRESHOLD: u8 = 24;
+
+/// 边缘线段的最短长度 (像素), 过滤文字等细碎的边缘
+const MIN_EDGE_LEN: i32 = 8;
+
+/// 矩形的四条边被边缘线段覆盖的最低比例
+const MIN_COVERAGE: f32 = 0.8;
+
+/// 从截图中检测到的边缘 (桌面物理坐标)
+///
+/// - 竖直边缘: x 为像素 x - 1 与 x 之间的分界, 线段为 \[start, end) 的 y 范围
+/// - 水平边缘: y 为像素 y - 1 与 y 之间的分界, 线段为 \[start, end) 的 x 范围
+///
+/// 屏幕的边界也视为边缘
+#[derive(Debug, Clone, Default)]
+pub struct EdgeMap {
+    vertical: BTreeMap<i32, Vec<(i32, i32)>>,
+    horizontal: BTreeMap<i32, Vec<(i32, i32)>>,
+}
+
+/// 像素的亮度 (0 ~ 255)
+fn luma(pixel: &[u8]) -> u8 {
+    ((pixel[0] as u32 * 299 + pixel[1] as u32 * 587 + pixel[2] as u32 * 114) / 1000) as u8
+}
+
+/// 记录连续的边缘像素: 边缘在 `pos` 处中断且足够长时返回其起点
+fn track_run(run: &mut Option<usize>, strong: bool, pos: usize) -> Option<usize> {
+    match (strong, *run) {
+        (true, None) => {
+            *run = Some(pos);
+            None
+        }
+        (false, Some(start)) => {
+            *run = None;
+            (pos - start >= MIN_EDGE_LEN as usize).then_some(start)
+        }
+        _ => None,
+    }
+}
+
+/// 线段在 \[start, end) 范围内的覆盖比例
+fn coverage(segments: &[(i32, i32)], start: i32, end: i32) -> f32 {
+    let covered: i32 = segments
+        .iter()
+        .map(|&(s, e)| (e.min(end) - s.max(start)).max(0))
+        .sum();
+
+    (covered as f32 / (end - start).max(1) as f32).min(1.0)
+}
+
+/// 在 `lines` 中查找距离 `pos` 不超过 `max_distance` 且线段覆盖 `across` 的最近的边缘
+fn nearest(lines: &BTreeMap<i32, Vec<(i32, i32)>>, pos: i32, across: i32, max_distance: i32) -> Option<i32> {
+    lines
+        .range(pos - max_distance..=pos + max_distance)
+        .filter(|(_, segments)| segments.iter().any(|&(start, end)| (start..end).contains(&across)))
+        .map(|(&line, _)| line)
+        .min_by_key(|line| (line - pos).abs())
+}
+
+impl EdgeMap {
+    /// 检测各屏幕截图中的边缘
+    pub fn detect(captures: &[CaptureInfo]) -> EdgeMap {
+        let mut edges = EdgeMap::default();
+
+        for capture in captures {
+            edges.detect_screen(capture);
+            edges.add_boundary(capture.physical_rect());
+        }
+
+        edges
+    }
+
+    /// 逐行扫描, 记录亮度突变的像素连续成段的位置
+    fn detect_screen(&mut self, capture: &CaptureInfo) {
+        let (w, h) = (capture.physical_width as usize, capture.physical_height as usize);
+        let (ox, oy) = (capture.physical_x, capture.physical_y);
+        let luma: Vec<u8> = capture.rgba.chunks_exact(4).map(luma).collect();
+
+        if luma.len() < w * h {
+            return;
+        }
+        let is_edge = |a: usize, b: usize| luma[a].abs_diff(luma[b]) >= EDGE_THRESHOLD;
+
+        // 竖直边缘: 像素 (x - 1, y) 与 (x, y), 记录各列边缘的起始行
+        let mut column_runs: Vec<Option<usize>> = vec![None; w];
+        for y in 0..=h {
+            for (x, run) in column_runs.iter_mut().enumerate().skip(1) {
+                let strong = y < h && is_edge(y * w + x - 1, y * w + x);
+                if let Some(start) = track_run(run, strong, y) {
+                    self.vertical.entry(ox + x as i32).or_default().push((oy + start as i32, oy + y as i32));
+                }
+            }
+        }
+
+        // 水平边缘: 像素 (x, y - 1) 与 (x, y)
+        for y in 1..h {
+            let mut run = None;
+            for x in 0..=w {
+                let strong = x < w && is_edge((y - 1) * w + x, y * w + x);
+                if let Some(start) = track_run(&mut run, strong, x) {
+                    self.horizontal.entry(oy + y as i32).or_default().push((ox + start as i32, ox + x as i32));
+                }
+            }
+        }
+    }
+
+    /// 屏幕的四条边界
+    fn add_boundary(&mut self, screen: DesktopRect) {
+        let (x, y, w, h) = screen.xywh();
+        let (right, bottom) = (x + w as i32, y + h as i32);
+
+        for line in [x, right] {
+            self.vertical.entry(line).or_default().push((y, bottom));
+        }
+        for line in [y, bottom] {
+            self.horizontal.entry(line).or_default().push((x, right));
+        }
+    }
+
+    /// 距离 x 不超过 `max_distance` 且经过 y 的最近的竖直边缘
+    pub fn nearest_vertical(&self, x: i32, y: i32, max_distance: i32) -> Option<i32> {
+        nearest(&self.vertical, x, y, max_distance)
+    }
+
+    /// 距离 y 不超过 `max_distance` 且经过 x 的最近的水平边缘
+    pub fn nearest_horizontal(&self, y: i32, x: i32, max_distance: i32) -> Option<i32> {
+        nearest(&self.horizontal, y, x, max_distance)
+    }
+
+    /// 包含点 (x, y) 的最小矩形区域: 向四个方向查找最近的边缘, 且四条边大部分被边缘覆盖
+    pub fn rect_at(&self, x: i32, y: i32) -> Option<DesktopRect> {
+        let passes = |segments: &Vec<(i32, i32)>, across: i32| segments.iter().any(|&(start, end)| (start..end).contains(&across));
+
+        let left = self.vertical.range(..=x).rev().find(|(_, segments)| passes(segments, y))?;
+        let right = self.vertical.range(x + 1..).find(|(_, segments)| passes(segments, y))?;
+        let top = self.horizontal.range(..=y).rev().find(|(_, segments)| passes(segments, x))?;
+        let bottom = self.horizontal.range(y + 1..).find(|(_, segments)| passes(segments, x))?;
+
+        let (x1, x2, y1, y2) = (*left.0, *right.0, *top.0, *bottom.0);
+        let supported = coverage(left.1, y1, y2) >= MIN_COVERAGE
+            && coverage(right.1, y1, y2) >= MIN_COVERAGE
+            && coverage(top.1, x1, x2) >= MIN_COVERAGE
+            && coverage(bottom.1, x1, x2) >= MIN_COVERAGE;
+
+        supported.then(|| DesktopRect::new(x1, y1, (x2 - x1) as u32, (y2 - y1) as u32))
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use crate::declares::CaptureInfo;
+    use crate::geometry::DesktopRect;
+    use crate::utils::detect::EdgeMap;
+
+    /// 100x80 灰色背景的屏幕上: 白色对话框 (20, 10, 50, 50) 中有一个蓝色按钮 (30, 40, 20, 12), 及一行细碎的 "文字"
+    fn fixture(physical_x: i32) -> CaptureInfo {
+        let (w, h) = (100u32, 80u32);
+        let mut rgba = [200u8, 200, 200, 255].repeat((w * h) as usize);
+        let mut fill = |x: u32, y: u32, rw: u32, rh: u32, color: [u8; 4]| {
+            for row in y..y + rh {
+                for col in x..x + rw {
+                    let i = ((row * w + col) * 4) as usize;
+                    rgba[i..i + 4].copy_from_slice(&color);
+                }
+            }
+        };
+        fill(20, 10, 50, 50, [255, 255, 255, 255]);
+        fill(30, 40, 20, 12, [0, 0, 255, 255]);
+        for col in (24..64).step_by(4) {
+            fill(col, 20, 2, 5, [0, 0, 0, 255]);
+        }
+
+        CaptureInfo {
+            screen_id: 1,
+            scale_factor: 1.0,
+            physical_x,
+            physical_y: 0,
+            physical_width: w,
+            physical_height: h,
+            buffer: None,
+            stride: None,
+            rgba,
+        }
+    }
+
+    #[test]
+    fn detect_nested_rects() {
+        let edges = EdgeMap::detect(&[fixture(0)]);
+
+        // 对话框内 (文字的边缘过短, 被忽略)
+        assert_eq!(edges.rect_at(25, 30), Some(DesktopRect::new(20, 10, 50, 50)));
+        assert_eq!(edges.rect_at(25, 22), Some(DesktopRect::new(20, 10, 50, 50)));
+        // 按钮内
+        assert_eq!(edges.rect_at(35, 45), Some(DesktopRect::new(30, 40, 20, 12)));
+        // 背景: 屏幕边界
+        assert_eq!(edges.rect_at(5, 5), Some(DesktopRect::new(0, 0, 100, 80)));
+    }
+
+    #[test]
+    fn nearest_edges_in_desktop() {
+        // 屏幕位于 x = -100
+        let edges = EdgeMap::detect(&[fixture(-100)]);
+
+        assert_eq!(edges.nearest_vertical(-77, 30, 4), Some(-80));
+        assert_eq!(edges.nearest_vertical(-77, 70, 4), None);
+        assert_eq!(edges.nearest_horizontal(57, -60, 4), Some(60));
+        // 屏幕边界
+        assert_eq!(edges.nearest_vertical(-3, 70, 4), Some(0));
+    }
+}
@@ -1,5 +1,341 @@
+use std::borrow::Cow;
 use std::cmp::{max, min};
+use std::io::Cursor;
+use image::ColorType;
+use image::codecs::bmp::BmpEncoder;
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::tiff::TiffEncoder;
+use image_webp::WebPEncoder;
+use crate::declares::{CaptureInfo, ImageFormat, OutputFormat};
+use crate::error::CaptureError;
 
-pub fn clamp(suppose: i32, low: i32, high: i32) -> i32 {
-    min(high, max(low, suppose))
-}
\ No newline at end of file
+pub mod annotate;
+pub mod detect;
+pub mod redact;
+pub mod text;
+
+/// 两个 xywh 矩形的交集 (无交集时为 `None`)
+pub fn intersect(a: (i32, i32, i32, i32), b: (i32, i32, i32, i32)) -> Option<(i32, i32, i32, i32)> {
+    let x1 = max(a.0, b.0);
+    let y1 = max(a.1, b.1);
+    let x2 = min(a.0 + a.2, b.0 + b.2);
+    let y2 = min(a.1 + a.3, b.1 + b.3);
+
+    if x1 < x2 && y1 < y2 {
+        Some((x1, y1, x2 - x1, y2 - y1))
+    } else {
+        None
+    }
+}
+
+/// `width` x `height` 的 rgba 阵列的字节数, 溢出时为 `None`
+pub fn rgba_len(width: u32, height: u32) -> Option<usize> {
+    (width as usize).checked_mul(height as usize)?.checked_mul(4)
+}
+
+/// 从 rgba 阵列 (行优先, 宽 `src_w` 高 `src_h`) 中裁剪 xywh 区域,
+/// 超出原图的部分填充为透明
+pub fn crop_rgba(rgba: &[u8], src_w: u32, src_h: u32, xywh: (i32, i32, u32, u32)) -> Vec<u8> {
+    let (x, y, w, h) = xywh;
+    let mut cropped = vec![0u8; rgba_len(w, h).expect("crop region is too large")];
+
+    blit_rgba(&mut cropped, w, h, rgba, src_w, src_h, (-x, -y));
+
+    cropped
+}
+
+/// 将 rgba 阵列 `src` 拷贝到 `dst` 的 (x, y) 处, 超出 `dst` 的部分忽略
+pub fn blit_rgba(dst: &mut [u8], dst_w: u32, dst_h: u32, src: &[u8], src_w: u32, src_h: u32, xy: (i32, i32)) {
+    let (x, y) = xy;
+
+    if let Some((ix, iy, iw, ih)) = intersect((0, 0, dst_w as i32, dst_h as i32), (x, y, src_w as i32, src_h as i32)) {
+        for row in iy..(iy + ih) {
+            let dst_start = ((row as u32 * dst_w + ix as u32) * 4) as usize;
+            let src_start = (((row - y) as u32 * src_w + (ix - x) as u32) * 4) as usize;
+            let len = (iw * 4) as usize;
+            dst[dst_start..dst_start + len].copy_from_slice(&src[src_start..src_start + len]);
+        }
+    }
+}
+
+/// 将颜色 `src` (rgba 未预乘不透明度) 按覆盖率 `coverage` 混合到像素 `dst` 上
+pub fn blend_pixel(dst: &mut [u8], src: [u8; 4], coverage: f32) {
+    let alpha = src[3] as f32 / 255.0 * coverage;
+    let dst_alpha = dst[3] as f32 / 255.0 * (1.0 - alpha);
+    let out_alpha = alpha + dst_alpha;
+    if out_alpha <= 0.0 {
+        return;
+    }
+
+    for (channel, src) in dst[..3].iter_mut().zip(src) {
+        *channel = ((src as f32 * alpha + *channel as f32 * dst_alpha) / out_alpha).round() as u8;
+    }
+    dst[3] = (out_alpha * 255.0).round() as u8;
+}
+
+/// 从各屏幕的截图中裁剪出桌面上的 xywh 区域 (物理坐标), 不被任何屏幕覆盖的部分为透明
+///
+/// 区域的尺寸由调用方约束在桌面范围内
+pub fn crop_captures(captures: &[CaptureInfo], xywh: (i32, i32, u32, u32)) -> Vec<u8> {
+    let (x, y, w, h) = xywh;
+    let mut rgba = vec![0u8; rgba_len(w, h).expect("crop region is too large")];
+
+    for capture in captures {
+        blit_rgba(
+            &mut rgba, w, h,
+            &capture.rgba, capture.physical_width, capture.physical_height,
+            (capture.physical_x - x, capture.physical_y - y),
+        );
+    }
+
+    rgba
+}
+
+/// 桌面上 (x, y) 处像素的 rgba (物理坐标), 不被任何屏幕覆盖时为 `None`
+pub fn pixel_at(captures: &[CaptureInfo], x: i32, y: i32) -> Option<[u8; 4]> {
+    captures.iter().find_map(|capture| {
+        let (cx, cy) = (x - capture.physical_x, y - capture.physical_y);
+
+        if cx < 0 || cy < 0 || cx >= capture.physical_width as i32 || cy >= capture.physical_height as i32 {
+            return None;
+        }
+        let start = ((cy as u32 * capture.physical_width + cx as u32) * 4) as usize;
+        capture.rgba.get(start..start + 4).map(|pixel| [pixel[0], pixel[1], pixel[2], pixel[3]])
+    })
+}
+
+/// 取色时采样区域边长的上限
+pub const MAX_SAMPLE_SIZE: u32 = 64;
+
+/// 以 (x, y) 为中心的 `size` x `size` 区域的平均色 (物理坐标), 只统计被屏幕覆盖的像素, 均不被覆盖时为 `None`
+pub fn average_color(captures: &[CaptureInfo], x: i32, y: i32, size: u32) -> Option<[u8; 3]> {
+    let start = -(size as i32 / 2);
+    let (mut sum, mut count) = ([0u32; 3], 0u32);
+
+    for dy in start..start + size as i32 {
+        for dx in start..start + size as i32 {
+            if let Some([r, g, b, _]) = pixel_at(captures, x + dx, y + dy) {
+                sum = [sum[0] + r as u32, sum[1] + g as u32, sum[2] + b as u32];
+                count += 1;
+            }
+        }
+    }
+
+    (count > 0).then(|| sum.map(|channel| ((channel + count / 2) / count) as u8))
+}
+
+/// rgb 转 hsl: \[h (0 ~ 360), s (0 ~ 100), l (0 ~ 100)\]
+pub fn rgb_to_hsl(rgb: [u8; 3]) -> [f64; 3] {
+    let [r, g, b] = rgb.map(|channel| channel as f64 / 255.0);
+    let (max, min) = (r.max(g).max(b), r.min(g).min(b));
+    let (delta, l) = (max - min, (max + min) / 2.0);
+
+    if delta == 0.0 {
+        return [0.0, 0.0, l * 100.0];
+    }
+
+    let s = delta / (1.0 - (2.0 * l - 1.0).abs());
+    let h = if max == r {
+        ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        (b - r) / delta + 2.0
+    } else {
+        (r - g) / delta + 4.0
+    };
+
+    [h * 60.0, s * 100.0, l * 100.0]
+}
+
+/// 将 rgba 阵列顺时针旋转 `rotation` 度 (仅支持 90 的整数倍), 返回旋转后的阵列及宽高
+pub fn rotate_rgba(rgba: &[u8], width: u32, height: u32, rotation: f64) -> (Vec<u8>, u32, u32) {
+    let quarter_turns = ((rotation / 90.0).round() as i64).rem_euclid(4);
+    let (w, h) = (width as usize, height as usize);
+
+    if quarter_turns == 0 {
+        return (rgba.to_vec(), width, height);
+    }
+
+    let (out_w, out_h) = if quarter_turns == 2 { (w, h) } else { (h, w) };
+    let mut rotated = vec![0u8; out_w * out_h * 4];
+    for y in 0..out_h {
+        for x in 0..out_w {
+            // 旋转后 (x, y) 处的像素在原图中的坐标
+            let (sx, sy) = match quarter_turns {
+                1 => (y, h - 1 - x),
+                2 => (w - 1 - x, h - 1 - y),
+                _ => (w - 1 - y, x),
+            };
+            let src = (sy * w + sx) * 4;
+            let dst = (y * out_w + x) * 4;
+            rotated[dst..dst + 4].copy_from_slice(&rgba[src..src + 4]);
+        }
+    }
+
+    (rotated, out_w as u32, out_h as u32)
+}
+
+/// 丢弃 rgba 阵列的 alpha 通道
+pub fn rgba_to_rgb(rgba: &[u8]) -> Vec<u8> {
+    rgba.chunks_exact(4).flat_map(|pixel| &pixel[..3]).copied().collect()
+}
+
+/// 按指定的行字节数重新排列像素阵列, 行尾补 0
+fn pad_rows(pixels: &[u8], row_len: u32, stride: u32, height: u32) -> Vec<u8> {
+    let (row_len, stride) = (row_len as usize, stride as usize);
+    let mut padded = vec![0u8; stride * height as usize];
+
+    for (src, dst) in pixels.chunks_exact(row_len).zip(padded.chunks_exact_mut(stride)) {
+        dst[..row_len].copy_from_slice(src);
+    }
+
+    padded
+}
+
+/// 将 rgba 阵列 (行优先) 编码为指定格式
+pub fn encode_image(rgba: &[u8], width: u32, height: u32, output: OutputFormat) -> Result<Vec<u8>, CaptureError> {
+    if rgba_len(width, height) != Some(rgba.len()) {
+        return Err(CaptureError::Encode(String::from("rgba buffer does not match the image size")));
+    }
+
+    let row_len = width * output.channels();
+    let stride = output.stride(width);
+    if stride < row_len {
+        return Err(CaptureError::InvalidArgument(format!("stride {stride} is smaller than the row length {row_len}")));
+    }
+
+    let (pixels, color) = if output.has_alpha() {
+        (Cow::Borrowed(rgba), ColorType::Rgba8)
+    } else {
+        (Cow::Owned(rgba_to_rgb(rgba)), ColorType::Rgb8)
+    };
+
+    let mut bytes = vec![];
+    match output.format {
+        ImageFormat::Png => PngEncoder::new(&mut bytes)
+            .encode(&pixels, width, height, color)
+            .map_err(|err| err.to_string()),
+        ImageFormat::Jpeg => JpegEncoder::new_with_quality(&mut bytes, output.quality)
+            .encode(&pixels, width, height, color)
+            .map_err(|err| err.to_string()),
+        ImageFormat::Bmp => BmpEncoder::new(&mut bytes)
+            .encode(&pixels, width, height, color)
+            .map_err(|err| err.to_string()),
+        ImageFormat::Tiff => TiffEncoder::new(Cursor::new(&mut bytes))
+            .encode(&pixels, width, height, color)
+            .map_err(|err| err.to_string()),
+        ImageFormat::WebP => {
+            let color = if output.has_alpha() { image_webp::ColorType::Rgba8 } else { image_webp::ColorType::Rgb8 };
+            WebPEncoder::new(&mut bytes)
+                .encode(&pixels, width, height, color)
+                .map_err(|err| err.to_string())
+        }
+        ImageFormat::Qoi => qoi::encode_to_vec(&pixels, width, height)
+            .map(|encoded| bytes = encoded)
+            .map_err(|err| err.to_string()),
+        ImageFormat::RawRgba | ImageFormat::RawRgb => {
+            bytes = pad_rows(&pixels, row_len, stride, height);
+            Ok(())
+        }
+    }.map_err(CaptureError::Encode)?;
+
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod unit_test {
+    use crate::declares::{CaptureInfo, ImageFormat, OutputFormat};
+    use crate::utils::{average_color, encode_image, pixel_at, rgb_to_hsl};
+
+    /// 2x2, 半透明红色
+    const RGBA: [u8; 16] = [255, 0, 0, 128, 255, 0, 0, 128, 255, 0, 0, 128, 255, 0, 0, 128];
+
+    #[test]
+    fn encode_all_formats() {
+        let signatures: [(ImageFormat, &[u8]); 6] = [
+            (ImageFormat::Png, b"\x89PNG"),
+            (ImageFormat::Jpeg, b"\xff\xd8"),
+            (ImageFormat::WebP, b"RIFF"),
+            (ImageFormat::Bmp, b"BM"),
+            (ImageFormat::Qoi, b"qoif"),
+            (ImageFormat::Tiff, b"II*\0"),
+        ];
+
+        for (format, signature) in signatures {
+            let bytes = encode_image(&RGBA, 2, 2, OutputFormat::from(format)).unwrap();
+            assert!(bytes.starts_with(signature), "{format:?}");
+        }
+
+        assert_eq!("gif".parse::<ImageFormat>().unwrap_err().code(), "INVALID_ARGUMENT");
+    }
+
+    #[test]
+    fn raw_with_stride_and_alpha() {
+        let rgba = encode_image(&RGBA, 2, 2, OutputFormat::from(ImageFormat::RawRgba)).unwrap();
+        assert_eq!(rgba, RGBA.to_vec());
+
+        // rgb 总是丢弃 alpha, 每行补齐到 8 字节
+        let rgb = encode_image(&RGBA, 2, 2, OutputFormat { stride: Some(8), ..OutputFormat::from(ImageFormat::RawRgb) }).unwrap();
+        assert_eq!(rgb, vec![255, 0, 0, 255, 0, 0, 0, 0, 255, 0, 0, 255, 0, 0, 0, 0]);
+
+        let too_small = OutputFormat { stride: Some(4), ..OutputFormat::from(ImageFormat::RawRgba) };
+        assert_eq!(encode_image(&RGBA, 2, 2, too_small).unwrap_err().code(), "INVALID_ARGUMENT");
+    }
+
+    #[test]
+    fn drop_alpha_channel() {
+        let output = OutputFormat { drop_alpha: true, ..OutputFormat::from(ImageFormat::RawRgba) };
+        assert_eq!(output.channels(), 3);
+        assert_eq!(encode_image(&RGBA, 2, 2, output).unwrap().len(), 2 * 2 * 3);
+
+        let png = image::load_from_memory(&encode_image(&RGBA, 2, 2, OutputFormat { drop_alpha: true, ..OutputFormat::default() }).unwrap()).unwrap();
+        assert_eq!(png.color(), image::ColorType::Rgb8);
+    }
+
+    #[test]
+    fn pixel_across_screens() {
+        let screen = |physical_x: i32, rgba: Vec<u8>| CaptureInfo {
+            screen_id: 1,
+            scale_factor: 1.0,
+            physical_x,
+            physical_y: 0,
+            physical_width: 2,
+            physical_height: 1,
+            buffer: None,
+            stride: None,
+            rgba,
+        };
+        let captures = [screen(-2, (0..8).collect()), screen(0, (8..16).collect())];
+
+        assert_eq!(pixel_at(&captures, -1, 0), Some([4, 5, 6, 7]));
+        assert_eq!(pixel_at(&captures, 0, 0), Some([8, 9, 10, 11]));
+        assert_eq!(pixel_at(&captures, 2, 0), None);
+        assert_eq!(pixel_at(&captures, 0, 1), None);
+    }
+
+    #[test]
+    fn average_and_hsl() {
+        // 2x2: 黑白交替
+        let capture = CaptureInfo {
+            screen_id: 1,
+            scale_factor: 1.0,
+            physical_x: 0,
+            physical_y: 0,
+            physical_width: 2,
+            physical_height: 2,
+            buffer: None,
+            stride: None,
+            rgba: [[0, 0, 0, 255], [255, 255, 255, 255], [255, 255, 255, 255], [0, 0, 0, 255]].concat(),
+        };
+        let captures = [capture];
+
+        assert_eq!(average_color(&captures, 1, 0, 1), Some([255, 255, 255]));
+        // 3x3 区域中只有 2x2 被屏幕覆盖
+        assert_eq!(average_color(&captures, 0, 0, 3), Some([128, 128, 128]));
+        assert_eq!(average_color(&captures, 5, 5, 3), None);
+
+        assert_eq!(rgb_to_hsl([255, 0, 0]), [0.0, 100.0, 50.0]);
+        assert_eq!(rgb_to_hsl([0, 0, 255]), [240.0, 100.0, 50.0]);
+        assert_eq!(rgb_to_hsl([255, 255, 255]), [0.0, 0.0, 100.0]);
+    }
+}
@@ -0,0 +1,262 @@
+use std::str::FromStr;
+use crate::error::CaptureError;
+use crate::geometry::DesktopRect;
+
+/// 马赛克方块的默认边长 / 模糊的默认半径 (像素)
+pub const DEFAULT_STRENGTH: u32 = 12;
+
+/// 马赛克方块的最小边长 / 模糊的最小半径 (像素), 更小时仍可辨认原内容
+pub const MIN_STRENGTH: u32 = 4;
+
+/// 打码的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RedactKind {
+    /// 马赛克: 每个方块取平均色
+    Pixelate,
+    /// 高斯模糊 (模糊后的像素仍与原像素相关, 文字等敏感信息建议使用马赛克或填充)
+    Blur,
+    /// 纯色填充
+    Fill,
+}
+
+impl FromStr for RedactKind {
+    type Err = CaptureError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name.to_ascii_lowercase().as_str() {
+            "pixelate" | "mosaic" => Ok(RedactKind::Pixelate),
+            "blur" => Ok(RedactKind::Blur),
+            "fill" => Ok(RedactKind::Fill),
+            _ => Err(CaptureError::InvalidArgument(format!("unsupported redaction '{name}'"))),
+        }
+    }
+}
+
+/// 一个打码区域 (桌面物理坐标)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Redaction {
+    pub kind: RedactKind,
+    pub rect: DesktopRect,
+    /// 马赛克方块的边长 / 模糊的半径 (像素), 填充时忽略
+    pub strength: u32,
+    /// 填充的颜色 rgb (不透明), 其他方式忽略
+    pub color: [u8; 3],
+}
+
+/// `rgba` 中桌面上 (x, y) 处像素的起始下标 (`rgba` 对应桌面上的 `rect` 区域)
+fn index(rect: DesktopRect, x: i32, y: i32) -> usize {
+    (((y - rect.y) as u32 * rect.width + (x - rect.x) as u32) * 4) as usize
+}
+
+/// 以 `size` 为边长, 对齐到 `start` 的各区间与 `from..to` 的交集
+fn blocks(start: i32, size: i32, from: i32, to: i32) -> impl Iterator<Item=(i32, i32)> {
+    let first = start + (from - start).div_euclid(size) * size;
+
+    (first..to)
+        .step_by(size as usize)
+        .map(move |block| (block.max(from), (block + size).min(to)))
+}
+
+/// 归一化的一维高斯核 (长度为 `2 * radius + 1`)
+fn gaussian_kernel(radius: u32) -> Vec<f32> {
+    let sigma = (radius as f32 / 2.0).max(0.5);
+    let kernel: Vec<f32> = (-(radius as i32)..=radius as i32)
+        .map(|d| (-(d * d) as f32 / (2.0 * sigma * sigma)).exp())
+        .collect();
+    let sum: f32 = kernel.iter().sum();
+
+    kernel.into_iter().map(|weight| weight / sum).collect()
+}
+
+/// 对 `w` x `h` 的像素沿水平 / 垂直方向卷积, 超出边界时取边界上的像素
+fn convolve(src: &[[f32; 4]], kernel: &[f32], w: usize, h: usize, horizontal: bool) -> Vec<[f32; 4]> {
+    let radius = (kernel.len() / 2) as i32;
+    let mut dst = vec![[0.0; 4]; w * h];
+
+    for y in 0..h {
+        for x in 0..w {
+            let mut sum = [0.0; 4];
+            for (i, weight) in kernel.iter().enumerate() {
+                let d = i as i32 - radius;
+                let (sx, sy) = match horizontal {
+                    true => ((x as i32 + d).clamp(0, w as i32 - 1) as usize, y),
+                    false => (x, (y as i32 + d).clamp(0, h as i32 - 1) as usize),
+                };
+                for (channel, value) in sum.iter_mut().zip(src[sy * w + sx]) {
+                    *channel += value * weight;
+                }
+            }
+            dst[y * w + x] = sum;
+        }
+    }
+
+    dst
+}
+
+impl Redaction {
+    /// 对 `part` (已约束在 `rect` 内) 中的像素打码
+    fn apply(&self, rgba: &mut [u8], rect: DesktopRect, part: DesktopRect) {
+        let (x2, y2) = (part.x + part.width as i32, part.y + part.height as i32);
+
+        match self.kind {
+            RedactKind::Fill => {
+                let [r, g, b] = self.color;
+                for y in part.y..y2 {
+                    for x in part.x..x2 {
+                        let i = index(rect, x, y);
+                        rgba[i..i + 4].copy_from_slice(&[r, g, b, 255]);
+                    }
+                }
+            }
+            // 方块对齐到打码区域的左上角, 与 `rect` 无关
+            RedactKind::Pixelate => {
+                let size = self.strength.max(MIN_STRENGTH) as i32;
+                for (by1, by2) in blocks(self.rect.y, size, part.y, y2) {
+                    for (bx1, bx2) in blocks(self.rect.x, size, part.x, x2) {
+                        let mut sum = [0u32; 4];
+                        for y in by1..by2 {
+                            for x in bx1..bx2 {
+                                let i = index(rect, x, y);
+                                for (channel, value) in sum.iter_mut().zip(&rgba[i..i + 4]) {
+                                    *channel += *value as u32;
+                                }
+                            }
+                        }
+
+                        let count = ((bx2 - bx1) * (by2 - by1)) as u32;
+                        let average = sum.map(|channel| ((channel + count / 2) / count) as u8);
+                        for y in by1..by2 {
+                            for x in bx1..bx2 {
+                                let i = index(rect, x, y);
+                                rgba[i..i + 4].copy_from_slice(&average);
+                            }
+                        }
+                    }
+                }
+            }
+            // 只使用区域内的像素, 区域外的内容不会混入
+            RedactKind::Blur => {
+                let (w, h) = (part.width as usize, part.height as usize);
+                let mut pixels = Vec::with_capacity(w * h);
+                for y in part.y..y2 {
+                    for x in part.x..x2 {
+                        let i = index(rect, x, y);
+                        pixels.push([rgba[i], rgba[i + 1], rgba[i + 2], rgba[i + 3]].map(|value| value as f32));
+                    }
+                }
+
+                let kernel = gaussian_kernel(self.strength.max(MIN_STRENGTH));
+                let blurred = convolve(&convolve(&pixels, &kernel, w, h, true), &kernel, w, h, false);
+                for (n, pixel) in blurred.into_iter().enumerate() {
+                    let i = index(rect, part.x + (n % w) as i32, part.y + (n / w) as i32);
+                    rgba[i..i + 4].copy_from_slice(&pixel.map(|value| value.round().clamp(0.0, 255.0) as u8));
+                }
+            }
+        }
+    }
+}
+
+/// 按顺序对 rgba 阵列 (行优先, 对应桌面上的 `rect` 区域) 打码, 直接覆盖原有像素
+///
+/// 每个打码区域只作用于与 `rect` 相交的部分, 结果只取决于相交部分的像素
+pub fn apply_redactions(rgba: &mut [u8], rect: DesktopRect, redactions: &[Redaction]) {
+    for redaction in redactions {
+        if let Some(part) = redaction.rect.intersect(&rect) {
+            redaction.apply(rgba, rect, part);
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use crate::declares::RedactRegion;
+    use crate::geometry::DesktopRect;
+    use crate::utils::redact::{apply_redactions, RedactKind, Redaction, MIN_STRENGTH};
+
+    /// 10x4 的渐变: (x * 20, y * 60, 0)
+    fn gradient(rect: DesktopRect) -> Vec<u8> {
+        (0..rect.height)
+            .flat_map(|y| (0..rect.width).flat_map(move |x| [(x * 20) as u8, (y * 60) as u8, 0, 255]))
+            .collect()
+    }
+
+    fn pixel(rgba: &[u8], rect: DesktopRect, x: i32, y: i32) -> [u8; 4] {
+        let i = (((y - rect.y) as u32 * rect.width + (x - rect.x) as u32) * 4) as usize;
+        rgba[i..i + 4].try_into().unwrap()
+    }
+
+    fn redaction(kind: RedactKind, xywh: (i32, i32, u32, u32), strength: u32) -> Redaction {
+        let (x, y, w, h) = xywh;
+        Redaction { kind, rect: DesktopRect::new(x, y, w, h), strength, color: [0, 0, 0] }
+    }
+
+    #[test]
+    fn fill_and_pixelate() {
+        let rect = DesktopRect::new(100, 50, 10, 4);
+        let mut rgba = gradient(rect);
+        apply_redactions(&mut rgba, rect, &[redaction(RedactKind::Fill, (98, 49, 4, 2), 0)]);
+
+        // 只覆盖与 `rect` 相交的部分
+        assert_eq!((pixel(&rgba, rect, 100, 50), pixel(&rgba, rect, 101, 50)), ([0, 0, 0, 255], [0, 0, 0, 255]));
+        assert_eq!((pixel(&rgba, rect, 102, 50), pixel(&rgba, rect, 100, 51)), ([40, 0, 0, 255], [0, 60, 0, 255]));
+
+        // 4x4 的方块取平均色, 不足一个方块的部分单独取平均
+        let mut rgba = gradient(rect);
+        apply_redactions(&mut rgba, rect, &[redaction(RedactKind::Pixelate, (104, 50, 5, 4), 4)]);
+        assert_eq!(pixel(&rgba, rect, 104, 50), [110, 90, 0, 255]);
+        assert_eq!(pixel(&rgba, rect, 107, 53), [110, 90, 0, 255]);
+        assert_eq!(pixel(&rgba, rect, 108, 51), [160, 90, 0, 255]);
+        assert_eq!(pixel(&rgba, rect, 109, 52), [180, 120, 0, 255]);
+
+        // 过小的方块按最小边长处理
+        let mut weak = gradient(rect);
+        apply_redactions(&mut weak, rect, &[redaction(RedactKind::Pixelate, (104, 50, 5, 4), 1)]);
+        assert_eq!(weak, rgba);
+    }
+
+    #[test]
+    fn blur_inside_region() {
+        let rect = DesktopRect::new(0, 0, 10, 4);
+        let mut rgba = gradient(rect);
+        apply_redactions(&mut rgba, rect, &[redaction(RedactKind::Blur, (2, 0, 6, 4), MIN_STRENGTH)]);
+
+        // 区域外不变, 区域内变得平滑 (红色通道仍单调递增)
+        assert_eq!((pixel(&rgba, rect, 1, 0), pixel(&rgba, rect, 8, 3)), ([20, 0, 0, 255], [160, 180, 0, 255]));
+        let row: Vec<u8> = (2..8).map(|x| pixel(&rgba, rect, x, 1)[0]).collect();
+        assert!(row.windows(2).all(|pair| pair[0] <= pair[1]), "{row:?}");
+        assert!(row[0] > 40 && row[5] < 140, "{row:?}");
+    }
+
+    #[test]
+    fn same_result_within_larger_buffer() {
+        // 在整张图上打码与只在打码区域附近打码 (预览) 的结果一致
+        let full = DesktopRect::new(0, 0, 10, 4);
+        let part = DesktopRect::new(1, 0, 8, 4);
+        let redactions = [redaction(RedactKind::Pixelate, (3, 1, 5, 3), 4), redaction(RedactKind::Blur, (2, 0, 4, 4), 4)];
+
+        let mut rgba = gradient(full);
+        apply_redactions(&mut rgba, full, &redactions);
+        let mut cropped = crate::utils::crop_rgba(&gradient(full), full.width, full.height, (1, 0, 8, 4));
+        apply_redactions(&mut cropped, part, &redactions);
+
+        for y in 0..4 {
+            for x in 1..9 {
+                assert_eq!(pixel(&rgba, full, x, y), pixel(&cropped, part, x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn region_strength() {
+        let region = |kind: &str, strength: Option<u32>| RedactRegion { kind: String::from(kind), x: 0, y: 0, width: 4, height: 4, strength, color: None };
+
+        // 马赛克 / 模糊的强度过小时仍可辨认原内容, 拒绝; 填充不使用强度
+        assert_eq!(Redaction::try_from(region("pixelate", None)).map(|redaction| redaction.strength), Ok(12));
+        assert_eq!(Redaction::try_from(region("blur", Some(4))).map(|redaction| redaction.strength), Ok(4));
+        assert_eq!(Redaction::try_from(region("fill", Some(0))).map(|redaction| redaction.kind), Ok(RedactKind::Fill));
+        for (kind, strength) in [("pixelate", 3), ("blur", 1), ("mosaic", 0)] {
+            assert_eq!(Redaction::try_from(region(kind, Some(strength))).unwrap_err().code(), "INVALID_ARGUMENT");
+        }
+        assert_eq!(Redaction::try_from(region("swirl", None)).unwrap_err().code(), "INVALID_ARGUMENT");
+    }
+}
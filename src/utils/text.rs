@@ -0,0 +1,222 @@
+use std::env;
+use std::fs;
+use std::sync::OnceLock;
+use ab_glyph::{point, Font, FontArc, FontVec, Glyph, PxScale, ScaleFont};
+use crate::geometry::{DesktopPoint, DesktopRect};
+use crate::utils::blend_pixel;
+
+/// 指定文字标注字体的环境变量, 优先于系统字体 (ttc 使用其中的第一个字体)
+pub const FONT_ENV: &str = "SCREEN_CAPTURE_FONT";
+
+/// 系统中常见的 CJK 字体: (路径, ttc 中的序号)
+#[cfg(target_os = "windows")]
+const SYSTEM_FONTS: &[(&str, u32)] = &[
+    (r"C:\Windows\Fonts\msyh.ttc", 0),
+    (r"C:\Windows\Fonts\simhei.ttf", 0),
+    (r"C:\Windows\Fonts\simsun.ttc", 0),
+];
+
+/// 系统中常见的 CJK 字体: (路径, ttc 中的序号)
+#[cfg(target_os = "macos")]
+const SYSTEM_FONTS: &[(&str, u32)] = &[
+    ("/System/Library/Fonts/PingFang.ttc", 0),
+    ("/System/Library/Fonts/STHeiti Medium.ttc", 0),
+    ("/Library/Fonts/Arial Unicode.ttf", 0),
+];
+
+/// 系统中常见的 CJK 字体: (路径, ttc 中的序号), Noto Sans CJK 中序号 2 为简体中文
+#[cfg(not(any(target_os = "windows", target_os = "macos")))]
+const SYSTEM_FONTS: &[(&str, u32)] = &[
+    ("/usr/share/fonts/opentype/noto/NotoSansCJK-Regular.ttc", 2),
+    ("/usr/share/fonts/noto-cjk/NotoSansCJK-Regular.ttc", 2),
+    ("/usr/share/fonts/google-noto-cjk/NotoSansCJK-Regular.ttc", 2),
+    ("/usr/share/fonts/truetype/wqy/wqy-microhei.ttc", 0),
+    ("/usr/share/fonts/wenquanyi/wqy-microhei/wqy-microhei.ttc", 0),
+    ("/usr/share/fonts/truetype/droid/DroidSansFallbackFull.ttf", 0),
+];
+
+/// 用于检查字体是否包含 CJK 字符
+const CJK_PROBE: char = '截';
+
+/// 文字框的内边距 (相对字号)
+const TEXT_PADDING: f32 = 0.25;
+
+/// 载入字体文件中的第 `index` 个字体
+fn load_font_file(path: &str, index: u32) -> Option<FontArc> {
+    let data = fs::read(path).ok()?;
+    FontVec::try_from_vec_and_index(data, index).ok().map(FontArc::new)
+}
+
+/// 文字标注使用的字体 (首次调用时查找并载入, 之后复用)
+///
+/// 优先使用环境变量 `SCREEN_CAPTURE_FONT` 指定的字体, 否则为系统中第一个包含 CJK 字符的字体, 都没有时为 `None`
+pub fn text_font() -> Option<&'static FontArc> {
+    static FONT: OnceLock<Option<FontArc>> = OnceLock::new();
+
+    FONT.get_or_init(|| {
+        let from_env = env::var(FONT_ENV).ok().and_then(|path| {
+            let font = load_font_file(&path, 0);
+            if font.is_none() {
+                println!("Failed to load font '{path}' ({FONT_ENV})");
+            }
+            font
+        });
+        let font = from_env.or_else(|| {
+            SYSTEM_FONTS
+                .iter()
+                .filter_map(|&(path, index)| load_font_file(path, index))
+                .find(|font| font.glyph_id(CJK_PROBE).0 != 0)
+        });
+
+        if font.is_none() {
+            println!("No CJK font found, text annotations are disabled (set {FONT_ENV} to a font file)");
+        }
+        font
+    }).as_ref()
+}
+
+/// 文字标注 (桌面物理坐标)
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextLabel {
+    pub text: String,
+    /// 文字框的左上角
+    pub position: DesktopPoint,
+    /// 字号 (物理像素)
+    pub font_size: f32,
+    /// 文字的颜色 rgba
+    pub color: [u8; 4],
+    /// 文字框的背景色, 为 `None` 时无背景
+    pub background: Option<[u8; 4]>,
+}
+
+/// 光栅化后的文字框 (rgba 未预乘不透明度)
+#[derive(Debug, Clone)]
+pub struct TextBitmap {
+    pub width: u32,
+    pub height: u32,
+    pub rgba: Vec<u8>,
+    /// 文字末尾 (光标) 的位置, 相对文字框的左上角
+    pub caret: [f32; 2],
+}
+
+/// 与文字颜色形成对比的背景色: 浅色文字为半透明黑色, 否则为半透明白色
+pub fn contrast_background(color: [u8; 4]) -> [u8; 4] {
+    let [r, g, b, _] = color;
+    let luma = (r as u32 * 299 + g as u32 * 587 + b as u32 * 114) / 1000;
+
+    if luma >= 128 { [0x00, 0x00, 0x00, 0xb0] } else { [0xff, 0xff, 0xff, 0xd0] }
+}
+
+impl TextLabel {
+    /// 单行排版: (各字形, 文字框的宽高, 光标的位置)
+    fn layout(&self, font: &FontArc) -> (Vec<Glyph>, [u32; 2], [f32; 2]) {
+        let scaled = font.as_scaled(PxScale::from(self.font_size));
+        let padding = (self.font_size * TEXT_PADDING).round();
+        let baseline = padding + scaled.ascent();
+
+        let mut glyphs = vec![];
+        let mut x = padding;
+        for ch in self.text.chars() {
+            let id = scaled.glyph_id(ch);
+            if let Some(previous) = glyphs.last().map(|glyph: &Glyph| glyph.id) {
+                x += scaled.kern(previous, id);
+            }
+            glyphs.push(id.with_scale_and_position(scaled.scale(), point(x, baseline)));
+            x += scaled.h_advance(id);
+        }
+
+        let line_height = scaled.ascent() - scaled.descent();
+        let size = [(x + padding).ceil() as u32, (line_height + padding * 2.0).ceil() as u32];
+        (glyphs, size, [x, padding])
+    }
+
+    /// 文字框的区域 (桌面物理坐标)
+    pub fn bounds(&self, font: &FontArc) -> DesktopRect {
+        let (_, [width, height], _) = self.layout(font);
+        DesktopRect::new(self.position.x, self.position.y, width, height)
+    }
+
+    /// 光栅化文字框: 背景 + 按覆盖率混合的文字
+    pub fn render(&self, font: &FontArc) -> TextBitmap {
+        let (glyphs, [width, height], caret) = self.layout(font);
+        let mut rgba = self.background.unwrap_or([0; 4]).repeat((width * height) as usize);
+
+        for glyph in glyphs {
+            let Some(outlined) = font.outline_glyph(glyph) else { continue };
+            let bounds = outlined.px_bounds();
+
+            outlined.draw(|x, y, coverage| {
+                let (x, y) = (bounds.min.x as i32 + x as i32, bounds.min.y as i32 + y as i32);
+                if (0..width as i32).contains(&x) && (0..height as i32).contains(&y) {
+                    let i = ((y as u32 * width + x as u32) * 4) as usize;
+                    blend_pixel(&mut rgba[i..i + 4], self.color, coverage);
+                }
+            });
+        }
+
+        TextBitmap { width, height, rgba, caret }
+    }
+}
+
+/// 将文字标注绘制到 rgba 阵列 (行优先, 对应桌面上的 `rect` 区域) 中
+pub fn draw_text_label(rgba: &mut [u8], rect: DesktopRect, label: &TextLabel, font: &FontArc) {
+    let bitmap = label.render(font);
+    let bounds = DesktopRect::new(label.position.x, label.position.y, bitmap.width, bitmap.height);
+    let Some(part) = bounds.intersect(&rect) else { return };
+
+    for y in part.y..part.y + part.height as i32 {
+        for x in part.x..part.x + part.width as i32 {
+            let src = (((y - bounds.y) as u32 * bitmap.width + (x - bounds.x) as u32) * 4) as usize;
+            let dst = (((y - rect.y) as u32 * rect.width + (x - rect.x) as u32) * 4) as usize;
+            let color = [bitmap.rgba[src], bitmap.rgba[src + 1], bitmap.rgba[src + 2], bitmap.rgba[src + 3]];
+            blend_pixel(&mut rgba[dst..dst + 4], color, 1.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use crate::geometry::{DesktopPoint, DesktopRect};
+    use crate::utils::text::{contrast_background, draw_text_label, text_font, TextLabel};
+
+    #[test]
+    fn background_contrasts_with_text() {
+        assert_eq!(contrast_background([0xff, 0xff, 0xff, 0xff])[..3], [0, 0, 0]);
+        assert_eq!(contrast_background([0xff, 0x3b, 0x30, 0xff])[..3], [0xff, 0xff, 0xff]);
+    }
+
+    /// 需要 CJK 字体, 如: `sudo apt-get install fonts-noto-cjk`
+    #[test]
+    #[ignore]
+    fn render_cjk_label() {
+        let font = text_font().expect("no CJK font");
+        let label = TextLabel {
+            text: String::from("截图 Ab"),
+            position: DesktopPoint::new(105, 52),
+            font_size: 24.0,
+            color: [255, 0, 0, 255],
+            background: Some([0, 0, 255, 255]),
+        };
+        let bitmap = label.render(font);
+        let bounds = label.bounds(font);
+        assert_eq!((bounds.x, bounds.y, bounds.width, bounds.height), (105, 52, bitmap.width, bitmap.height));
+
+        // CJK 字形有笔画 (有红色像素), 且文字框内都被背景覆盖
+        let red = bitmap.rgba.chunks(4).filter(|pixel| pixel[0] > 200 && pixel[2] < 50).count();
+        assert!(red > 50, "only {red} pixels of text");
+        assert!(bitmap.rgba.chunks(4).all(|pixel| pixel[3] == 255));
+        // 两个 CJK 字符及空格的宽度至少为 2 个字号
+        assert!(bitmap.caret[0] > 48.0 && bitmap.caret[0] < bitmap.width as f32);
+
+        // 绘制到选区中的部分与文字框图像相同
+        let rect = DesktopRect::new(100, 50, 200, 60);
+        let mut rgba = [255u8, 255, 255, 255].repeat((rect.width * rect.height) as usize);
+        draw_text_label(&mut rgba, rect, &label, font);
+        for y in 0..bitmap.height {
+            let dst = (((y + 2) * rect.width + 5) * 4) as usize;
+            let src = (y * bitmap.width * 4) as usize;
+            assert_eq!(&rgba[dst..dst + (bitmap.width * 4) as usize], &bitmap.rgba[src..src + (bitmap.width * 4) as usize]);
+        }
+        assert_eq!(&rgba[..4], &[255, 255, 255, 255]);
+    }
+}
@@ -0,0 +1,491 @@
+use crate::declares::WindowInfo;
+use crate::error::CaptureError;
+use crate::geometry::DesktopRect;
+
+/// 获取所有顶层窗口 (由上到下), 区域包含窗口管理器的边框
+///
+/// 优先读取窗口管理器维护的 `_NET_CLIENT_LIST_STACKING` (含最小化等不可见的窗口),
+/// 没有窗口管理器时 (如 Xvfb) 退回根窗口的可见子窗口
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))]
+pub fn list_windows() -> Result<Vec<WindowInfo>, CaptureError> {
+    use std::ptr;
+    use x11_dl::xlib;
+
+    let xlib = xlib::Xlib::open().map_err(|err| CaptureError::WindowList(err.to_string()))?;
+
+    // SAFETY: display 在本函数内打开和关闭; 期间忽略 X 错误 (窗口可能在查询过程中被销毁), 结束后恢复原处理函数
+    unsafe {
+        let display = (xlib.XOpenDisplay)(ptr::null());
+        if display.is_null() {
+            return Err(CaptureError::WindowList(String::from("cannot open X display")));
+        }
+
+        let previous_handler = (xlib.XSetErrorHandler)(Some(x11::ignore_error));
+        let windows = x11::list_windows(&xlib, display);
+        (xlib.XSync)(display, xlib::False);
+        (xlib.XSetErrorHandler)(previous_handler);
+        (xlib.XCloseDisplay)(display);
+
+        Ok(windows)
+    }
+}
+
+/// 直接读取窗口内容 (不受遮挡影响), 返回窗口内容区域 (桌面物理坐标, 不含窗口管理器的边框) 及 rgba 阵列
+///
+/// 合成窗口管理器运行时窗口 (所在的顶层窗口) 已被重定向到离屏的 pixmap, 通过 XComposite 读取该 pixmap;
+/// 没有合成窗口管理器或 libXcomposite 时退回直接读取窗口 (被遮挡的部分内容不确定)
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))]
+pub fn read_window(window_id: u32) -> Result<(DesktopRect, Vec<u8>), CaptureError> {
+    use std::ptr;
+    use x11_dl::xlib;
+
+    let failed = |reason: String| CaptureError::WindowCapture { window_id, reason };
+    let xlib = xlib::Xlib::open().map_err(|err| failed(err.to_string()))?;
+    let composite = x11::Xcomposite::open();
+
+    // SAFETY: 同 `list_windows`
+    unsafe {
+        let display = (xlib.XOpenDisplay)(ptr::null());
+        if display.is_null() {
+            return Err(failed(String::from("cannot open X display")));
+        }
+
+        let previous_handler = (xlib.XSetErrorHandler)(Some(x11::ignore_error));
+        let contents = x11::read_window(&xlib, composite.as_ref(), display, window_id as _);
+        (xlib.XSync)(display, xlib::False);
+        (xlib.XSetErrorHandler)(previous_handler);
+        (xlib.XCloseDisplay)(display);
+
+        contents.map_err(|reason| failed(String::from(reason)))
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd")))]
+pub fn list_windows() -> Result<Vec<WindowInfo>, CaptureError> {
+    Err(CaptureError::WindowList(String::from("only supported on X11")))
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd")))]
+pub fn read_window(window_id: u32) -> Result<(DesktopRect, Vec<u8>), CaptureError> {
+    Err(CaptureError::WindowCapture { window_id, reason: String::from("only supported on X11") })
+}
+
+/// 将 32 位的 ZPixmap 像素转换为 rgba 阵列 (行优先, 不透明)
+///
+/// `masks` 为 \[red, green, blue\] 通道在像素值中的掩码, `lsb_first` 为像素值是否按小端序存储
+#[cfg(any(test, target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))]
+fn pixels_to_rgba(data: &[u8], width: usize, height: usize, bytes_per_line: usize, masks: [u32; 3], lsb_first: bool) -> Vec<u8> {
+    let channel = |pixel: u32, mask: u32| {
+        let max = mask >> mask.trailing_zeros();
+        (((pixel & mask) >> mask.trailing_zeros()) * 255 / max.max(1)) as u8
+    };
+
+    let mut rgba = Vec::with_capacity(width * height * 4);
+    for row in data.chunks(bytes_per_line).take(height) {
+        for bytes in row.chunks_exact(4).take(width) {
+            let bytes = bytes.try_into().unwrap();
+            let pixel = if lsb_first { u32::from_le_bytes(bytes) } else { u32::from_be_bytes(bytes) };
+            rgba.extend(masks.map(|mask| channel(pixel, mask)));
+            rgba.push(255);
+        }
+    }
+
+    rgba
+}
+
+#[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))]
+mod x11 {
+    use std::ffi::{CStr, CString};
+    use std::os::raw::{c_int, c_uchar, c_ulong};
+    use std::{ptr, slice};
+    use libloading::Library;
+    use x11_dl::xlib;
+    use crate::declares::WindowInfo;
+    use crate::geometry::DesktopRect;
+    use crate::window_list::pixels_to_rgba;
+
+    /// 忽略 X 错误, 默认的处理函数会直接结束进程
+    pub unsafe extern "C" fn ignore_error(_display: *mut xlib::Display, _event: *mut xlib::XErrorEvent) -> c_int {
+        0
+    }
+
+    unsafe fn atom(xlib: &xlib::Xlib, display: *mut xlib::Display, name: &str) -> c_ulong {
+        let name = CString::new(name).unwrap();
+        (xlib.XInternAtom)(display, name.as_ptr(), xlib::False)
+    }
+
+    /// 读取窗口属性的原始数据: (格式, 数据), 属性不存在时为 `None`
+    unsafe fn property(xlib: &xlib::Xlib, display: *mut xlib::Display, window: c_ulong, name: &str) -> Option<(c_int, Vec<u8>)> {
+        let (mut actual_type, mut format, mut count, mut remaining) = (0, 0, 0, 0);
+        let mut data: *mut c_uchar = ptr::null_mut();
+
+        let status = (xlib.XGetWindowProperty)(
+            display, window, atom(xlib, display, name),
+            0, i32::MAX as _, xlib::False, xlib::AnyPropertyType as c_ulong,
+            &mut actual_type, &mut format, &mut count, &mut remaining, &mut data,
+        );
+        if status != xlib::Success as c_int || data.is_null() {
+            return None;
+        }
+
+        // 格式为 32 时每项在内存中为 long
+        let item_size = match format {
+            8 => 1,
+            16 => 2,
+            32 => std::mem::size_of::<c_ulong>(),
+            _ => 0,
+        };
+        let bytes = slice::from_raw_parts(data, count as usize * item_size).to_vec();
+        (xlib.XFree)(data as *mut _);
+
+        (actual_type != 0).then_some((format, bytes))
+    }
+
+    /// 格式为 32 的属性 (窗口列表 / 边框宽度等)
+    unsafe fn property_longs(xlib: &xlib::Xlib, display: *mut xlib::Display, window: c_ulong, name: &str) -> Option<Vec<c_ulong>> {
+        match property(xlib, display, window, name)? {
+            (32, bytes) => Some(bytes
+                .chunks_exact(std::mem::size_of::<c_ulong>())
+                .map(|chunk| c_ulong::from_ne_bytes(chunk.try_into().unwrap()))
+                .collect()),
+            _ => None,
+        }
+    }
+
+    /// 窗口标题: 优先 `_NET_WM_NAME` (utf8), 否则 `WM_NAME`
+    unsafe fn title(xlib: &xlib::Xlib, display: *mut xlib::Display, window: c_ulong) -> String {
+        if let Some((8, bytes)) = property(xlib, display, window, "_NET_WM_NAME") {
+            return String::from_utf8_lossy(&bytes).into_owned();
+        }
+
+        let mut name = ptr::null_mut();
+        if (xlib.XFetchName)(display, window, &mut name) != 0 && !name.is_null() {
+            let title = CStr::from_ptr(name).to_string_lossy().into_owned();
+            (xlib.XFree)(name as *mut _);
+            return title;
+        }
+
+        String::new()
+    }
+
+    /// 窗口类名: `WM_CLASS` 为 "instance\0class\0"
+    unsafe fn class(xlib: &xlib::Xlib, display: *mut xlib::Display, window: c_ulong) -> String {
+        match property(xlib, display, window, "WM_CLASS") {
+            Some((8, bytes)) => bytes
+                .split(|byte| *byte == 0)
+                .nth(1)
+                .map(|class| String::from_utf8_lossy(class).into_owned())
+                .unwrap_or_default(),
+            _ => String::new(),
+        }
+    }
+
+    /// 窗口类型: `_NET_WM_WINDOW_TYPE` 的第一项去掉前缀后的小写名称, 如 "normal" / "dock" / "desktop"
+    unsafe fn window_type(xlib: &xlib::Xlib, display: *mut xlib::Display, window: c_ulong) -> String {
+        let Some(&[window_type, ..]) = property_longs(xlib, display, window, "_NET_WM_WINDOW_TYPE").as_deref() else {
+            return String::new();
+        };
+
+        let name = (xlib.XGetAtomName)(display, window_type);
+        if name.is_null() {
+            return String::new();
+        }
+        let window_type = CStr::from_ptr(name).to_string_lossy().trim_start_matches("_NET_WM_WINDOW_TYPE_").to_lowercase();
+        (xlib.XFree)(name as *mut _);
+
+        window_type
+    }
+
+    /// 窗口的信息, 已销毁 (或 `include_hidden` 为 false 时不可见) 时为 `None`
+    unsafe fn window_info(xlib: &xlib::Xlib, display: *mut xlib::Display, root: c_ulong, window: c_ulong, include_hidden: bool) -> Option<WindowInfo> {
+        let mut attributes: xlib::XWindowAttributes = std::mem::zeroed();
+        if (xlib.XGetWindowAttributes)(display, window, &mut attributes) == 0 || attributes.class != xlib::InputOutput {
+            return None;
+        }
+        let visible = attributes.map_state == xlib::IsViewable;
+        if !visible && !include_hidden {
+            return None;
+        }
+
+        // 窗口内容区域左上角在根窗口中的位置
+        let (mut x, mut y, mut child) = (0, 0, 0);
+        if (xlib.XTranslateCoordinates)(display, window, root, 0, 0, &mut x, &mut y, &mut child) == 0 {
+            return None;
+        }
+
+        // 窗口管理器的边框: [left, right, top, bottom]
+        let [left, right, top, bottom] = match property_longs(xlib, display, window, "_NET_FRAME_EXTENTS").as_deref() {
+            Some(&[left, right, top, bottom]) => [left, right, top, bottom].map(|extent| extent as i32),
+            _ => [0; 4],
+        };
+        let width = attributes.width + left + right;
+        let height = attributes.height + top + bottom;
+
+        (width > 0 && height > 0).then(|| WindowInfo {
+            window_id: window as u32,
+            title: title(xlib, display, window),
+            class: class(xlib, display, window),
+            window_type: window_type(xlib, display, window),
+            pid: property_longs(xlib, display, window, "_NET_WM_PID").and_then(|pid| pid.first().map(|pid| *pid as u32)),
+            visible,
+            physical_x: x - left,
+            physical_y: y - top,
+            physical_width: width as u32,
+            physical_height: height as u32,
+        })
+    }
+
+    /// 所有顶层窗口 (由上到下)
+    pub unsafe fn list_windows(xlib: &xlib::Xlib, display: *mut xlib::Display) -> Vec<WindowInfo> {
+        let root = (xlib.XDefaultRootWindow)(display);
+
+        // 由下到上; 根窗口的子窗口中有大量未映射的内部窗口, 只保留可见的
+        let (stacking, include_hidden) = match property_longs(xlib, display, root, "_NET_CLIENT_LIST_STACKING") {
+            Some(windows) => (windows, true),
+            None => {
+                let (mut root_return, mut parent, mut children, mut count) = (0, 0, ptr::null_mut(), 0);
+                if (xlib.XQueryTree)(display, root, &mut root_return, &mut parent, &mut children, &mut count) == 0 || children.is_null() {
+                    return vec![];
+                }
+                let windows = slice::from_raw_parts(children, count as usize).to_vec();
+                (xlib.XFree)(children as *mut _);
+                (windows, false)
+            }
+        };
+
+        stacking
+            .into_iter()
+            .rev()
+            .filter_map(|window| window_info(xlib, display, root, window, include_hidden))
+            .collect()
+    }
+
+    /// 运行时加载的 libXcomposite
+    pub struct Xcomposite {
+        query_extension: unsafe extern "C" fn(*mut xlib::Display, *mut c_int, *mut c_int) -> xlib::Bool,
+        name_window_pixmap: unsafe extern "C" fn(*mut xlib::Display, c_ulong) -> c_ulong,
+        _library: Library,
+    }
+
+    impl Xcomposite {
+        /// 找不到 libXcomposite 时为 `None`
+        pub fn open() -> Option<Xcomposite> {
+            // SAFETY: 函数签名与 Xcomposite.h 一致, 函数指针随 `_library` 一同释放
+            unsafe {
+                let library = Library::new("libXcomposite.so.1").or_else(|_| Library::new("libXcomposite.so")).ok()?;
+
+                Some(Xcomposite {
+                    query_extension: *library.get(b"XCompositeQueryExtension\0").ok()?,
+                    name_window_pixmap: *library.get(b"XCompositeNameWindowPixmap\0").ok()?,
+                    _library: library,
+                })
+            }
+        }
+    }
+
+    /// 是否有合成窗口管理器在运行 (持有默认屏幕的 `_NET_WM_CM_Sn` 选择)
+    unsafe fn has_compositor(xlib: &xlib::Xlib, display: *mut xlib::Display) -> bool {
+        let selection = format!("_NET_WM_CM_S{}", (xlib.XDefaultScreen)(display));
+        (xlib.XGetSelectionOwner)(display, atom(xlib, display, &selection)) != 0
+    }
+
+    /// 窗口所在的顶层窗口 (根窗口的子窗口): 重新设置父窗口的窗口管理器中为其边框窗口, 否则为窗口自身
+    unsafe fn top_level(xlib: &xlib::Xlib, display: *mut xlib::Display, root: c_ulong, window: c_ulong) -> c_ulong {
+        let mut current = window;
+        loop {
+            let (mut root_return, mut parent, mut children, mut count) = (0, 0, ptr::null_mut(), 0);
+            if (xlib.XQueryTree)(display, current, &mut root_return, &mut parent, &mut children, &mut count) == 0 {
+                return current;
+            }
+            if !children.is_null() {
+                (xlib.XFree)(children as *mut _);
+            }
+            if parent == root || parent == 0 {
+                return current;
+            }
+            current = parent;
+        }
+    }
+
+    /// 从合成窗口管理器重定向的顶层窗口的离屏 pixmap 中读取窗口的内容区域
+    ///
+    /// 合成窗口管理器重定向的是顶层窗口 (边框窗口), 对客户窗口取 pixmap 会失败, 因此读取边框窗口并裁剪出内容区域
+    unsafe fn read_composited(
+        xlib: &xlib::Xlib,
+        composite: &Xcomposite,
+        display: *mut xlib::Display,
+        root: c_ulong,
+        window: c_ulong,
+        width: u32,
+        height: u32,
+    ) -> Result<Vec<u8>, &'static str> {
+        let frame = top_level(xlib, display, root, window);
+        let mut attributes: xlib::XWindowAttributes = std::mem::zeroed();
+        let (mut x, mut y, mut child) = (0, 0, 0);
+        if (xlib.XGetWindowAttributes)(display, frame, &mut attributes) == 0
+            || (xlib.XTranslateCoordinates)(display, window, frame, 0, 0, &mut x, &mut y, &mut child) == 0 {
+            return Err("window frame not found");
+        }
+
+        // pixmap 包含顶层窗口的边界
+        let pixmap = (composite.name_window_pixmap)(display, frame);
+        let rgba = get_image(xlib, display, pixmap, x + attributes.border_width, y + attributes.border_width, width, height);
+        (xlib.XFreePixmap)(display, pixmap);
+        rgba
+    }
+
+    /// 读取 drawable 的 (x, y, width, height) 区域
+    unsafe fn get_image(xlib: &xlib::Xlib, display: *mut xlib::Display, drawable: c_ulong, x: i32, y: i32, width: u32, height: u32) -> Result<Vec<u8>, &'static str> {
+        let image = (xlib.XGetImage)(display, drawable, x, y, width, height, (xlib.XAllPlanes)(), xlib::ZPixmap);
+        if image.is_null() {
+            return Err("failed to read window image");
+        }
+
+        let xlib::XImage { data, width, height, bytes_per_line, bits_per_pixel, red_mask, green_mask, blue_mask, byte_order, .. } = *image;
+        let rgba = if bits_per_pixel == 32 && !data.is_null() {
+            let data = slice::from_raw_parts(data as *const u8, (bytes_per_line * height) as usize);
+            let masks = [red_mask, green_mask, blue_mask].map(|mask| mask as u32);
+            Ok(pixels_to_rgba(data, width as usize, height as usize, bytes_per_line as usize, masks, byte_order == xlib::LSBFirst))
+        } else {
+            Err("unsupported pixel format (only 32 bits per pixel is supported)")
+        };
+        (xlib.XDestroyImage)(image);
+
+        rgba
+    }
+
+    /// 读取窗口内容, 返回内容区域 (桌面物理坐标) 及 rgba 阵列
+    pub unsafe fn read_window(
+        xlib: &xlib::Xlib,
+        composite: Option<&Xcomposite>,
+        display: *mut xlib::Display,
+        window: c_ulong,
+    ) -> Result<(DesktopRect, Vec<u8>), &'static str> {
+        let root = (xlib.XDefaultRootWindow)(display);
+        let mut attributes: xlib::XWindowAttributes = std::mem::zeroed();
+        if (xlib.XGetWindowAttributes)(display, window, &mut attributes) == 0 {
+            return Err("window not found");
+        }
+        // 只有映射的窗口才有内容
+        if attributes.map_state != xlib::IsViewable {
+            return Err("window is not visible");
+        }
+
+        let (mut x, mut y, mut child) = (0, 0, 0);
+        (xlib.XTranslateCoordinates)(display, window, root, 0, 0, &mut x, &mut y, &mut child);
+        let (width, height) = (attributes.width as u32, attributes.height as u32);
+        let area = DesktopRect::new(x, y, width, height);
+
+        let (mut event_base, mut error_base) = (0, 0);
+        let rgba = match composite {
+            // 窗口已被合成窗口管理器重定向, 离屏 pixmap 中为完整的窗口内容; 读取失败时退回直接读取窗口
+            Some(composite) if has_compositor(xlib, display) && (composite.query_extension)(display, &mut event_base, &mut error_base) != 0 => {
+                read_composited(xlib, composite, display, root, window, width, height)
+                    .or_else(|_| get_image(xlib, display, window, 0, 0, width, height))
+            }
+            _ => get_image(xlib, display, window, 0, 0, width, height),
+        }?;
+
+        Ok((area, rgba))
+    }
+}
+
+#[cfg(test)]
+mod unit_test {
+    use crate::window_list::pixels_to_rgba;
+
+    #[test]
+    fn convert_zpixmap() {
+        // 2x1 像素, 每行补齐到 12 字节; BGRX (小端序的 0x00RRGGBB)
+        let data = [0x30, 0x20, 0x10, 0x00, 0xff, 0x00, 0x80, 0x00, 0, 0, 0, 0];
+        let masks = [0xff0000, 0x00ff00, 0x0000ff];
+
+        assert_eq!(pixels_to_rgba(&data, 2, 1, 12, masks, true), vec![0x10, 0x20, 0x30, 255, 0x80, 0x00, 0xff, 255]);
+        // 大端序
+        assert_eq!(pixels_to_rgba(&[0x00, 0x10, 0x20, 0x30], 1, 1, 4, masks, false), vec![0x10, 0x20, 0x30, 255]);
+    }
+
+    /// 需要 X11 图形环境, 如: `xvfb-run -a cargo test -- --ignored`
+    ///
+    /// 创建几个虚拟的 X 客户端窗口, 检查位置、标题及层叠顺序
+    #[test]
+    #[ignore]
+    #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))]
+    fn list_dummy_clients() {
+        use std::ffi::CString;
+        use std::ptr;
+        use x11_dl::xlib;
+        use crate::window_list::list_windows;
+
+        let xlib = xlib::Xlib::open().unwrap();
+        // SAFETY: display 在测试结束前保持打开, 窗口随之销毁
+        let (display, ids) = unsafe {
+            let display = (xlib.XOpenDisplay)(ptr::null());
+            assert!(!display.is_null());
+            let root = (xlib.XDefaultRootWindow)(display);
+
+            let ids: Vec<u64> = [("bottom", 10, 20, 200, 100), ("top", 50, 60, 120, 80)]
+                .into_iter()
+                .map(|(name, x, y, w, h)| {
+                    let window = (xlib.XCreateSimpleWindow)(display, root, x, y, w, h, 0, 0, 0);
+                    let name = CString::new(name).unwrap();
+                    (xlib.XStoreName)(display, window, name.as_ptr());
+                    (xlib.XMapRaised)(display, window);
+                    window as u64
+                })
+                .collect();
+            (xlib.XSync)(display, xlib::False);
+            (display, ids)
+        };
+
+        let windows = list_windows().unwrap();
+        let found: Vec<_> = windows
+            .iter()
+            .filter(|window| ids.contains(&(window.window_id as u64)))
+            .map(|window| (window.title.as_str(), window.physical_rect().xywh()))
+            .collect();
+
+        assert_eq!(found, vec![("top", (50, 60, 120, 80)), ("bottom", (10, 20, 200, 100))]);
+
+        unsafe { (xlib.XCloseDisplay)(display) };
+    }
+
+    /// 需要 X11 图形环境及合成窗口管理器, 如: `xvfb-run -a sh -c 'xcompmgr & cargo test -- --ignored'`
+    ///
+    /// 读取被另一个窗口部分遮挡的窗口 (位于模拟的边框窗口中), 被遮挡的部分也应为窗口自身的背景色
+    #[test]
+    #[ignore]
+    #[cfg(any(target_os = "linux", target_os = "dragonfly", target_os = "freebsd", target_os = "netbsd", target_os = "openbsd"))]
+    fn read_obscured_client() {
+        use std::ptr;
+        use x11_dl::xlib;
+        use crate::window_list::read_window;
+
+        let xlib = xlib::Xlib::open().unwrap();
+        // SAFETY: display 在测试结束前保持打开, 窗口随之销毁
+        let (display, bottom) = unsafe {
+            let display = (xlib.XOpenDisplay)(ptr::null());
+            assert!(!display.is_null());
+            let root = (xlib.XDefaultRootWindow)(display);
+
+            // 背景: 红色 (下, 位于绿色的边框窗口内) / 蓝色 (上, 遮挡左上角)
+            let frame = (xlib.XCreateSimpleWindow)(display, root, 90, 80, 80, 70, 0, 0, 0x00ff00);
+            let bottom = (xlib.XCreateSimpleWindow)(display, frame, 10, 20, 60, 40, 0, 0, 0xff0000);
+            let top = (xlib.XCreateSimpleWindow)(display, root, 80, 80, 50, 40, 0, 0, 0x0000ff);
+            (xlib.XMapWindow)(display, bottom);
+            (xlib.XMapRaised)(display, frame);
+            (xlib.XMapRaised)(display, top);
+            (xlib.XSync)(display, xlib::False);
+            (display, bottom)
+        };
+
+        let (area, rgba) = read_window(bottom as u32).unwrap();
+        assert_eq!(area.xywh(), (100, 100, 60, 40));
+        assert_eq!(rgba.len(), 60 * 40 * 4);
+        // 被遮挡的左上角及未遮挡的右下角
+        assert_eq!(&rgba[0..4], &[255, 0, 0, 255]);
+        assert_eq!(&rgba[rgba.len() - 4..], &[255, 0, 0, 255]);
+
+        unsafe { (xlib.XCloseDisplay)(display) };
+    }
+}